@@ -0,0 +1,61 @@
+//! Serialization over the readers and writers of `futures`.
+
+use std::future::poll_fn;
+use std::pin::Pin;
+
+use futures_io::{AsyncRead, AsyncWrite};
+
+use crate::{AsyncDeserialize, AsyncSerialize, AsyncSink, AsyncSource, DecodeLimits};
+
+/// Adapts a `futures` reader or writer to [`AsyncSource`] or [`AsyncSink`].
+pub struct Compat<T>(pub T);
+
+impl<R> AsyncSource for Compat<R>
+where
+    R: AsyncRead + Unpin + Send,
+{
+    async fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        poll_fn(|cx| Pin::new(&mut self.0).poll_read(cx, buf)).await
+    }
+}
+
+impl<W> AsyncSink for Compat<W>
+where
+    W: AsyncWrite + Unpin + Send,
+{
+    async fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        poll_fn(|cx| Pin::new(&mut self.0).poll_write(cx, buf)).await
+    }
+
+    async fn flush(&mut self) -> std::io::Result<()> {
+        poll_fn(|cx| Pin::new(&mut self.0).poll_flush(cx)).await
+    }
+}
+
+/// Reads a `T` from `reader`.
+pub async fn deserialize<T, R>(reader: &mut R) -> crate::Result<T>
+where
+    T: AsyncDeserialize,
+    R: AsyncRead + Unpin + Send,
+{
+    T::deserialize_async(&mut Compat(reader)).await
+}
+
+/// Reads a `T` from `reader` within `limits`, see
+/// [`deserialize_with_limits`](crate::deserialize_with_limits).
+pub async fn deserialize_with_limits<T, R>(reader: &mut R, limits: DecodeLimits) -> crate::Result<T>
+where
+    T: AsyncDeserialize,
+    R: AsyncRead + Unpin + Send,
+{
+    crate::deserialize_with_limits_async(&mut Compat(reader), limits).await
+}
+
+/// Writes `value` to `writer` and returns the number of bytes written.
+pub async fn serialize<T, W>(value: &T, writer: &mut W) -> crate::Result<usize>
+where
+    T: AsyncSerialize,
+    W: AsyncWrite + Unpin + Send,
+{
+    value.serialize_async(&mut Compat(writer)).await
+}
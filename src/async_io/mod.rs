@@ -0,0 +1,914 @@
+//! Asynchronous counterparts of [`Serialize`] and [`Deserialize`].
+//!
+//! The traits are written against [`AsyncSource`] and [`AsyncSink`], which
+//! the [`tokio`] and [`futures`] modules, exported as `tlv::async_tokio` and
+//! `tlv::async_futures`, implement for the readers and writers of their
+//! runtime. Records are still decoded and encoded synchronously, only one
+//! record at a time is held in memory, and within the
+//! [default limits](crate::DecodeLimits::DEFAULT) unless others are given to
+//! [`deserialize_with_limits_async`].
+
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+
+use crate::deser::limits::capacity;
+use crate::varint::{self, BigSize, Leb128, QuicVarInt, ZigZag};
+use crate::{
+    Config, DecodeLimits, Deserialize, Error, ErrorKind, Extracted, IntEncoding, Layout, Limiter,
+    RawField, Serialize,
+};
+
+#[cfg(feature = "futures")]
+pub mod futures;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+
+/// Source of bytes read asynchronously.
+pub trait AsyncSource: Send {
+    /// Reads some bytes into `buf` and returns how many, `0` at the end of
+    /// the input.
+    fn read(&mut self, buf: &mut [u8]) -> impl Future<Output = std::io::Result<usize>> + Send;
+
+    /// Fills `buf`, failing with [`ErrorKind::Truncated`] if the input ends
+    /// before.
+    fn read_exact(&mut self, buf: &mut [u8]) -> impl Future<Output = crate::Result<()>> + Send {
+        async move {
+            let mut filled = 0;
+            while filled < buf.len() {
+                match self.read(&mut buf[filled..]).await {
+                    Ok(0) => return Err(ErrorKind::Truncated.into()),
+                    Ok(read) => filled += read,
+                    Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Sink bytes are written to asynchronously.
+pub trait AsyncSink: Send {
+    /// Writes some bytes of `buf` and returns how many.
+    fn write(&mut self, buf: &[u8]) -> impl Future<Output = std::io::Result<usize>> + Send;
+
+    fn flush(&mut self) -> impl Future<Output = std::io::Result<()>> + Send;
+
+    /// Writes the whole of `buf`.
+    fn write_all(&mut self, buf: &[u8]) -> impl Future<Output = crate::Result<()>> + Send {
+        async move {
+            let mut written = 0;
+            while written < buf.len() {
+                match self.write(&buf[written..]).await {
+                    Ok(0) => return Err(std::io::Error::from(std::io::ErrorKind::WriteZero).into()),
+                    Ok(count) => written += count,
+                    Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Decodes a value from an [`AsyncSource`].
+///
+/// Like [`Deserialize`], types whose decoding allocates or recurses
+/// implement [`deserialize_async_limited`](Self::deserialize_async_limited)
+/// and have `deserialize_async` call [`deserialize_with_limits_async`] with
+/// the [default limits](crate::DecodeLimits::DEFAULT).
+pub trait AsyncDeserialize: Deserialize + Send + Sized {
+    fn deserialize_async<R>(reader: &mut R) -> impl Future<Output = crate::Result<Self>> + Send
+    where
+        R: AsyncSource + ?Sized;
+
+    /// Decodes a value nested in another one, see
+    /// [`Deserialize::deserialize_limited`]. The default implementation
+    /// ignores `limiter`.
+    fn deserialize_async_limited<R>(
+        reader: &mut R,
+        _limiter: &mut Limiter,
+    ) -> impl Future<Output = crate::Result<Self>> + Send
+    where
+        R: AsyncSource + ?Sized,
+    {
+        Self::deserialize_async(reader)
+    }
+}
+
+/// Encodes a value into an [`AsyncSink`].
+///
+/// The default implementation encodes the whole value in memory and writes
+/// it at once, the derived one writes one record at a time.
+pub trait AsyncSerialize: Serialize + Sync {
+    fn serialize_async<W>(
+        &self,
+        writer: &mut W,
+    ) -> impl Future<Output = crate::Result<usize>> + Send
+    where
+        W: AsyncSink + ?Sized,
+    {
+        async move {
+            let mut buf = Vec::with_capacity(self.serialized_length());
+            self.serialize(&mut buf)?;
+            writer.write_all(&buf).await?;
+            Ok(buf.len())
+        }
+    }
+}
+
+/// Decodes a `T` from `reader` within `limits`, see
+/// [`deserialize_with_limits`](crate::deserialize_with_limits).
+pub async fn deserialize_with_limits_async<T, R>(
+    reader: &mut R,
+    limits: DecodeLimits,
+) -> crate::Result<T>
+where
+    T: AsyncDeserialize,
+    R: AsyncSource + ?Sized,
+{
+    deserialize_with_config_async(reader, &Config::DEFAULT.with_limits(limits)).await
+}
+
+/// Decodes a `T` from `reader` as `config` asks, see
+/// [`deserialize_with_config`](crate::deserialize_with_config).
+pub async fn deserialize_with_config_async<T, R>(
+    reader: &mut R,
+    config: &Config,
+) -> crate::Result<T>
+where
+    T: AsyncDeserialize,
+    R: AsyncSource + ?Sized,
+{
+    let max_total_bytes = config.limits.max_total_bytes;
+    let mut budget = Budget {
+        inner: reader,
+        remaining: max_total_bytes,
+        exceeded: false,
+    };
+
+    let decoded =
+        T::deserialize_async_limited(&mut budget, &mut Limiter::from_config(config)).await;
+    match budget.exceeded {
+        true => Err(Error::new(ErrorKind::LimitExceeded).with_offset(max_total_bytes)),
+        false => decoded,
+    }
+}
+
+/// Source ending after a number of bytes, the asynchronous counterpart of
+/// the reader [`deserialize_with_config`](crate::deserialize_with_config)
+/// wraps its input in.
+struct Budget<'a, R: ?Sized> {
+    inner: &'a mut R,
+    remaining: u64,
+    exceeded: bool,
+}
+
+impl<R> AsyncSource for Budget<'_, R>
+where
+    R: AsyncSource + ?Sized,
+{
+    async fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.remaining == 0 {
+            let mut probe = [0; 1];
+            self.exceeded |= self.inner.read(&mut probe).await? != 0;
+            return Ok(0);
+        }
+
+        let len = buf
+            .len()
+            .min(self.remaining.try_into().unwrap_or(usize::MAX));
+        let read = self.inner.read(&mut buf[..len]).await?;
+        self.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+/// Reads a tag and a length, or returns `None` if `eof` is allowed and the
+/// input ends before the tag. `offset` is advanced past the header.
+async fn read_header<R>(
+    reader: &mut R,
+    layout: &Layout,
+    offset: &mut u64,
+    eof: bool,
+) -> crate::Result<Option<(u64, usize)>>
+where
+    R: AsyncSource + ?Sized,
+{
+    let start = *offset;
+    let mut tag = [0; varint::MAX_SIZE];
+    let mut read = 0;
+
+    if eof {
+        read = loop {
+            match reader.read(&mut tag[..1]).await {
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+                read => break read.map_err(|err| Error::from(err).with_offset(start))?,
+            }
+        };
+        if read == 0 {
+            return Ok(None);
+        }
+    }
+    let tag_size = read_int(reader, layout.tag, &mut tag, read)
+        .await
+        .map_err(|err| err.with_offset(start))?;
+    let tag = layout
+        .read_tag(&mut &tag[..tag_size])
+        .map_err(|err| err.with_offset(start))?;
+
+    let mut length = [0; varint::MAX_SIZE];
+    let length_size = read_int(reader, layout.length, &mut length, 0)
+        .await
+        .map_err(|err| err.with_tag(tag).with_offset(start))?;
+    let length = layout
+        .read_length(&mut &length[..length_size])
+        .map_err(|err| err.with_tag(tag).with_offset(start))?;
+
+    *offset += (tag_size + length_size) as u64;
+    Ok(Some((tag, length)))
+}
+
+/// Reads the bytes of an integer of `encoding` into `buf` past the `len`
+/// already there, and returns the number of bytes it has in total.
+async fn read_int<R>(
+    reader: &mut R,
+    encoding: IntEncoding,
+    buf: &mut [u8; varint::MAX_SIZE],
+    mut len: usize,
+) -> crate::Result<usize>
+where
+    R: AsyncSource + ?Sized,
+{
+    loop {
+        match varint::remaining(encoding, &buf[..len]) {
+            0 => return Ok(len),
+            more => {
+                reader.read_exact(&mut buf[len..len + more]).await?;
+                len += more;
+            }
+        }
+    }
+}
+
+/// Reads `len` bytes in chunks, so that memory grows with the bytes
+/// actually read rather than with the length announced, once `len` is
+/// checked against the limits of `limiter`.
+async fn read_bytes<R>(reader: &mut R, len: usize, limiter: &Limiter) -> crate::Result<Vec<u8>>
+where
+    R: AsyncSource + ?Sized,
+{
+    const CHUNK: usize = 64 * 1024;
+
+    limiter.check_length(len)?;
+
+    let mut buf = Vec::with_capacity(len.min(CHUNK));
+    while buf.len() < len {
+        let start = buf.len();
+        let end = len.min(start + start.max(CHUNK));
+        buf.resize(end, 0);
+        reader.read_exact(&mut buf[start..]).await?;
+    }
+    Ok(buf)
+}
+
+/// Reads the value of a record whose header was just read.
+async fn read_value<R>(
+    reader: &mut R,
+    limiter: &Limiter,
+    offset: &mut u64,
+    id: u64,
+    len: usize,
+) -> crate::Result<RawField>
+where
+    R: AsyncSource + ?Sized,
+{
+    let start = *offset;
+    let value = read_bytes(reader, len, limiter)
+        .await
+        .map_err(|err| err.with_tag(id).with_offset(start))?;
+    *offset += len as u64;
+
+    Ok(RawField {
+        offset: start,
+        ..RawField::new(id, len, value)
+    })
+}
+
+/// Reads a single record, see [`extract_record`](crate::extract_record).
+pub async fn extract_record_async<R>(
+    reader: &mut R,
+    layout: &Layout,
+    limiter: &Limiter,
+) -> crate::Result<RawField>
+where
+    R: AsyncSource + ?Sized,
+{
+    let mut offset = 0;
+    let (id, len) = read_header(reader, layout, &mut offset, false)
+        .await?
+        .ok_or(ErrorKind::Truncated)?;
+
+    read_value(reader, limiter, &mut offset, id, len).await
+}
+
+/// Reads one record for each of `tags`, see
+/// [`extract_raw`](crate::extract_raw).
+pub async fn extract_raw_async<R, const LENGTH: usize>(
+    reader: &mut R,
+    layout: &Layout,
+    tags: [u64; LENGTH],
+    limiter: &Limiter,
+) -> crate::Result<BTreeMap<u64, RawField>>
+where
+    R: AsyncSource + ?Sized,
+{
+    let mut offset = 0;
+    let mut gathered = BTreeMap::new();
+
+    for _ in tags {
+        let start = offset;
+        let (id, len) = read_header(reader, layout, &mut offset, false)
+            .await?
+            .ok_or(ErrorKind::Truncated)?;
+
+        if !tags.contains(&id) {
+            return Err(Error::new(ErrorKind::UnexpectedTag)
+                .with_tag(id)
+                .with_offset(start));
+        }
+        if gathered.contains_key(&id) {
+            return Err(Error::new(ErrorKind::DuplicateTag)
+                .with_tag(id)
+                .with_offset(start));
+        }
+
+        gathered.insert(id, read_value(reader, limiter, &mut offset, id, len).await?);
+    }
+
+    Ok(gathered)
+}
+
+/// Reads records until the input ends, see
+/// [`extract_stream`](crate::extract_stream).
+pub async fn extract_stream_async<R>(
+    reader: &mut R,
+    layout: &Layout,
+    tags: &[u64],
+    limiter: &Limiter,
+) -> crate::Result<Extracted>
+where
+    R: AsyncSource + ?Sized,
+{
+    read_stream(reader, layout, limiter, false, |id, _| {
+        Ok(tags.contains(&id))
+    })
+    .await
+}
+
+/// Reads records until the input ends, keeping those with unknown tags, see
+/// [`extract_stream_with_unknown`](crate::extract_stream_with_unknown).
+pub async fn extract_stream_with_unknown_async<R>(
+    reader: &mut R,
+    layout: &Layout,
+    tags: &[u64],
+    limiter: &Limiter,
+) -> crate::Result<Extracted>
+where
+    R: AsyncSource + ?Sized,
+{
+    read_stream(
+        reader,
+        layout,
+        limiter,
+        true,
+        |id, _| Ok(tags.contains(&id)),
+    )
+    .await
+}
+
+/// Reads records until the input ends, failing at the first one with an
+/// unknown tag, see
+/// [`extract_stream_deny_unknown`](crate::extract_stream_deny_unknown).
+pub async fn extract_stream_deny_unknown_async<R>(
+    reader: &mut R,
+    layout: &Layout,
+    tags: &[u64],
+    limiter: &Limiter,
+) -> crate::Result<Extracted>
+where
+    R: AsyncSource + ?Sized,
+{
+    read_stream(reader, layout, limiter, false, |id, start| {
+        crate::deser::deny_unknown(tags.contains(&id), id, start)
+    })
+    .await
+}
+
+/// Reads a BOLT #1 TLV stream until the input ends, see
+/// [`extract_bolt`](crate::extract_bolt).
+pub async fn extract_bolt_async<R>(
+    reader: &mut R,
+    layout: &Layout,
+    tags: &[u64],
+    limiter: &Limiter,
+) -> crate::Result<Extracted>
+where
+    R: AsyncSource + ?Sized,
+{
+    let mut types = crate::bolt::Types::new(tags);
+    read_stream(reader, layout, limiter, false, |id, start| {
+        types.check(id, start)
+    })
+    .await
+}
+
+/// Reads a BOLT #1 TLV stream until the input ends, keeping the records of
+/// unknown odd types, see
+/// [`extract_bolt_with_unknown`](crate::extract_bolt_with_unknown).
+pub async fn extract_bolt_with_unknown_async<R>(
+    reader: &mut R,
+    layout: &Layout,
+    tags: &[u64],
+    limiter: &Limiter,
+) -> crate::Result<Extracted>
+where
+    R: AsyncSource + ?Sized,
+{
+    let mut types = crate::bolt::Types::new(tags);
+    read_stream(reader, layout, limiter, true, |id, start| {
+        types.check(id, start)
+    })
+    .await
+}
+
+/// Reads a BOLT #1 TLV stream until the input ends, failing at the first
+/// record of an unknown type, see
+/// [`extract_bolt_deny_unknown`](crate::extract_bolt_deny_unknown).
+pub async fn extract_bolt_deny_unknown_async<R>(
+    reader: &mut R,
+    layout: &Layout,
+    tags: &[u64],
+    limiter: &Limiter,
+) -> crate::Result<Extracted>
+where
+    R: AsyncSource + ?Sized,
+{
+    let mut types = crate::bolt::Types::new(tags);
+    read_stream(reader, layout, limiter, false, |id, start| {
+        crate::deser::deny_unknown(types.check(id, start)?, id, start)
+    })
+    .await
+}
+
+/// Reads the records of an ASN.1 DER value until the input ends, see
+/// [`extract_der`](crate::extract_der).
+pub async fn extract_der_async<R>(
+    reader: &mut R,
+    layout: &Layout,
+    limiter: &Limiter,
+) -> crate::Result<crate::der::Records>
+where
+    R: AsyncSource + ?Sized,
+{
+    let mut offset = 0;
+    let mut records = crate::der::Records::new();
+
+    loop {
+        let start = offset;
+        let Some((id, len)) = read_header(reader, layout, &mut offset, true).await? else {
+            break;
+        };
+        crate::der::check_tag(id).map_err(|err| err.with_tag(id).with_offset(start))?;
+
+        let field = read_value(reader, limiter, &mut offset, id, len).await?;
+        crate::der::check_length(id, start, len, field.offset)
+            .map_err(|err| err.with_tag(id).with_offset(start))?;
+        records.push(id, start, field);
+    }
+
+    Ok(records)
+}
+
+/// Reads records until the input ends, reading those for which `known`
+/// returns `true` and skipping the others, or reading them as unknown if
+/// `keep` is set. Values are checked against the limits of `limiter`
+/// before they are read, and so is the number of unknown records kept.
+async fn read_stream<R>(
+    reader: &mut R,
+    layout: &Layout,
+    limiter: &Limiter,
+    keep: bool,
+    mut known: impl FnMut(u64, u64) -> crate::Result<bool> + Send,
+) -> crate::Result<Extracted>
+where
+    R: AsyncSource + ?Sized,
+{
+    let mut offset = 0;
+    let mut extracted = Extracted {
+        fields: BTreeMap::new(),
+        skipped: Vec::new(),
+        unknown: Vec::new(),
+    };
+
+    loop {
+        let start = offset;
+        let Some((id, len)) = read_header(reader, layout, &mut offset, true).await? else {
+            break;
+        };
+
+        let is_known = known(id, start)?;
+        if !is_known && keep {
+            limiter
+                .check_elements(extracted.unknown.len() + 1)
+                .map_err(|err| err.with_tag(id).with_offset(start))?;
+            let field = read_value(reader, limiter, &mut offset, id, len).await?;
+            extracted.unknown.push(field);
+            continue;
+        }
+
+        // unknown records are read in chunks, so a long one is not
+        // buffered
+        if !is_known {
+            let mut chunk = [0; 512];
+            let mut left = len;
+            while left > 0 {
+                let size = left.min(chunk.len());
+                reader
+                    .read_exact(&mut chunk[..size])
+                    .await
+                    .map_err(|err| err.with_tag(id).with_offset(offset))?;
+                left -= size;
+            }
+            offset += len as u64;
+            extracted.skipped.push(id);
+            continue;
+        }
+
+        if extracted.fields.contains_key(&id) {
+            return Err(Error::new(ErrorKind::DuplicateTag)
+                .with_tag(id)
+                .with_offset(start));
+        }
+
+        let field = read_value(reader, limiter, &mut offset, id, len).await?;
+        extracted.fields.insert(id, field);
+    }
+
+    Ok(extracted)
+}
+
+/// Types of a fixed size are read whole and decoded from memory. Types
+/// whose encoding is not their own size give the type they are written as.
+macro_rules! impl_async_fixed {
+    ($($ty:ty),*) => {
+        impl_async_fixed!($($ty as $ty),*);
+    };
+    ($($ty:ty as $wire:ty),*) => {
+        $(
+            impl AsyncDeserialize for $ty {
+                async fn deserialize_async<R>(reader: &mut R) -> crate::Result<Self>
+                where
+                    R: AsyncSource + ?Sized,
+                {
+                    deserialize_with_limits_async(reader, DecodeLimits::DEFAULT).await
+                }
+
+                async fn deserialize_async_limited<R>(
+                    reader: &mut R,
+                    limiter: &mut Limiter,
+                ) -> crate::Result<Self>
+                where
+                    R: AsyncSource + ?Sized,
+                {
+                    let mut buf = [0; std::mem::size_of::<$wire>()];
+                    reader.read_exact(&mut buf).await?;
+                    Self::deserialize_limited(&mut buf.as_slice(), limiter)
+                }
+            }
+
+            impl AsyncSerialize for $ty {}
+        )*
+    };
+}
+
+impl_async_fixed!(bool, char, f32, f64);
+impl_async_fixed!(usize as u64, isize as i64);
+impl_async_fixed!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+/// Variable-length integers are read a byte at a time until complete and
+/// decoded from memory.
+macro_rules! impl_async_varint {
+    ($($ty:ty),*) => {
+        $(
+            impl AsyncDeserialize for $ty {
+                async fn deserialize_async<R>(reader: &mut R) -> crate::Result<Self>
+                where
+                    R: AsyncSource + ?Sized,
+                {
+                    deserialize_with_limits_async(reader, DecodeLimits::DEFAULT).await
+                }
+
+                async fn deserialize_async_limited<R>(
+                    reader: &mut R,
+                    limiter: &mut Limiter,
+                ) -> crate::Result<Self>
+                where
+                    R: AsyncSource + ?Sized,
+                {
+                    let mut buf = [0; varint::MAX_SIZE];
+                    let len = read_int(reader, Self::ENCODING, &mut buf, 0).await?;
+                    Self::deserialize_limited(&mut &buf[..len], limiter)
+                }
+            }
+
+            impl AsyncSerialize for $ty {}
+        )*
+    };
+}
+
+impl_async_varint!(Leb128, ZigZag, BigSize, QuicVarInt, crate::ber::Tag);
+
+/// Reads the rest of the input in chunks, checking its length against the
+/// limits of `limiter` as it grows.
+async fn read_to_end<R>(reader: &mut R, limiter: &Limiter) -> crate::Result<Vec<u8>>
+where
+    R: AsyncSource + ?Sized,
+{
+    let mut buf = Vec::new();
+    let mut chunk = [0; 512];
+    loop {
+        match reader.read(&mut chunk).await {
+            Ok(0) => return Ok(buf),
+            Ok(read) => {
+                buf.extend_from_slice(&chunk[..read]);
+                limiter.check_length(buf.len())?;
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+/// DER values take the rest of their input, which is read whole and decoded
+/// from memory.
+macro_rules! impl_async_der {
+    ($($ty:ident $(<$param:ident>)?),*) => {
+        $(
+            impl$(<$param>)? AsyncDeserialize for crate::der::$ty$(<$param>)?
+            where
+                $($param: crate::der::Tagged + Deserialize + Send,)?
+            {
+                async fn deserialize_async<R>(reader: &mut R) -> crate::Result<Self>
+                where
+                    R: AsyncSource + ?Sized,
+                {
+                    deserialize_with_limits_async(reader, DecodeLimits::DEFAULT).await
+                }
+
+                async fn deserialize_async_limited<R>(
+                    reader: &mut R,
+                    limiter: &mut Limiter,
+                ) -> crate::Result<Self>
+                where
+                    R: AsyncSource + ?Sized,
+                {
+                    let buf = read_to_end(reader, limiter).await?;
+                    Self::deserialize_limited(&mut buf.as_slice(), limiter)
+                }
+            }
+
+            impl$(<$param>)? AsyncSerialize for crate::der::$ty$(<$param>)?
+            where
+                $($param: crate::der::Tagged + Serialize + Sync,)?
+            {
+            }
+        )*
+    };
+}
+
+impl_async_der!(
+    Boolean,
+    Integer,
+    BigInteger,
+    OctetString,
+    Null,
+    ObjectIdentifier,
+    Utf8String,
+    SequenceOf<T>,
+    SetOf<T>,
+    Explicit<T>
+);
+
+impl<const LENGTH: usize> AsyncDeserialize for [u8; LENGTH] {
+    async fn deserialize_async<R>(reader: &mut R) -> crate::Result<Self>
+    where
+        R: AsyncSource + ?Sized,
+    {
+        let mut buf = [0; LENGTH];
+        reader.read_exact(&mut buf).await?;
+        Ok(buf)
+    }
+}
+
+impl AsyncDeserialize for String {
+    async fn deserialize_async<R>(reader: &mut R) -> crate::Result<Self>
+    where
+        R: AsyncSource + ?Sized,
+    {
+        deserialize_with_limits_async(reader, DecodeLimits::DEFAULT).await
+    }
+
+    async fn deserialize_async_limited<R>(
+        reader: &mut R,
+        limiter: &mut Limiter,
+    ) -> crate::Result<Self>
+    where
+        R: AsyncSource + ?Sized,
+    {
+        let len = u32::deserialize_async_limited(reader, limiter).await? as usize;
+        let buf = read_bytes(reader, len, limiter).await?;
+        String::from_utf8(buf).map_err(|_| ErrorKind::InvalidUtf8.into())
+    }
+}
+
+impl<T> AsyncDeserialize for Option<T>
+where
+    T: AsyncDeserialize,
+{
+    async fn deserialize_async<R>(reader: &mut R) -> crate::Result<Self>
+    where
+        R: AsyncSource + ?Sized,
+    {
+        deserialize_with_limits_async(reader, DecodeLimits::DEFAULT).await
+    }
+
+    async fn deserialize_async_limited<R>(
+        reader: &mut R,
+        limiter: &mut Limiter,
+    ) -> crate::Result<Self>
+    where
+        R: AsyncSource + ?Sized,
+    {
+        if u8::deserialize_async_limited(reader, limiter).await? == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(T::deserialize_async_limited(reader, limiter).await?))
+        }
+    }
+}
+
+impl<T> AsyncDeserialize for Vec<T>
+where
+    T: AsyncDeserialize,
+{
+    async fn deserialize_async<R>(reader: &mut R) -> crate::Result<Self>
+    where
+        R: AsyncSource + ?Sized,
+    {
+        deserialize_with_limits_async(reader, DecodeLimits::DEFAULT).await
+    }
+
+    async fn deserialize_async_limited<R>(
+        reader: &mut R,
+        limiter: &mut Limiter,
+    ) -> crate::Result<Self>
+    where
+        R: AsyncSource + ?Sized,
+    {
+        let len = u32::deserialize_async_limited(reader, limiter).await? as usize;
+        limiter.check_elements(len)?;
+        limiter.enter()?;
+        let decoded = async {
+            let mut vec = Vec::with_capacity(capacity::<T>(len));
+            for _ in 0..len {
+                vec.push(T::deserialize_async_limited(reader, &mut *limiter).await?);
+            }
+            Ok(vec)
+        }
+        .await;
+        limiter.leave();
+        decoded
+    }
+}
+
+impl<const LENGTH: usize> AsyncSerialize for [u8; LENGTH] {}
+impl AsyncSerialize for String {}
+impl AsyncSerialize for &str {}
+impl AsyncSerialize for &[u8] {}
+impl<T> AsyncSerialize for Option<T> where T: Serialize + Sync {}
+impl<T> AsyncSerialize for Vec<T> where T: Serialize + Sync {}
+impl<K, V> AsyncSerialize for HashMap<K, V>
+where
+    K: Serialize + Sync,
+    V: Serialize + Sync,
+{
+}
+impl<K, V> AsyncSerialize for BTreeMap<K, V>
+where
+    K: Serialize + Sync,
+    V: Serialize + Sync,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Source returning at most one byte per read.
+    struct Trickle<'a>(&'a [u8]);
+
+    impl AsyncSource for Trickle<'_> {
+        async fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = buf.len().min(1);
+            std::io::Read::read(&mut self.0, &mut buf[..len])
+        }
+    }
+
+    impl AsyncSink for Vec<u8> {
+        async fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        async fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        ::futures::executor::block_on(future)
+    }
+
+    #[test]
+    fn test_extract_raw_async() {
+        let buf = [2, 1, 0, 7, 1, 0, 0];
+
+        let fields = block_on(extract_raw_async(
+            &mut Trickle(&buf),
+            &Layout::DEFAULT,
+            [1, 2],
+            &Limiter::default(),
+        ))
+        .unwrap();
+
+        assert_eq!(fields[&2].value, [7]);
+        assert_eq!(fields[&2].offset, 3);
+        assert_eq!(fields[&1].offset, 7);
+
+        let err = block_on(extract_raw_async(
+            &mut Trickle(&buf[..5]),
+            &Layout::DEFAULT,
+            [1, 2],
+            &Limiter::default(),
+        ))
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Truncated);
+        assert_eq!(err.tag(), Some(1));
+        assert_eq!(err.offset(), Some(4));
+    }
+
+    #[test]
+    fn test_extract_stream_async() {
+        let mut buf = vec![3, 0x10, 2];
+        buf.extend([0; 0x210]);
+        buf.extend([1, 1, 0, 5]);
+
+        let extracted = block_on(extract_stream_async(
+            &mut Trickle(&buf),
+            &Layout::DEFAULT,
+            &[1],
+            &Limiter::default(),
+        ))
+        .unwrap();
+
+        assert_eq!(extracted.fields[&1].value, [5]);
+        assert_eq!(extracted.fields[&1].offset, 0x216);
+        assert_eq!(extracted.skipped, [3]);
+
+        // a skipped record is reported with the position of its value
+        let err = block_on(extract_stream_async(
+            &mut Trickle(&buf[..0x100]),
+            &Layout::DEFAULT,
+            &[1],
+            &Limiter::default(),
+        ))
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Truncated);
+        assert_eq!(err.tag(), Some(3));
+        assert_eq!(err.offset(), Some(3));
+    }
+
+    #[test]
+    fn test_values_async() {
+        let mut buf = Vec::new();
+        block_on(vec![Some("a".to_string()), None].serialize_async(&mut buf)).unwrap();
+
+        let value = block_on(Vec::<Option<String>>::deserialize_async(&mut Trickle(&buf))).unwrap();
+        assert_eq!(value, [Some("a".to_string()), None]);
+    }
+}
@@ -0,0 +1,283 @@
+//! BER-TLV, the TLV encoding of ISO/IEC 7816-4 and EMV.
+//!
+//! A tag is made of BER identifier octets: the class and whether the value
+//! is constructed, i.e. itself a sequence of records, in the first byte,
+//! then the tag number, continued over subsequent bytes when it does not fit.
+//! Tags are handled as the integer their octets make read as big-endian,
+//! `0x9f02` for the amount authorised, so that they can be written as they
+//! appear in specifications. [`Tag`] gives access to their parts.
+//!
+//! Lengths are definite, written in the short form below `0x80` and the long
+//! form above, and also read in the long form below `0x80` or with leading
+//! zero octets. Indefinite lengths fail with [`ErrorKind::Unsupported`].
+//!
+//! The padding bytes `0x00` and `0xff` ISO/IEC 7816-4 allows before and
+//! between records are not skipped: they are read as identifier octets, so
+//! they have to be removed from the input before it is decoded.
+//!
+//! A type deriving `Serialize` and `Deserialize` with `#[tlv(mode = "ber")]`
+//! maps to a template, whose constructed records are fields of types
+//! deriving them the same way.
+
+use crate::{ByteOrder, Config, Deserialize, Error, ErrorKind, IntEncoding, Layout, Serialize};
+
+/// Layout of BER-TLV records: BER tags and lengths, big-endian values.
+pub const LAYOUT: Layout =
+    Layout::new(IntEncoding::BerTag, IntEncoding::BerLength).with_byte_order(ByteOrder::Big);
+
+/// Whether `value` read as big-endian octets is a well-formed tag: a single
+/// octet whose tag number is not `0x1f`, or a first octet with a tag number
+/// of `0x1f` followed by octets of seven bits of tag number each, with the
+/// high bit set on all of them but the last and not starting with a zero
+/// group.
+pub(crate) fn is_tag(value: u64) -> bool {
+    let size = IntEncoding::BerTag.encoded_size(value);
+    let bytes = &value.to_be_bytes()[8 - size..];
+    match bytes {
+        [first] => first & 0x1f != 0x1f,
+        [first, subsequent @ ..] => {
+            first & 0x1f == 0x1f
+                && subsequent[0] != 0x80
+                && subsequent.iter().rev().skip(1).all(|byte| byte & 0x80 != 0)
+                && subsequent[subsequent.len() - 1] & 0x80 == 0
+        }
+        [] => false,
+    }
+}
+
+/// Class of a tag, the two high bits of its first octet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Class {
+    Universal = 0,
+    Application = 1,
+    ContextSpecific = 2,
+    Private = 3,
+}
+
+/// A BER tag, see [the module](self).
+///
+/// ```
+/// use tlv::ber::{Class, Tag};
+///
+/// let tag = Tag::try_from(0x9f02).unwrap();
+/// assert_eq!(tag.class(), Class::ContextSpecific);
+/// assert!(!tag.is_constructed());
+/// assert_eq!(tag.number(), 2);
+///
+/// assert_eq!(Tag::new(Class::Application, true, 0x10).value(), 0x70);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Tag(u64);
+
+impl Tag {
+    pub(crate) const ENCODING: IntEncoding = IntEncoding::BerTag;
+
+    /// The tag of the given class, constructed or primitive, and number, in
+    /// a single octet if the number is below `0x1f`.
+    pub const fn new(class: Class, constructed: bool, number: u32) -> Self {
+        let first = (class as u64) << 6 | if constructed { 0x20 } else { 0 };
+        if number < 0x1f {
+            return Self(first | number as u64);
+        }
+
+        let mut shift = 28;
+        while number >> shift == 0 {
+            shift -= 7;
+        }
+
+        let mut value = first | 0x1f;
+        loop {
+            let more = if shift > 0 { 0x80 } else { 0 };
+            value = value << 8 | ((number >> shift) & 0x7f) as u64 | more;
+            if shift == 0 {
+                return Self(value);
+            }
+            shift -= 7;
+        }
+    }
+
+    /// The octets of the tag read as big-endian.
+    pub const fn value(&self) -> u64 {
+        self.0
+    }
+
+    pub fn class(&self) -> Class {
+        match self.first() >> 6 {
+            0 => Class::Universal,
+            1 => Class::Application,
+            2 => Class::ContextSpecific,
+            _ => Class::Private,
+        }
+    }
+
+    /// Whether the value of the record is itself a sequence of records.
+    pub fn is_constructed(&self) -> bool {
+        self.first() & 0x20 != 0
+    }
+
+    pub fn number(&self) -> u64 {
+        match self.first() & 0x1f {
+            0x1f => {
+                let size = Self::ENCODING.encoded_size(self.0);
+                self.0.to_be_bytes()[8 - size + 1..]
+                    .iter()
+                    .fold(0, |number, byte| number << 7 | u64::from(byte & 0x7f))
+            }
+            number => u64::from(number),
+        }
+    }
+
+    fn first(&self) -> u8 {
+        let size = Self::ENCODING.encoded_size(self.0);
+        (self.0 >> (8 * (size - 1))) as u8
+    }
+}
+
+/// Fails with [`ErrorKind::OutOfRange`] if `value` is not a well-formed tag.
+impl TryFrom<u64> for Tag {
+    type Error = Error;
+
+    fn try_from(value: u64) -> crate::Result<Self> {
+        match is_tag(value) {
+            true => Ok(Self(value)),
+            false => Err(ErrorKind::OutOfRange.into()),
+        }
+    }
+}
+
+impl From<Tag> for u64 {
+    fn from(tag: Tag) -> Self {
+        tag.0
+    }
+}
+
+/// Tags are written as their identifier octets, whatever the byte order.
+impl Serialize for Tag {
+    fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        self.serialize_with(writer, &Config::DEFAULT)
+    }
+
+    fn serialize_with<W>(&self, writer: &mut W, config: &Config) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        Self::ENCODING.write(writer, self.0, config.byte_order)
+    }
+
+    fn serialized_length(&self) -> usize {
+        Self::ENCODING.encoded_size(self.0)
+    }
+}
+
+impl Deserialize for Tag {
+    fn deserialize<R>(reader: &mut R) -> crate::Result<Self>
+    where
+        R: crate::io::Read,
+    {
+        Self::deserialize_limited(reader, &mut crate::Limiter::default())
+    }
+
+    fn deserialize_limited<R>(reader: &mut R, limiter: &mut crate::Limiter) -> crate::Result<Self>
+    where
+        R: crate::io::Read,
+    {
+        Self::ENCODING.read(reader, limiter.byte_order()).map(Self)
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn test_tags() {
+        for (value, class, constructed, number) in [
+            (0x02, Class::Universal, false, 2),
+            (0x70, Class::Application, true, 0x10),
+            (0x9f02, Class::ContextSpecific, false, 2),
+            (0xbf0c, Class::ContextSpecific, true, 0x0c),
+            (0x5f2a, Class::Application, false, 0x2a),
+            (0xdf8101, Class::Private, false, 0x81),
+        ] {
+            let tag = Tag::try_from(value).unwrap();
+            assert_eq!(tag.class(), class, "{value:x}");
+            assert_eq!(tag.is_constructed(), constructed, "{value:x}");
+            assert_eq!(tag.number(), number, "{value:x}");
+        }
+
+        assert_eq!(
+            Tag::new(Class::ContextSpecific, false, 0x1f).value(),
+            0x9f1f
+        );
+        assert_eq!(Tag::new(Class::Private, true, 0x81).value(), 0xff8101);
+        let tag = Tag::new(Class::Universal, false, u32::MAX);
+        assert_eq!(tag.value(), 0x1f_8f_ff_ff_ff_7f);
+        assert_eq!(tag.number(), u64::from(u32::MAX));
+
+        // incomplete, unterminated and padded tag numbers
+        for value in [0x1f, 0x9f81, 0x9f8001] {
+            let err = Tag::try_from(value).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::OutOfRange, "{value:x}");
+        }
+    }
+
+    #[test]
+    fn test_tag_encoding() {
+        let mut buf = Vec::new();
+        Tag::try_from(0x9f02).unwrap().serialize(&mut buf).unwrap();
+        assert_eq!(buf, [0x9f, 0x02]);
+        let tag = Tag::deserialize(&mut buf.as_slice()).unwrap();
+        assert_eq!(tag.value(), 0x9f02);
+
+        let err = Tag::deserialize(&mut [0x9f, 0x80, 0x01].as_slice()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NonCanonical);
+        let err = Tag::deserialize(&mut [0x9f, 0x81].as_slice()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Truncated);
+        let err = Tag::deserialize(
+            &mut [0x1f, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01].as_slice(),
+        )
+        .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::OutOfRange);
+
+        let err = LAYOUT.write_tag(&mut Vec::new(), 0x9f).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::OutOfRange);
+    }
+
+    #[test]
+    fn test_lengths() {
+        for (length, expected) in [
+            (0, &[0x00][..]),
+            (0x7f, &[0x7f]),
+            (0x80, &[0x81, 0x80]),
+            (0xff, &[0x81, 0xff]),
+            (0x100, &[0x82, 0x01, 0x00]),
+            (0x1_0000, &[0x83, 0x01, 0x00, 0x00]),
+        ] {
+            let mut buf = Vec::new();
+            LAYOUT.write_length(&mut buf, length).unwrap();
+            assert_eq!(buf, expected, "{length}");
+            assert_eq!(LAYOUT.read_length(&mut &expected[..]).unwrap(), length);
+            assert_eq!(LAYOUT.length.encoded_size(length as u64), expected.len());
+        }
+
+        // the long form is also read where it is not needed
+        for (input, length) in [(&[0x81, 0x05][..], 5), (&[0x82, 0x00, 0xff], 0xff)] {
+            assert_eq!(LAYOUT.read_length(&mut &input[..]).unwrap(), length);
+        }
+
+        for (input, kind) in [
+            (&[0x80][..], ErrorKind::Unsupported),
+            (&[0x89, 0, 0, 0, 0, 0, 0, 0, 0, 1], ErrorKind::OutOfRange),
+            (&[0xff], ErrorKind::OutOfRange),
+            (&[0x82, 0x01], ErrorKind::Truncated),
+        ] {
+            let err = LAYOUT.read_length(&mut &input[..]).unwrap_err();
+            assert_eq!(err.kind(), kind, "{input:x?}");
+        }
+    }
+}
@@ -0,0 +1,213 @@
+//! The TLV streams of the Lightning Network, as specified by BOLT #1.
+//!
+//! A stream is a sequence of records whose types and lengths are
+//! [`BigSize`](crate::varint::BigSize) integers, in strictly increasing order
+//! of type. A record of a type the reader does not know is skipped if the
+//! type is odd and fails to decode if it is even: "it's OK to be odd".
+//! Integers are big-endian, and the truncated [`Tu16`], [`Tu32`] and
+//! [`Tu64`] take no more bytes than their value needs.
+//!
+//! Streams are read with [`extract_bolt`](crate::extract_bolt) and its
+//! borrowed and asynchronous counterparts, or with a type deriving
+//! `Deserialize` with `#[tlv(mode = "bolt")]`.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::{ByteOrder, Config, Deserialize, ErrorKind, IntEncoding, Layout, Serialize};
+#[cfg(feature = "alloc")]
+use crate::{Error, UnknownRecords};
+
+/// Layout of the records of a stream: BigSize types and lengths, big-endian
+/// values.
+pub const LAYOUT: Layout =
+    Layout::new(IntEncoding::BigSize, IntEncoding::BigSize).with_byte_order(ByteOrder::Big);
+
+/// The records of `unknown` sorted by type, the order a stream has them in
+/// whatever the order they are kept in. A type deriving `Serialize` writes
+/// them this way.
+#[cfg(feature = "alloc")]
+pub fn sorted_records<U>(unknown: &U) -> Vec<(u64, &[u8])>
+where
+    U: UnknownRecords,
+{
+    let mut records = unknown.records().collect::<Vec<_>>();
+    records.sort_by_key(|&(id, _)| id);
+    records
+}
+
+/// Checks the types of the records of a stream as they are read.
+#[cfg(feature = "alloc")]
+pub(crate) struct Types<'a> {
+    known: &'a [u64],
+    previous: Option<u64>,
+}
+
+#[cfg(feature = "alloc")]
+impl<'a> Types<'a> {
+    pub(crate) fn new(known: &'a [u64]) -> Self {
+        Self {
+            known,
+            previous: None,
+        }
+    }
+
+    /// Returns whether the record of type `id` starting at `offset` is to
+    /// be read, or skipped as an unknown odd type.
+    pub(crate) fn check(&mut self, id: u64, offset: u64) -> crate::Result<bool> {
+        match self.previous {
+            Some(previous) if id == previous => {
+                return Err(Error::new(ErrorKind::DuplicateTag)
+                    .with_tag(id)
+                    .with_offset(offset))
+            }
+            Some(previous) if id < previous => {
+                return Err(Error::new(ErrorKind::UnorderedTag)
+                    .with_tag(id)
+                    .with_offset(offset))
+            }
+            _ => self.previous = Some(id),
+        }
+
+        match self.known.contains(&id) || id % 2 == 1 {
+            true => Ok(self.known.contains(&id)),
+            false => Err(Error::new(ErrorKind::UnexpectedTag)
+                .with_tag(id)
+                .with_offset(offset)),
+        }
+    }
+}
+
+/// Truncated integers: big-endian without their leading zero bytes, so
+/// zero is no bytes at all. As nothing tells where they end, they take the
+/// rest of the input and are only found at the end of a record.
+macro_rules! impl_truncated {
+    ($($(#[$doc:meta])* $name:ident($ty:ty);)*) => {
+        $(
+            $(#[$doc])*
+            #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+            pub struct $name(pub $ty);
+
+            impl Serialize for $name {
+                fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
+                where
+                    W: crate::io::Write,
+                {
+                    let bytes = self.0.to_be_bytes();
+                    let len = self.serialized_length();
+                    writer.write_all(&bytes[bytes.len() - len..])?;
+                    Ok(len)
+                }
+
+                fn serialize_with<W>(&self, writer: &mut W, _config: &Config) -> crate::Result<usize>
+                where
+                    W: crate::io::Write,
+                {
+                    self.serialize(writer)
+                }
+
+                fn serialized_length(&self) -> usize {
+                    core::mem::size_of::<$ty>() - self.0.leading_zeros() as usize / 8
+                }
+            }
+
+            /// More bytes than the integer has are [`ErrorKind::OutOfRange`],
+            /// a leading zero byte is [`ErrorKind::NonCanonical`].
+            impl Deserialize for $name {
+                fn deserialize<R>(reader: &mut R) -> crate::Result<Self>
+                where
+                    R: crate::io::Read,
+                {
+                    const SIZE: usize = core::mem::size_of::<$ty>();
+
+                    // one byte more than fits tells a value too long apart
+                    let mut buf = [0; SIZE + 1];
+                    let mut len = 0;
+                    while len < buf.len() {
+                        match reader.read(&mut buf[len..]) {
+                            Ok(0) => break,
+                            Ok(read) => len += read,
+                            Err(err) if err.kind() == crate::io::ErrorKind::Interrupted => {}
+                            Err(err) => return Err(err.into()),
+                        }
+                    }
+
+                    match &buf[..len] {
+                        bytes if bytes.len() > SIZE => Err(ErrorKind::OutOfRange.into()),
+                        [0, ..] => Err(ErrorKind::NonCanonical.into()),
+                        bytes => {
+                            let mut value = [0; SIZE];
+                            value[SIZE - bytes.len()..].copy_from_slice(bytes);
+                            Ok(Self(<$ty>::from_be_bytes(value)))
+                        }
+                    }
+                }
+            }
+
+            impl<'de> crate::BorrowDeserialize<'de> for $name {
+                fn borrow_deserialize(input: &mut &'de [u8]) -> crate::Result<Self> {
+                    Self::deserialize(input)
+                }
+            }
+        )*
+    };
+}
+
+impl_truncated! {
+    /// A `tu16`, 0 to 2 bytes.
+    Tu16(u16);
+    /// A `tu32`, 0 to 4 bytes.
+    Tu32(u32);
+    /// A `tu64`, 0 to 8 bytes.
+    Tu64(u64);
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    #[test]
+    fn test_truncated() {
+        for (value, expected) in [
+            (0, &[][..]),
+            (1, &[1]),
+            (0x100, &[1, 0]),
+            (u64::MAX, &[0xff; 8]),
+        ] {
+            let mut buf = Vec::new();
+            assert_eq!(Tu64(value).serialize(&mut buf).unwrap(), expected.len());
+            assert_eq!(buf, expected);
+            assert_eq!(Tu64::deserialize(&mut &expected[..]).unwrap(), Tu64(value));
+        }
+
+        let err = Tu16::deserialize(&mut [1, 2, 3].as_slice()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::OutOfRange);
+        let err = Tu32::deserialize(&mut [0, 1].as_slice()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NonCanonical);
+    }
+
+    #[test]
+    fn test_types() {
+        let mut types = Types::new(&[2, 4]);
+        assert!(types.check(2, 0).unwrap());
+        assert!(!types.check(3, 0).unwrap());
+        assert!(types.check(4, 0).unwrap());
+
+        let err = types.check(6, 9).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedTag);
+        assert_eq!(err.offset(), Some(9));
+
+        let mut types = Types::new(&[]);
+        types.check(5, 0).unwrap();
+        assert_eq!(
+            types.check(5, 0).unwrap_err().kind(),
+            ErrorKind::DuplicateTag
+        );
+        assert_eq!(
+            types.check(1, 0).unwrap_err().kind(),
+            ErrorKind::UnorderedTag
+        );
+    }
+}
@@ -0,0 +1,391 @@
+//! Framing of a byte stream into TLV records with `tokio_util::codec`.
+
+use std::marker::PhantomData;
+
+use bytes::{Buf, BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::{DecodeLimits, Deserialize, Error, ErrorKind, Layout, Limiter, Serialize};
+
+/// Encodes and decodes values of type `T`, each as one record of a stream.
+///
+/// A frame is a single record whose tag is [`tag`](Self::with_tag) and
+/// whose value is the encoding of a `T`. Its length is checked against the
+/// [maximum frame length](Self::with_max_frame_length) as soon as the
+/// header is read, before the value is waited for, and before anything is
+/// written when encoding. Frames are decoded as soon as they are complete,
+/// partial ones are left in the buffer until the rest arrives, and their
+/// values within the [limits](Self::with_limits) of the codec.
+///
+/// ```
+/// use bytes::BytesMut;
+/// use tlv::TlvCodec;
+/// use tokio_util::codec::{Decoder, Encoder};
+///
+/// let mut codec = TlvCodec::<u16>::new();
+/// let mut buf = BytesMut::new();
+/// codec.encode(7, &mut buf).unwrap();
+/// assert_eq!(buf[..], [0, 2, 0, 7, 0]);
+///
+/// let mut partial = buf.split_to(3);
+/// assert_eq!(codec.decode(&mut partial).unwrap(), None);
+/// partial.unsplit(buf);
+/// assert_eq!(codec.decode(&mut partial).unwrap(), Some(7));
+/// ```
+pub struct TlvCodec<T> {
+    layout: Layout,
+    tag: u64,
+    max_frame_length: usize,
+    limits: DecodeLimits,
+    marker: PhantomData<fn(T) -> T>,
+}
+
+impl<T> TlvCodec<T> {
+    /// Maximum length of the value of a frame, unless configured otherwise.
+    pub const DEFAULT_MAX_FRAME_LENGTH: usize = 8 * 1024 * 1024;
+
+    /// Frames with tag `0` and the default [`Layout`].
+    pub fn new() -> Self {
+        Self {
+            layout: Layout::DEFAULT,
+            tag: 0,
+            max_frame_length: Self::DEFAULT_MAX_FRAME_LENGTH,
+            limits: DecodeLimits::DEFAULT,
+            marker: PhantomData,
+        }
+    }
+
+    pub fn with_layout(self, layout: Layout) -> Self {
+        Self { layout, ..self }
+    }
+
+    /// Tag of every frame, a frame with another tag fails to decode with
+    /// [`ErrorKind::UnexpectedTag`].
+    pub fn with_tag(self, tag: u64) -> Self {
+        Self { tag, ..self }
+    }
+
+    /// Maximum length of the value of a frame. Longer frames fail with
+    /// [`ErrorKind::FrameTooLarge`], both ways.
+    pub fn with_max_frame_length(self, max_frame_length: usize) -> Self {
+        Self {
+            max_frame_length,
+            ..self
+        }
+    }
+
+    /// Limits the value of a frame is decoded within, see
+    /// [`deserialize_with_limits`](crate::deserialize_with_limits).
+    pub fn with_limits(self, limits: DecodeLimits) -> Self {
+        Self { limits, ..self }
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    pub fn tag(&self) -> u64 {
+        self.tag
+    }
+
+    pub fn max_frame_length(&self) -> usize {
+        self.max_frame_length
+    }
+
+    pub fn limits(&self) -> &DecodeLimits {
+        &self.limits
+    }
+
+    fn read_header(&self, input: &mut &[u8]) -> crate::Result<(u64, usize)> {
+        let tag = self.layout.read_tag(input)?;
+        if tag != self.tag {
+            return Err(Error::new(ErrorKind::UnexpectedTag)
+                .with_tag(tag)
+                .with_offset(0));
+        }
+        let len = self
+            .layout
+            .read_length(input)
+            .map_err(|err| err.with_tag(tag))?;
+        Ok((tag, len))
+    }
+
+    fn too_large(&self, offset: u64) -> Error {
+        Error::new(ErrorKind::FrameTooLarge)
+            .with_tag(self.tag)
+            .with_offset(offset)
+    }
+}
+
+impl<T> Default for TlvCodec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> Clone for TlvCodec<T> {
+    fn clone(&self) -> Self {
+        Self {
+            layout: self.layout,
+            tag: self.tag,
+            max_frame_length: self.max_frame_length,
+            limits: self.limits,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for TlvCodec<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TlvCodec")
+            .field("layout", &self.layout)
+            .field("tag", &self.tag)
+            .field("max_frame_length", &self.max_frame_length)
+            .field("limits", &self.limits)
+            .finish()
+    }
+}
+
+impl<T> Decoder for TlvCodec<T>
+where
+    T: Deserialize,
+{
+    type Item = T;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> crate::Result<Option<T>> {
+        // the header is read from what arrived so far, until it is complete
+        let mut input = &src[..];
+        let header = match self.read_header(&mut input) {
+            Err(err) if err.kind() == ErrorKind::Truncated => {
+                let header = self.layout.tag.size() + self.layout.length.size();
+                src.reserve(header.saturating_sub(src.len()));
+                return Ok(None);
+            }
+            header => header?,
+        };
+        let (tag, len) = header;
+        let header = src.len() - input.len();
+        if len > self.max_frame_length {
+            return Err(self.too_large(header as u64));
+        }
+        // the value is all that is read, as much as a frame may hold
+        let max_total_bytes = self.limits.max_total_bytes;
+        if len as u64 > max_total_bytes {
+            return Err(Error::new(ErrorKind::LimitExceeded)
+                .with_tag(tag)
+                .with_offset(header as u64 + max_total_bytes));
+        }
+
+        if src.len() < header + len {
+            src.reserve(header + len - src.len());
+            return Ok(None);
+        }
+
+        src.advance(header);
+        let frame = src.split_to(len);
+        let mut value = &frame[..];
+        let mut limiter = Limiter::new(self.limits).with_byte_order(self.layout.byte_order);
+
+        let decoded =
+            T::deserialize_limited(&mut value, &mut limiter)
+                .and_then(|decoded| match value.len() {
+                    0 => Ok(decoded),
+                    _ => Err(Error::new(ErrorKind::LengthMismatch)
+                        .with_offset((len - value.len()) as u64)),
+                })
+                .map_err(|err| err.advance(header as u64).or_tag(tag))?;
+
+        Ok(Some(decoded))
+    }
+}
+
+impl<T> Encoder<T> for TlvCodec<T>
+where
+    T: Serialize,
+{
+    type Error = Error;
+
+    fn encode(&mut self, item: T, dst: &mut BytesMut) -> crate::Result<()> {
+        Encoder::<&T>::encode(self, &item, dst)
+    }
+}
+
+impl<T> Encoder<&T> for TlvCodec<T>
+where
+    T: Serialize,
+{
+    type Error = Error;
+
+    fn encode(&mut self, item: &T, dst: &mut BytesMut) -> crate::Result<()> {
+        let len = item.serialized_length();
+        if len > self.max_frame_length {
+            return Err(self.too_large(0));
+        }
+
+        // a failed encode leaves nothing of the frame behind, e.g. when the
+        // value is not as long as it said
+        let start = dst.len();
+        dst.reserve(self.layout.record_length(self.tag, len));
+        self.layout
+            .write_record(&mut dst.writer(), self.tag, item)
+            .map(drop)
+            .inspect_err(|_| dst.truncate(start))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{SinkExt, StreamExt};
+    use tokio_util::codec::{FramedRead, FramedWrite};
+
+    use super::*;
+    use crate::IntEncoding;
+
+    #[test]
+    fn test_partial_frames() {
+        let mut codec = TlvCodec::<String>::new().with_tag(5);
+        let mut encoded = BytesMut::new();
+        codec.encode("tlv".to_string(), &mut encoded).unwrap();
+        codec.encode(&"rs".to_string(), &mut encoded).unwrap();
+        assert_eq!(encoded[..10], [5, 7, 0, 3, 0, 0, 0, b't', b'l', b'v']);
+
+        // fed one byte at a time, every frame comes out once it is complete
+        let mut src = BytesMut::new();
+        let mut decoded = Vec::new();
+        for byte in encoded {
+            src.put_u8(byte);
+            decoded.extend(codec.decode(&mut src).unwrap());
+        }
+
+        assert_eq!(decoded, ["tlv", "rs"]);
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn test_max_frame_length() {
+        let layout = Layout::DEFAULT.with_length(IntEncoding::U32);
+        let mut codec = TlvCodec::<Vec<u8>>::new()
+            .with_layout(layout)
+            .with_max_frame_length(8);
+
+        let mut dst = BytesMut::new();
+        let err = codec.encode(vec![0; 5], &mut dst).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::FrameTooLarge);
+        assert!(dst.is_empty());
+
+        // rejected from the header alone, without waiting for the value
+        let mut src = BytesMut::from(&[0, 0xff, 0xff, 0xff, 0xff][..]);
+        let err = codec.decode(&mut src).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::FrameTooLarge);
+        assert_eq!(err.offset(), Some(5));
+    }
+
+    #[test]
+    fn test_invalid_frames() {
+        let mut codec = TlvCodec::<u8>::new();
+
+        let err = codec
+            .decode(&mut BytesMut::from(&[1, 1, 0, 7][..]))
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedTag);
+        assert_eq!(err.tag(), Some(1));
+
+        let err = codec
+            .decode(&mut BytesMut::from(&[0, 2, 0, 7, 8][..]))
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LengthMismatch);
+        assert_eq!(err.offset(), Some(4));
+    }
+
+    #[test]
+    fn test_limits() {
+        let limits = DecodeLimits::DEFAULT.with_max_string_length(2);
+        let mut codec = TlvCodec::<String>::new().with_limits(limits);
+        assert_eq!(codec.limits(), &limits);
+
+        let mut src = BytesMut::new();
+        codec.encode("tlv".to_string(), &mut src).unwrap();
+        let err = codec.decode(&mut src.clone()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+        assert_eq!(err.tag(), Some(0));
+
+        // the value of a frame is all a decode may read
+        let limits = DecodeLimits::DEFAULT.with_max_total_bytes(6);
+        let mut codec = codec.with_limits(limits);
+        let err = codec.decode(&mut src).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+        assert_eq!(err.offset(), Some(9));
+    }
+
+    /// A value claiming to be longer than it is.
+    struct Short;
+
+    impl Serialize for Short {
+        fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
+        where
+            W: crate::io::Write,
+        {
+            1u8.serialize(writer)
+        }
+
+        fn serialized_length(&self) -> usize {
+            2
+        }
+    }
+
+    #[test]
+    fn test_failed_encode() {
+        let mut codec = TlvCodec::<Short>::new();
+        let mut dst = BytesMut::from(&[9][..]);
+
+        let err = codec.encode(Short, &mut dst).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LengthMismatch);
+        assert_eq!(dst[..], [9]);
+    }
+
+    #[test]
+    fn test_big_endian() {
+        let layout = Layout::DEFAULT.with_byte_order(crate::ByteOrder::Big);
+        let mut codec = TlvCodec::<u16>::new().with_layout(layout).with_tag(1);
+
+        let mut buf = BytesMut::new();
+        codec.encode(0x0102, &mut buf).unwrap();
+        assert_eq!(buf[..], [1, 0, 2, 1, 2]);
+        assert_eq!(codec.decode(&mut buf).unwrap(), Some(0x0102));
+    }
+
+    #[test]
+    fn test_variable_length_header() {
+        let layout = Layout::new(IntEncoding::Quic, IntEncoding::Leb128);
+        let mut codec = TlvCodec::<Vec<u8>>::new().with_layout(layout).with_tag(100);
+
+        let mut encoded = BytesMut::new();
+        codec.encode(vec![7; 200], &mut encoded).unwrap();
+        assert_eq!(encoded[..4], [0x40, 100, 0xcc, 0x01]);
+
+        let mut src = BytesMut::new();
+        for byte in &encoded[..4] {
+            assert_eq!(codec.decode(&mut src).unwrap(), None);
+            src.put_u8(*byte);
+        }
+        src.extend_from_slice(&encoded[4..]);
+        assert_eq!(codec.decode(&mut src).unwrap(), Some(vec![7; 200]));
+    }
+
+    #[tokio::test]
+    async fn test_framed() {
+        let (client, server) = tokio::io::duplex(64);
+
+        let mut sink = FramedWrite::new(client, TlvCodec::<Option<u32>>::new());
+        let mut stream = FramedRead::new(server, TlvCodec::<Option<u32>>::new());
+
+        sink.send(Some(7)).await.unwrap();
+        sink.send(None).await.unwrap();
+        drop(sink);
+
+        assert_eq!(stream.next().await.unwrap().unwrap(), Some(7));
+        assert_eq!(stream.next().await.unwrap().unwrap(), None);
+        assert!(stream.next().await.is_none());
+    }
+}
@@ -0,0 +1,49 @@
+use crate::{ByteOrder, DecodeLimits};
+
+/// Options of a single encode or decode call.
+///
+/// They apply to every value that does not fix them itself: a type deriving
+/// `Serialize` or `Deserialize` with a `byte_order` attribute is always
+/// written in that order, and so are its fields.
+///
+/// ```
+/// use tlv::{ByteOrder, Config};
+///
+/// let config = Config::DEFAULT.with_byte_order(ByteOrder::Big);
+/// let mut buf = [0; 2];
+/// tlv::serialize_with_config(&0x0102u16, &mut buf.as_mut_slice(), &config).unwrap();
+/// assert_eq!(buf, [1, 2]);
+///
+/// let value: u16 = tlv::deserialize_with_config(&mut buf.as_slice(), &config).unwrap();
+/// assert_eq!(value, 0x0102);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Config {
+    /// Byte order of the integers, including the tags and lengths of
+    /// records and the length prefixes of collections and strings.
+    pub byte_order: ByteOrder,
+    /// Limits of a decode, unused when encoding.
+    pub limits: DecodeLimits,
+}
+
+impl Config {
+    /// Little-endian, within the default limits.
+    pub const DEFAULT: Self = Self {
+        byte_order: ByteOrder::Little,
+        limits: DecodeLimits::DEFAULT,
+    };
+
+    pub const fn with_byte_order(self, byte_order: ByteOrder) -> Self {
+        Self { byte_order, ..self }
+    }
+
+    pub const fn with_limits(self, limits: DecodeLimits) -> Self {
+        Self { limits, ..self }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
@@ -0,0 +1,1025 @@
+//! ASN.1 DER, the distinguished encoding rules of X.690.
+//!
+//! Every DER value is a BER-TLV record, see [`ber`](crate::ber), tagged by
+//! its ASN.1 type. The types of this module implement [`Serialize`] and
+//! [`Deserialize`] as the contents of that record, the value of the record
+//! in the terms of this crate, and give their universal tag through
+//! [`Tagged`]. [`serialize`] and [`deserialize`] add and check the tag.
+//!
+//! A struct deriving `Serialize` and `Deserialize` with `#[tlv(mode = "der")]`
+//! is a `SEQUENCE` whose fields are DER values, each tagged by its type, or
+//! in the context-specific class with `#[tlv(implicit = 0)]` or
+//! `#[tlv(explicit = 0)]`. `Option` fields are `OPTIONAL`. A `SET` of
+//! fields of different types cannot be derived, only [`SetOf`] is a `SET`.
+//!
+//! Decoding is strict: lengths are definite and in their shortest form, tag
+//! numbers below 31 take a single octet, and every type checks that its
+//! contents are in their single valid form, failing with
+//! [`ErrorKind::NonCanonical`] otherwise. As contents have no length of
+//! their own, the types of this module read until the end of their input.
+
+use alloc::collections::VecDeque;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+use crate::ber::{Class, Tag};
+use crate::{
+    deserialize_with_limits, BorrowDeserialize, BorrowedField, DecodeLimits, Deserialize, Error,
+    ErrorKind, Layout, Limiter, RawField, Serialize,
+};
+
+/// Layout of DER records, the one of [`ber`](crate::ber).
+pub const LAYOUT: Layout = crate::ber::LAYOUT;
+
+/// A type with a tag of its own, the universal tag of its ASN.1 type as
+/// BER identifier octets, e.g. `0x30` for a `SEQUENCE`.
+pub trait Tagged {
+    const TAG: u64;
+}
+
+/// Tag of a `T` implicitly tagged `[number]` in the context-specific class:
+/// the tag replaces the one of `T`, which stays constructed if it was.
+pub const fn implicit<T>(number: u32) -> u64
+where
+    T: Tagged + ?Sized,
+{
+    Tag::new(Class::ContextSpecific, T::TAG & 0x20 != 0, number).value()
+}
+
+/// Tag of a value explicitly tagged `[number]` in the context-specific
+/// class: a constructed record whose value is the record of the value.
+pub const fn explicit(number: u32) -> u64 {
+    Tag::new(Class::ContextSpecific, true, number).value()
+}
+
+/// Checks that a tag is in its DER form, tag numbers below 31 in a single
+/// octet. BER, and EMV, also allow them in the long form.
+pub(crate) fn check_tag(id: u64) -> crate::Result<()> {
+    let tag = Tag::try_from(id)?;
+    match tag.value() > 0xff && tag.number() < 0x1f {
+        true => Err(ErrorKind::NonCanonical.into()),
+        false => Ok(()),
+    }
+}
+
+/// Checks that the record of tag `id` read at `start`, whose value of `len`
+/// bytes starts at `value`, has its length in the shortest form, which BER
+/// does not require.
+pub(crate) fn check_length(id: u64, start: u64, len: usize, value: u64) -> crate::Result<()> {
+    let header = LAYOUT.tag.encoded_size(id) + LAYOUT.length.encoded_size(len as u64);
+    match start + header as u64 == value {
+        true => Ok(()),
+        false => Err(ErrorKind::NonCanonical.into()),
+    }
+}
+
+/// Encodes `value` as a whole DER value, tag and length included.
+pub fn serialize<T, W>(value: &T, writer: &mut W) -> crate::Result<usize>
+where
+    T: Tagged + Serialize,
+    W: crate::io::Write,
+{
+    LAYOUT.write_record(writer, T::TAG, value)
+}
+
+/// Number of bytes [`serialize`] writes.
+pub fn serialized_length<T>(value: &T) -> usize
+where
+    T: Tagged + Serialize,
+{
+    LAYOUT.record_length(T::TAG, value.serialized_length())
+}
+
+/// Decodes a whole DER value, which has to be all of `reader`.
+pub fn deserialize<T, R>(reader: &mut R) -> crate::Result<T>
+where
+    T: Tagged + Deserialize,
+    R: crate::io::Read,
+{
+    deserialize_with_limits::<Explicit<T>, _>(reader, DecodeLimits::DEFAULT)
+        .map(|explicit| explicit.0)
+}
+
+/// Writes `value` explicitly tagged `tag`, see [`Explicit`].
+pub fn serialize_explicit<T, W>(writer: &mut W, tag: u64, value: &T) -> crate::Result<usize>
+where
+    T: Tagged + Serialize,
+    W: crate::io::Write,
+{
+    let mut written = LAYOUT.write_tag(writer, tag)?;
+    written += LAYOUT
+        .write_length(writer, serialized_length(value))
+        .map_err(|err| err.with_tag(tag))?;
+    let header = written;
+    written += serialize(value, writer).map_err(|err| err.advance(header as u64))?;
+    Ok(written)
+}
+
+/// Number of bytes [`serialize_explicit`] writes.
+pub fn explicit_length<T>(tag: u64, value: &T) -> usize
+where
+    T: Tagged + Serialize,
+{
+    LAYOUT.record_length(tag, serialized_length(value))
+}
+
+/// Records of a constructed value in the order they were read, see
+/// [`extract_der`](crate::extract_der).
+#[derive(Debug)]
+pub struct Records<F = RawField> {
+    records: VecDeque<(u64, u64, F)>,
+}
+
+impl<F> Records<F> {
+    pub(crate) fn new() -> Self {
+        Self {
+            records: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn push(&mut self, id: u64, offset: u64, field: F) {
+        self.records.push_back((id, offset, field));
+    }
+
+    /// Takes the next record if it is tagged `tag`, and leaves it for the
+    /// next call otherwise.
+    pub fn take_tagged(&mut self, tag: u64) -> Option<F> {
+        match self.records.front() {
+            Some((id, ..)) if *id == tag => self.records.pop_front().map(|(.., field)| field),
+            _ => None,
+        }
+    }
+
+    /// Fails with [`ErrorKind::UnexpectedTag`] if records were not taken.
+    pub fn finish(self) -> crate::Result<()> {
+        match self.records.front() {
+            Some((id, offset, _)) => Err(Error::new(ErrorKind::UnexpectedTag)
+                .with_tag(*id)
+                .with_offset(*offset)),
+            None => Ok(()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+}
+
+impl<F> Iterator for Records<F> {
+    type Item = F;
+
+    fn next(&mut self) -> Option<F> {
+        self.records.pop_front().map(|(.., field)| field)
+    }
+}
+
+/// Reads contents until the end of the input, checking their length
+/// against the limits of `limiter` as they grow.
+fn read_contents<R>(reader: &mut R, limiter: &Limiter) -> crate::Result<Vec<u8>>
+where
+    R: crate::io::Read,
+{
+    let mut contents = Vec::new();
+    let mut chunk = [0; 256];
+    loop {
+        match reader.read(&mut chunk) {
+            Ok(0) => return Ok(contents),
+            Ok(read) => {
+                contents.extend_from_slice(&chunk[..read]);
+                limiter.check_length(contents.len())?;
+            }
+            Err(err) if err.kind() == crate::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+/// The types whose contents are decoded from memory, once read whole.
+macro_rules! impl_contents {
+    ($($ty:ty: $tag:expr;)*) => {
+        $(
+            impl Tagged for $ty {
+                const TAG: u64 = $tag;
+            }
+
+            impl Deserialize for $ty {
+                fn deserialize<R>(reader: &mut R) -> crate::Result<Self>
+                where
+                    R: crate::io::Read,
+                {
+                    deserialize_with_limits(reader, DecodeLimits::DEFAULT)
+                }
+
+                fn deserialize_limited<R>(reader: &mut R, limiter: &mut Limiter) -> crate::Result<Self>
+                where
+                    R: crate::io::Read,
+                {
+                    Self::decode(&read_contents(reader, limiter)?, limiter)
+                }
+            }
+
+            impl<'de> BorrowDeserialize<'de> for $ty {
+                fn borrow_deserialize(input: &mut &'de [u8]) -> crate::Result<Self> {
+                    Self::borrow_deserialize_limited(input, &mut Limiter::default())
+                }
+
+                fn borrow_deserialize_limited(
+                    input: &mut &'de [u8],
+                    limiter: &mut Limiter,
+                ) -> crate::Result<Self> {
+                    limiter.check_length(input.len())?;
+                    Self::decode(core::mem::take(input), limiter)
+                }
+            }
+        )*
+    };
+}
+
+impl_contents! {
+    Boolean: 0x01;
+    Integer: 0x02;
+    BigInteger: 0x02;
+    OctetString: 0x04;
+    Null: 0x05;
+    ObjectIdentifier: 0x06;
+    Utf8String: 0x0c;
+}
+
+/// A `BOOLEAN`, whose contents are `ff` for true and `00` for false.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Boolean(pub bool);
+
+impl Boolean {
+    fn decode(contents: &[u8], _limiter: &Limiter) -> crate::Result<Self> {
+        match contents {
+            [0x00] => Ok(Self(false)),
+            [0xff] => Ok(Self(true)),
+            [_] => Err(ErrorKind::NonCanonical.into()),
+            _ => Err(ErrorKind::LengthMismatch.into()),
+        }
+    }
+}
+
+impl Serialize for Boolean {
+    fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        writer.write_all(&[if self.0 { 0xff } else { 0x00 }])?;
+        Ok(1)
+    }
+
+    fn serialized_length(&self) -> usize {
+        1
+    }
+}
+
+/// An `INTEGER`, in two's complement on as few bytes as possible. Values
+/// beyond 128 bits are [`ErrorKind::OutOfRange`], [`BigInteger`] reads
+/// integers of any length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Integer(pub i128);
+
+impl Integer {
+    /// Whether the first byte of `bytes` only repeats the sign of the next.
+    fn redundant(bytes: &[u8]) -> bool {
+        match bytes {
+            [0x00, next, ..] => next & 0x80 == 0,
+            [0xff, next, ..] => next & 0x80 != 0,
+            _ => false,
+        }
+    }
+
+    fn decode(contents: &[u8], _limiter: &Limiter) -> crate::Result<Self> {
+        if contents.is_empty() {
+            return Err(ErrorKind::Truncated.into());
+        }
+        if Self::redundant(contents) {
+            return Err(ErrorKind::NonCanonical.into());
+        }
+        if contents.len() > 16 {
+            return Err(ErrorKind::OutOfRange.into());
+        }
+
+        let fill = if contents[0] & 0x80 != 0 { 0xff } else { 0x00 };
+        let mut buf = [fill; 16];
+        buf[16 - contents.len()..].copy_from_slice(contents);
+        Ok(Self(i128::from_be_bytes(buf)))
+    }
+
+    fn bytes(&self) -> ([u8; 16], usize) {
+        let bytes = self.0.to_be_bytes();
+        let mut start = 0;
+        while start < 15 && Self::redundant(&bytes[start..]) {
+            start += 1;
+        }
+        (bytes, start)
+    }
+}
+
+impl Serialize for Integer {
+    fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        let (bytes, start) = self.bytes();
+        writer.write_all(&bytes[start..])?;
+        Ok(16 - start)
+    }
+
+    fn serialized_length(&self) -> usize {
+        16 - self.bytes().1
+    }
+}
+
+/// An `INTEGER` of any length, kept as its contents: big-endian two's
+/// complement bytes without a redundant leading byte, e.g. the up to 20
+/// bytes of the serial number of an X.509 certificate.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BigInteger(Vec<u8>);
+
+impl BigInteger {
+    /// Takes the contents of an `INTEGER`, failing with
+    /// [`ErrorKind::Truncated`] if there are none and with
+    /// [`ErrorKind::NonCanonical`] if the first byte only repeats the sign
+    /// of the next.
+    pub fn new(bytes: Vec<u8>) -> crate::Result<Self> {
+        if bytes.is_empty() {
+            return Err(ErrorKind::Truncated.into());
+        }
+        if Integer::redundant(&bytes) {
+            return Err(ErrorKind::NonCanonical.into());
+        }
+        Ok(Self(bytes))
+    }
+
+    /// The big-endian two's complement bytes of the integer.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Whether the integer is below zero.
+    pub fn is_negative(&self) -> bool {
+        self.0[0] & 0x80 != 0
+    }
+
+    fn decode(contents: &[u8], _limiter: &Limiter) -> crate::Result<Self> {
+        Self::new(contents.to_vec())
+    }
+}
+
+impl From<Integer> for BigInteger {
+    fn from(integer: Integer) -> Self {
+        let (bytes, start) = integer.bytes();
+        Self(bytes[start..].to_vec())
+    }
+}
+
+/// Fails with [`ErrorKind::OutOfRange`] beyond 128 bits.
+impl TryFrom<&BigInteger> for Integer {
+    type Error = Error;
+
+    fn try_from(integer: &BigInteger) -> crate::Result<Self> {
+        Self::decode(&integer.0, &Limiter::default())
+    }
+}
+
+impl Serialize for BigInteger {
+    fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        writer.write_all(&self.0)?;
+        Ok(self.0.len())
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// An `OCTET STRING`, in the primitive form.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct OctetString(pub Vec<u8>);
+
+impl OctetString {
+    fn decode(contents: &[u8], _limiter: &Limiter) -> crate::Result<Self> {
+        Ok(Self(contents.to_vec()))
+    }
+}
+
+impl Serialize for OctetString {
+    fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        writer.write_all(&self.0)?;
+        Ok(self.0.len())
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// A `NULL`, whose contents are empty.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Null;
+
+impl Null {
+    fn decode(contents: &[u8], _limiter: &Limiter) -> crate::Result<Self> {
+        match contents.is_empty() {
+            true => Ok(Self),
+            false => Err(ErrorKind::LengthMismatch.into()),
+        }
+    }
+}
+
+impl Serialize for Null {
+    fn serialize<W>(&self, _writer: &mut W) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        Ok(0)
+    }
+
+    fn serialized_length(&self) -> usize {
+        0
+    }
+}
+
+/// An `OBJECT IDENTIFIER`, as its arcs, e.g. `[1, 2, 840, 113549]`.
+///
+/// There are at least two arcs, the first one is 0, 1 or 2 and, unless it
+/// is 2, the second one is below 40. Other identifiers cannot be written.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ObjectIdentifier(pub Vec<u64>);
+
+impl ObjectIdentifier {
+    /// The arcs as encoded, the first two of them combined into one.
+    fn subidentifiers(&self) -> crate::Result<impl Iterator<Item = u64> + '_> {
+        let first = match self.0[..] {
+            [first @ 0..=1, second @ 0..=39, ..] => first * 40 + second,
+            [2, second, ..] => second.checked_add(80).ok_or(ErrorKind::OutOfRange)?,
+            _ => return Err(ErrorKind::OutOfRange.into()),
+        };
+
+        Ok(core::iter::once(first).chain(self.0[2..].iter().copied()))
+    }
+
+    /// Number of bytes of a subidentifier, seven bits per byte.
+    fn size(subidentifier: u64) -> usize {
+        (64 - (subidentifier | 1).leading_zeros() as usize).div_ceil(7)
+    }
+
+    fn decode(contents: &[u8], limiter: &Limiter) -> crate::Result<Self> {
+        if contents.is_empty() {
+            return Err(ErrorKind::Truncated.into());
+        }
+
+        let mut arcs = Vec::new();
+        let mut subidentifier: u64 = 0;
+        let mut start = true;
+        for byte in contents {
+            // a subidentifier padded with a leading zero group
+            if start && *byte == 0x80 {
+                return Err(ErrorKind::NonCanonical.into());
+            }
+            if subidentifier >> 57 != 0 {
+                return Err(ErrorKind::OutOfRange.into());
+            }
+
+            subidentifier = subidentifier << 7 | u64::from(byte & 0x7f);
+            start = byte & 0x80 == 0;
+            if !start {
+                continue;
+            }
+
+            match arcs.is_empty() {
+                true => arcs.extend(match subidentifier {
+                    0..=39 => [0, subidentifier],
+                    40..=79 => [1, subidentifier - 40],
+                    _ => [2, subidentifier - 80],
+                }),
+                false => arcs.push(subidentifier),
+            }
+            limiter.check_elements(arcs.len())?;
+            subidentifier = 0;
+        }
+
+        match start {
+            true => Ok(Self(arcs)),
+            false => Err(ErrorKind::Truncated.into()),
+        }
+    }
+}
+
+impl Serialize for ObjectIdentifier {
+    fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        let mut written = 0;
+        for subidentifier in self.subidentifiers()? {
+            let mut buf = [0; 10];
+            let size = Self::size(subidentifier);
+            for (i, byte) in buf[..size].iter_mut().enumerate() {
+                let more = if i + 1 < size { 0x80 } else { 0 };
+                *byte = (subidentifier >> (7 * (size - 1 - i))) as u8 & 0x7f | more;
+            }
+            writer.write_all(&buf[..size])?;
+            written += size;
+        }
+        Ok(written)
+    }
+
+    fn serialized_length(&self) -> usize {
+        match self.subidentifiers() {
+            Ok(subidentifiers) => subidentifiers.map(Self::size).sum(),
+            Err(_) => 0,
+        }
+    }
+}
+
+/// A `UTF8String`.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Utf8String(pub String);
+
+impl Utf8String {
+    fn decode(contents: &[u8], _limiter: &Limiter) -> crate::Result<Self> {
+        match core::str::from_utf8(contents) {
+            Ok(string) => Ok(Self(string.into())),
+            Err(_) => Err(ErrorKind::InvalidUtf8.into()),
+        }
+    }
+}
+
+impl Serialize for Utf8String {
+    fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        writer.write_all(self.0.as_bytes())?;
+        Ok(self.0.len())
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.0.len()
+    }
+}
+
+/// A `SEQUENCE OF`, the records of its elements in order.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct SequenceOf<T>(pub Vec<T>);
+
+/// A `SET OF`, the records of its elements in increasing order of their
+/// encodings, whatever the order of the `Vec`. Elements decoded in another
+/// order are [`ErrorKind::NonCanonical`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct SetOf<T>(pub Vec<T>);
+
+impl<T> Tagged for SequenceOf<T> {
+    const TAG: u64 = 0x30;
+}
+
+impl<T> Tagged for SetOf<T> {
+    const TAG: u64 = 0x31;
+}
+
+fn elements_length<T>(elements: &[T]) -> usize
+where
+    T: Tagged + Serialize,
+{
+    elements.iter().fold(0, |len, element| {
+        len.saturating_add(serialized_length(element))
+    })
+}
+
+impl<T> Serialize for SequenceOf<T>
+where
+    T: Tagged + Serialize,
+{
+    fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        let mut written = 0;
+        for element in &self.0 {
+            written += serialize(element, writer).map_err(|err| err.advance(written as u64))?;
+        }
+        Ok(written)
+    }
+
+    fn serialized_length(&self) -> usize {
+        elements_length(&self.0)
+    }
+}
+
+impl<T> Serialize for SetOf<T>
+where
+    T: Tagged + Serialize,
+{
+    fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        let mut encodings = self
+            .0
+            .iter()
+            .map(|element| {
+                let mut encoding = Vec::with_capacity(serialized_length(element));
+                serialize(element, &mut encoding).map(|_| encoding)
+            })
+            .collect::<crate::Result<Vec<_>>>()?;
+        encodings.sort();
+
+        let mut written = 0;
+        for encoding in encodings {
+            writer.write_all(&encoding)?;
+            written += encoding.len();
+        }
+        Ok(written)
+    }
+
+    fn serialized_length(&self) -> usize {
+        elements_length(&self.0)
+    }
+}
+
+/// Checks that the record `field` of an element of a `SET OF` does not
+/// sort before the `previous` one, which it then replaces.
+fn check_order(previous: &mut Vec<u8>, id: u64, value: &[u8]) -> crate::Result<()> {
+    let mut encoding = Vec::with_capacity(LAYOUT.record_length(id, value.len()));
+    LAYOUT.write_raw(&mut encoding, id, value)?;
+    match encoding >= *previous {
+        true => {
+            *previous = encoding;
+            Ok(())
+        }
+        false => Err(ErrorKind::NonCanonical.into()),
+    }
+}
+
+/// Decodes the elements of a `SEQUENCE OF` or, when `ordered`, of a
+/// `SET OF`.
+fn decode_elements<T, R>(
+    reader: &mut R,
+    limiter: &mut Limiter,
+    ordered: bool,
+) -> crate::Result<Vec<T>>
+where
+    T: Tagged + Deserialize,
+    R: crate::io::Read,
+{
+    let records = crate::extract_der(reader, &LAYOUT)?;
+    limiter.check_elements(records.len())?;
+
+    let mut previous = Vec::new();
+    let mut elements = Vec::with_capacity(records.len());
+    for record in records {
+        if record.id != T::TAG {
+            return Err(Error::new(ErrorKind::UnexpectedTag)
+                .with_tag(record.id)
+                .with_offset(record.offset));
+        }
+        if ordered {
+            check_order(&mut previous, record.id, &record.value)
+                .map_err(|err| err.with_tag(record.id).with_offset(record.offset))?;
+        }
+        elements.push(record.decode_limited(core::any::type_name::<T>(), limiter)?);
+    }
+    Ok(elements)
+}
+
+/// Decodes the elements of a `SEQUENCE OF` or of a `SET OF` like
+/// [`decode_elements`], borrowing from `input`.
+fn borrow_elements<'de, T>(
+    input: &mut &'de [u8],
+    limiter: &mut Limiter,
+    ordered: bool,
+) -> crate::Result<Vec<T>>
+where
+    T: Tagged + BorrowDeserialize<'de>,
+{
+    let records = crate::borrow_der(input, &LAYOUT)?;
+    limiter.check_elements(records.len())?;
+
+    let mut previous = Vec::new();
+    let mut elements = Vec::with_capacity(records.len());
+    for record in records {
+        let BorrowedField { id, value, offset } = record;
+        if id != T::TAG {
+            return Err(Error::new(ErrorKind::UnexpectedTag)
+                .with_tag(id)
+                .with_offset(offset));
+        }
+        if ordered {
+            check_order(&mut previous, id, value)
+                .map_err(|err| err.with_tag(id).with_offset(offset))?;
+        }
+        elements.push(record.decode_limited(core::any::type_name::<T>(), limiter)?);
+    }
+    Ok(elements)
+}
+
+macro_rules! impl_elements {
+    ($($ty:ident: $ordered:expr;)*) => {
+        $(
+            impl<T> Deserialize for $ty<T>
+            where
+                T: Tagged + Deserialize,
+            {
+                fn deserialize<R>(reader: &mut R) -> crate::Result<Self>
+                where
+                    R: crate::io::Read,
+                {
+                    deserialize_with_limits(reader, DecodeLimits::DEFAULT)
+                }
+
+                fn deserialize_limited<R>(reader: &mut R, limiter: &mut Limiter) -> crate::Result<Self>
+                where
+                    R: crate::io::Read,
+                {
+                    decode_elements(reader, limiter, $ordered).map(Self)
+                }
+            }
+
+            impl<'de, T> BorrowDeserialize<'de> for $ty<T>
+            where
+                T: Tagged + BorrowDeserialize<'de>,
+            {
+                fn borrow_deserialize(input: &mut &'de [u8]) -> crate::Result<Self> {
+                    Self::borrow_deserialize_limited(input, &mut Limiter::default())
+                }
+
+                fn borrow_deserialize_limited(
+                    input: &mut &'de [u8],
+                    limiter: &mut Limiter,
+                ) -> crate::Result<Self> {
+                    borrow_elements(input, limiter, $ordered).map(Self)
+                }
+            }
+        )*
+    };
+}
+
+impl_elements! {
+    SequenceOf: false;
+    SetOf: true;
+}
+
+/// The contents of an explicitly tagged value: the whole record of the
+/// value, with its own tag.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Explicit<T>(pub T);
+
+impl<T> Serialize for Explicit<T>
+where
+    T: Tagged + Serialize,
+{
+    fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        serialize(&self.0, writer)
+    }
+
+    fn serialized_length(&self) -> usize {
+        serialized_length(&self.0)
+    }
+}
+
+impl<T> Deserialize for Explicit<T>
+where
+    T: Tagged + Deserialize,
+{
+    fn deserialize<R>(reader: &mut R) -> crate::Result<Self>
+    where
+        R: crate::io::Read,
+    {
+        deserialize_with_limits(reader, DecodeLimits::DEFAULT)
+    }
+
+    fn deserialize_limited<R>(reader: &mut R, limiter: &mut Limiter) -> crate::Result<Self>
+    where
+        R: crate::io::Read,
+    {
+        let mut records = crate::extract_der(reader, &LAYOUT)?;
+        let record = records
+            .take_tagged(T::TAG)
+            .ok_or_else(|| Error::new(ErrorKind::MissingTag).with_tag(T::TAG))?;
+        records.finish()?;
+
+        record
+            .decode_limited(core::any::type_name::<T>(), limiter)
+            .map(Self)
+    }
+}
+
+impl<'de, T> BorrowDeserialize<'de> for Explicit<T>
+where
+    T: Tagged + BorrowDeserialize<'de>,
+{
+    fn borrow_deserialize(input: &mut &'de [u8]) -> crate::Result<Self> {
+        Self::borrow_deserialize_limited(input, &mut Limiter::default())
+    }
+
+    fn borrow_deserialize_limited(
+        input: &mut &'de [u8],
+        limiter: &mut Limiter,
+    ) -> crate::Result<Self> {
+        let mut records = crate::borrow_der(input, &LAYOUT)?;
+        let record = records
+            .take_tagged(T::TAG)
+            .ok_or_else(|| Error::new(ErrorKind::MissingTag).with_tag(T::TAG))?;
+        records.finish()?;
+
+        record
+            .decode_limited(core::any::type_name::<T>(), limiter)
+            .map(Self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    fn encoded<T: Tagged + Serialize>(value: &T) -> Vec<u8> {
+        let mut buf = Vec::new();
+        let written = serialize(value, &mut buf).unwrap();
+        assert_eq!(written, buf.len());
+        assert_eq!(serialized_length(value), buf.len());
+        buf
+    }
+
+    fn decoded<T: Tagged + Deserialize>(input: &[u8]) -> crate::Result<T> {
+        deserialize(&mut &input[..])
+    }
+
+    #[test]
+    fn test_integers() {
+        for (value, expected) in [
+            (0, &[0x02, 0x01, 0x00][..]),
+            (127, &[0x02, 0x01, 0x7f]),
+            (128, &[0x02, 0x02, 0x00, 0x80]),
+            (256, &[0x02, 0x02, 0x01, 0x00]),
+            (-1, &[0x02, 0x01, 0xff]),
+            (-128, &[0x02, 0x01, 0x80]),
+            (-129, &[0x02, 0x02, 0xff, 0x7f]),
+        ] {
+            assert_eq!(encoded(&Integer(value)), expected, "{value}");
+            assert_eq!(decoded::<Integer>(expected).unwrap(), Integer(value));
+        }
+        assert_eq!(encoded(&Integer(i128::MIN)).len(), 18);
+
+        for (input, kind) in [
+            (&[0x02, 0x02, 0x00, 0x7f][..], ErrorKind::NonCanonical),
+            (&[0x02, 0x02, 0xff, 0x80], ErrorKind::NonCanonical),
+            (&[0x02, 0x00], ErrorKind::Truncated),
+            (
+                &[
+                    0x02, 0x11, 0x01, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+                ],
+                ErrorKind::OutOfRange,
+            ),
+            (&[0x04, 0x01, 0x00], ErrorKind::MissingTag),
+        ] {
+            let err = decoded::<Integer>(input).unwrap_err();
+            assert_eq!(err.kind(), kind, "{input:x?}");
+        }
+    }
+
+    #[test]
+    fn test_big_integers() {
+        // the serial number of a certificate, 20 bytes
+        let mut serial = vec![0x02, 0x14, 0x7f];
+        serial.extend([0xee; 19]);
+        let integer = decoded::<BigInteger>(&serial).unwrap();
+        assert_eq!(integer.as_bytes(), &serial[2..]);
+        assert!(!integer.is_negative());
+        assert_eq!(encoded(&integer), serial);
+        let err = Integer::try_from(&integer).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::OutOfRange);
+
+        for value in [0, 128, -129, i128::MIN] {
+            let integer = BigInteger::from(Integer(value));
+            assert_eq!(encoded(&integer), encoded(&Integer(value)), "{value}");
+            assert_eq!(Integer::try_from(&integer).unwrap(), Integer(value));
+        }
+        assert!(BigInteger::from(Integer(-1)).is_negative());
+
+        for (input, kind) in [
+            (&[0x02, 0x02, 0x00, 0x7f][..], ErrorKind::NonCanonical),
+            (&[0x02, 0x02, 0xff, 0x80], ErrorKind::NonCanonical),
+            (&[0x02, 0x00], ErrorKind::Truncated),
+        ] {
+            let err = decoded::<BigInteger>(input).unwrap_err();
+            assert_eq!(err.kind(), kind, "{input:x?}");
+        }
+        let err = BigInteger::new(vec![0x00, 0x01]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NonCanonical);
+    }
+
+    #[test]
+    fn test_primitives() {
+        assert_eq!(encoded(&Boolean(true)), [0x01, 0x01, 0xff]);
+        assert_eq!(encoded(&Null), [0x05, 0x00]);
+        assert_eq!(encoded(&Utf8String("é".into())), [0x0c, 0x02, 0xc3, 0xa9]);
+        assert_eq!(encoded(&OctetString(vec![1, 2])), [0x04, 0x02, 0x01, 0x02]);
+
+        // BER allows any non-zero byte for true, DER only `ff`
+        let err = decoded::<Boolean>(&[0x01, 0x01, 0x01]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NonCanonical);
+        let err = decoded::<Null>(&[0x05, 0x01, 0x00]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LengthMismatch);
+        let err = decoded::<Utf8String>(&[0x0c, 0x01, 0xff]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidUtf8);
+        // a long-form length for a short value
+        let err = decoded::<OctetString>(&[0x04, 0x81, 0x01, 0x00]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NonCanonical);
+        // a constructed octet string
+        let err = decoded::<OctetString>(&[0x24, 0x03, 0x04, 0x01, 0x00]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::MissingTag);
+        // trailing bytes after the value
+        let err = decoded::<Null>(&[0x05, 0x00, 0x05, 0x00]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedTag);
+    }
+
+    #[test]
+    fn test_object_identifiers() {
+        // rsaEncryption
+        let rsa = ObjectIdentifier(vec![1, 2, 840, 113549, 1, 1, 1]);
+        let expected = [
+            0x06, 0x09, 0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x01,
+        ];
+        assert_eq!(encoded(&rsa), expected);
+        assert_eq!(decoded::<ObjectIdentifier>(&expected).unwrap(), rsa);
+
+        let joint = ObjectIdentifier(vec![2, 999, 3]);
+        assert_eq!(encoded(&joint), [0x06, 0x03, 0x88, 0x37, 0x03]);
+        assert_eq!(
+            decoded::<ObjectIdentifier>(&[0x06, 0x03, 0x88, 0x37, 0x03]).unwrap(),
+            joint
+        );
+
+        for invalid in [vec![1], vec![3, 1], vec![1, 40]] {
+            let err = serialize(&ObjectIdentifier(invalid), &mut Vec::new()).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::OutOfRange);
+        }
+
+        for (input, kind) in [
+            (&[0x06, 0x02, 0x2a, 0x86][..], ErrorKind::Truncated),
+            (&[0x06, 0x03, 0x2a, 0x80, 0x01], ErrorKind::NonCanonical),
+            (&[0x06, 0x00], ErrorKind::Truncated),
+        ] {
+            let err = decoded::<ObjectIdentifier>(input).unwrap_err();
+            assert_eq!(err.kind(), kind, "{input:x?}");
+        }
+    }
+
+    #[test]
+    fn test_collections() {
+        let sequence = SequenceOf(vec![Integer(2), Integer(1)]);
+        let expected = [0x30, 0x06, 0x02, 0x01, 0x02, 0x02, 0x01, 0x01];
+        assert_eq!(encoded(&sequence), expected);
+        assert_eq!(decoded::<SequenceOf<Integer>>(&expected).unwrap(), sequence);
+
+        // sorted when written, and required to be when read
+        let set = SetOf(vec![Integer(2), Integer(1)]);
+        let expected = [0x31, 0x06, 0x02, 0x01, 0x01, 0x02, 0x01, 0x02];
+        assert_eq!(encoded(&set), expected);
+        let decoded_set = decoded::<SetOf<Integer>>(&expected).unwrap();
+        assert_eq!(decoded_set, SetOf(vec![Integer(1), Integer(2)]));
+        let unsorted = [0x31, 0x06, 0x02, 0x01, 0x02, 0x02, 0x01, 0x01];
+        let err = decoded::<SetOf<Integer>>(&unsorted).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NonCanonical);
+
+        let mixed = [0x30, 0x05, 0x02, 0x01, 0x02, 0x05, 0x00];
+        let err = decoded::<SequenceOf<Integer>>(&mixed).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedTag);
+        assert_eq!(err.tag(), Some(0x05));
+    }
+
+    #[test]
+    fn test_tags() {
+        assert_eq!(implicit::<Integer>(0), 0x80);
+        assert_eq!(implicit::<SequenceOf<Integer>>(1), 0xa1);
+        assert_eq!(explicit(3), 0xa3);
+        assert_eq!(explicit(31), 0xbf1f);
+
+        check_tag(0xbf1f).unwrap();
+        let err = check_tag(0x9f02).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NonCanonical);
+
+        let mut buf = Vec::new();
+        serialize_explicit(&mut buf, explicit(0), &Integer(2)).unwrap();
+        assert_eq!(buf, [0xa0, 0x03, 0x02, 0x01, 0x02]);
+        assert_eq!(explicit_length(explicit(0), &Integer(2)), buf.len());
+    }
+}
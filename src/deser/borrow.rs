@@ -0,0 +1,394 @@
+//! Deserialization borrowing from the input instead of copying out of it.
+//!
+//! Everything here mirrors its owned counterpart in the parent module, but
+//! reads from a `&'de [u8]`, so records and the byte and string values in
+//! them can point into the input.
+
+#[cfg(feature = "alloc")]
+use alloc::collections::BTreeMap;
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::{Error, ErrorKind, Layout, Limiter};
+#[cfg(feature = "alloc")]
+use crate::{Extracted, RawField};
+
+/// Decodes a value that may borrow from the input it is decoded from.
+///
+/// `input` is advanced past the value, just like a reader would be. Limits
+/// are handled as for [`Deserialize`](crate::Deserialize), except for the
+/// total number of bytes: the input is already in memory.
+pub trait BorrowDeserialize<'de>: Sized {
+    fn borrow_deserialize(input: &mut &'de [u8]) -> crate::Result<Self>;
+
+    /// Decodes a value nested in another one, within the limits `limiter`
+    /// keeps track of. The default implementation ignores them.
+    fn borrow_deserialize_limited(
+        input: &mut &'de [u8],
+        _limiter: &mut Limiter,
+    ) -> crate::Result<Self> {
+        Self::borrow_deserialize(input)
+    }
+}
+
+/// A record whose value points into the input, see [`RawField`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BorrowedField<'de> {
+    pub id: u64,
+    pub value: &'de [u8],
+    /// Position of the value in the input the record was extracted from.
+    pub offset: u64,
+}
+
+impl<'de> BorrowedField<'de> {
+    /// Decodes the value of the record as the field named `field`.
+    ///
+    /// The whole value has to be consumed, errors are reported in the
+    /// context of the record.
+    pub fn decode<T>(&self, field: &'static str) -> crate::Result<T>
+    where
+        T: BorrowDeserialize<'de>,
+    {
+        self.decode_limited(field, &mut Limiter::default())
+    }
+
+    /// Decodes the value of the record like [`decode`](Self::decode), one
+    /// level deeper than `limiter`.
+    pub fn decode_limited<T>(&self, field: &'static str, limiter: &mut Limiter) -> crate::Result<T>
+    where
+        T: BorrowDeserialize<'de>,
+    {
+        self.decode_with(field, limiter, T::borrow_deserialize_limited)
+    }
+
+    /// Decodes the value of the record with `decode`, one level deeper than
+    /// `limiter`.
+    pub fn decode_with<T, F>(
+        &self,
+        field: &'static str,
+        limiter: &mut Limiter,
+        decode: F,
+    ) -> crate::Result<T>
+    where
+        F: FnOnce(&mut &'de [u8], &mut Limiter) -> crate::Result<T>,
+    {
+        let mut value = self.value;
+
+        limiter
+            .nested(|limiter| decode(&mut value, limiter))
+            .and_then(|decoded| match value.len() {
+                0 => Ok(decoded),
+                _ => Err(Error::new(ErrorKind::LengthMismatch)
+                    .with_offset((self.value.len() - value.len()) as u64)),
+            })
+            .map_err(|err| err.within(self.id, field, self.offset))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl From<BorrowedField<'_>> for RawField {
+    fn from(field: BorrowedField<'_>) -> Self {
+        RawField {
+            offset: field.offset,
+            ..RawField::new(field.id, field.value.len(), field.value.to_vec())
+        }
+    }
+}
+
+/// Splits the first `len` bytes off `input`.
+fn take<'de>(input: &mut &'de [u8], len: usize) -> crate::Result<&'de [u8]> {
+    if len > input.len() {
+        return Err(ErrorKind::Truncated.into());
+    }
+
+    let (value, rest) = input.split_at(len);
+    *input = rest;
+    Ok(value)
+}
+
+/// Reads the length and the value of a record whose tag was just read,
+/// `start` is the length of the whole input and `record` the position of
+/// the record in it.
+fn read_record<'de>(
+    input: &mut &'de [u8],
+    start: usize,
+    record: u64,
+    layout: &Layout,
+    id: u64,
+) -> crate::Result<BorrowedField<'de>> {
+    let len = layout
+        .read_length(input)
+        .map_err(|err| err.with_tag(id).with_offset(record))?;
+    let offset = (start - input.len()) as u64;
+    let value = take(input, len).map_err(|err| err.with_tag(id).with_offset(offset))?;
+
+    Ok(BorrowedField { id, value, offset })
+}
+
+/// Reads a single record, whatever its tag, see
+/// [`extract_record`](crate::extract_record).
+pub fn borrow_record<'de>(
+    input: &mut &'de [u8],
+    layout: &Layout,
+) -> crate::Result<BorrowedField<'de>> {
+    let start = input.len();
+    let id = layout.read_tag(input).map_err(|err| err.with_offset(0))?;
+
+    read_record(input, start, 0, layout, id)
+}
+
+/// Reads one record for each of `tags`, in any order, see
+/// [`extract_raw`](crate::extract_raw).
+#[cfg(feature = "alloc")]
+pub fn borrow_raw<'de, const LENGTH: usize>(
+    input: &mut &'de [u8],
+    layout: &Layout,
+    tags: [u64; LENGTH],
+) -> crate::Result<BTreeMap<u64, BorrowedField<'de>>> {
+    let start = input.len();
+    let mut gathered = BTreeMap::new();
+
+    for _ in tags {
+        let offset = (start - input.len()) as u64;
+        let id = layout
+            .read_tag(input)
+            .map_err(|err| err.with_offset(offset))?;
+
+        if !tags.contains(&id) {
+            return Err(Error::new(ErrorKind::UnexpectedTag)
+                .with_tag(id)
+                .with_offset(offset));
+        }
+        if gathered.contains_key(&id) {
+            return Err(Error::new(ErrorKind::DuplicateTag)
+                .with_tag(id)
+                .with_offset(offset));
+        }
+
+        gathered.insert(id, read_record(input, start, offset, layout, id)?);
+    }
+
+    Ok(gathered)
+}
+
+/// Reads records until the input is exhausted, see
+/// [`extract_stream`](crate::extract_stream).
+#[cfg(feature = "alloc")]
+pub fn borrow_stream<'de>(
+    input: &mut &'de [u8],
+    layout: &Layout,
+    tags: &[u64],
+) -> crate::Result<Extracted<BorrowedField<'de>>> {
+    read_stream(input, layout, false, |id, _| Ok(tags.contains(&id)))
+}
+
+/// Reads records until the input is exhausted, keeping those with unknown
+/// tags, see [`extract_stream_with_unknown`](crate::extract_stream_with_unknown).
+#[cfg(feature = "alloc")]
+pub fn borrow_stream_with_unknown<'de>(
+    input: &mut &'de [u8],
+    layout: &Layout,
+    tags: &[u64],
+) -> crate::Result<Extracted<BorrowedField<'de>>> {
+    read_stream(input, layout, true, |id, _| Ok(tags.contains(&id)))
+}
+
+/// Reads records until the input is exhausted, failing at the first one
+/// with an unknown tag, see
+/// [`extract_stream_deny_unknown`](crate::extract_stream_deny_unknown).
+#[cfg(feature = "alloc")]
+pub fn borrow_stream_deny_unknown<'de>(
+    input: &mut &'de [u8],
+    layout: &Layout,
+    tags: &[u64],
+) -> crate::Result<Extracted<BorrowedField<'de>>> {
+    read_stream(input, layout, false, |id, start| {
+        crate::deser::deny_unknown(tags.contains(&id), id, start)
+    })
+}
+
+/// Reads a BOLT #1 TLV stream until the input is exhausted, see
+/// [`extract_bolt`](crate::extract_bolt).
+#[cfg(feature = "alloc")]
+pub fn borrow_bolt<'de>(
+    input: &mut &'de [u8],
+    layout: &Layout,
+    tags: &[u64],
+) -> crate::Result<Extracted<BorrowedField<'de>>> {
+    let mut types = crate::bolt::Types::new(tags);
+    read_stream(input, layout, false, |id, start| types.check(id, start))
+}
+
+/// Reads a BOLT #1 TLV stream until the input is exhausted, keeping the
+/// records of unknown odd types, see
+/// [`extract_bolt_with_unknown`](crate::extract_bolt_with_unknown).
+#[cfg(feature = "alloc")]
+pub fn borrow_bolt_with_unknown<'de>(
+    input: &mut &'de [u8],
+    layout: &Layout,
+    tags: &[u64],
+) -> crate::Result<Extracted<BorrowedField<'de>>> {
+    let mut types = crate::bolt::Types::new(tags);
+    read_stream(input, layout, true, |id, start| types.check(id, start))
+}
+
+/// Reads a BOLT #1 TLV stream until the input is exhausted, failing at the
+/// first record of an unknown type, see
+/// [`extract_bolt_deny_unknown`](crate::extract_bolt_deny_unknown).
+#[cfg(feature = "alloc")]
+pub fn borrow_bolt_deny_unknown<'de>(
+    input: &mut &'de [u8],
+    layout: &Layout,
+    tags: &[u64],
+) -> crate::Result<Extracted<BorrowedField<'de>>> {
+    let mut types = crate::bolt::Types::new(tags);
+    read_stream(input, layout, false, |id, start| {
+        crate::deser::deny_unknown(types.check(id, start)?, id, start)
+    })
+}
+
+/// Reads the records of an ASN.1 DER value until the input is exhausted,
+/// see [`extract_der`](crate::extract_der).
+#[cfg(feature = "alloc")]
+pub fn borrow_der<'de>(
+    input: &mut &'de [u8],
+    layout: &Layout,
+) -> crate::Result<crate::der::Records<BorrowedField<'de>>> {
+    let start = input.len();
+    let mut records = crate::der::Records::new();
+
+    while !input.is_empty() {
+        let offset = (start - input.len()) as u64;
+        let id = layout
+            .read_tag(input)
+            .map_err(|err| err.with_offset(offset))?;
+        crate::der::check_tag(id).map_err(|err| err.with_tag(id).with_offset(offset))?;
+
+        let field = read_record(input, start, offset, layout, id)?;
+        crate::der::check_length(id, offset, field.value.len(), field.offset)
+            .map_err(|err| err.with_tag(id).with_offset(offset))?;
+        records.push(id, offset, field);
+    }
+
+    Ok(records)
+}
+
+/// Reads records until the input is exhausted, borrowing those for which
+/// `known` returns `true` and skipping the others, or borrowing them as
+/// unknown if `keep` is set.
+#[cfg(feature = "alloc")]
+fn read_stream<'de>(
+    input: &mut &'de [u8],
+    layout: &Layout,
+    keep: bool,
+    mut known: impl FnMut(u64, u64) -> crate::Result<bool>,
+) -> crate::Result<Extracted<BorrowedField<'de>>> {
+    let start = input.len();
+    let mut extracted = Extracted {
+        fields: BTreeMap::new(),
+        skipped: Vec::new(),
+        unknown: Vec::new(),
+    };
+
+    while !input.is_empty() {
+        let offset = (start - input.len()) as u64;
+        let id = layout
+            .read_tag(input)
+            .map_err(|err| err.with_offset(offset))?;
+
+        if !known(id, offset)? {
+            let field = read_record(input, start, offset, layout, id)?;
+            match keep {
+                true => extracted.unknown.push(field),
+                false => extracted.skipped.push(id),
+            }
+            continue;
+        }
+
+        if extracted.fields.contains_key(&id) {
+            return Err(Error::new(ErrorKind::DuplicateTag)
+                .with_tag(id)
+                .with_offset(offset));
+        }
+
+        let field = read_record(input, start, offset, layout, id)?;
+        extracted.fields.insert(id, field);
+    }
+
+    Ok(extracted)
+}
+
+/// Reads bytes written by [`serialize_bytes`](crate::serialize_bytes)
+/// without copying them.
+pub fn borrow_bytes<'de>(input: &mut &'de [u8]) -> crate::Result<&'de [u8]> {
+    borrow_bytes_limited(input, &mut Limiter::default())
+}
+
+/// Reads bytes like [`borrow_bytes`], checking their length against the
+/// limits of `limiter`.
+pub fn borrow_bytes_limited<'de>(
+    input: &mut &'de [u8],
+    limiter: &mut Limiter,
+) -> crate::Result<&'de [u8]> {
+    let len = u32::borrow_deserialize_limited(input, limiter)? as usize;
+    limiter.check_length(len)?;
+    take(input, len)
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_borrow_raw() {
+        let buf = [2, 1, 0, 7, 1, 0, 0, 9];
+        let mut input = buf.as_slice();
+
+        let fields = borrow_raw(&mut input, &Layout::DEFAULT, [1, 2]).unwrap();
+
+        assert_eq!(fields[&2].value, [7]);
+        assert_eq!(fields[&2].offset, 3);
+        assert_eq!(fields[&1].offset, 7);
+        assert!(std::ptr::eq(fields[&2].value, &buf[3..4]));
+        assert_eq!(input, [9]);
+
+        let err = borrow_raw(&mut [1, 2, 0, 7].as_slice(), &Layout::DEFAULT, [1]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Truncated);
+        assert_eq!(err.tag(), Some(1));
+        assert_eq!(err.offset(), Some(3));
+    }
+
+    #[test]
+    fn test_borrow_stream() {
+        let buf = [3, 1, 0, 0, 1, 1, 0, 5];
+
+        let extracted = borrow_stream(&mut buf.as_slice(), &Layout::DEFAULT, &[1, 2]).unwrap();
+
+        assert_eq!(extracted.fields[&1].value, [5]);
+        assert!(!extracted.fields.contains_key(&2));
+        assert_eq!(extracted.skipped, [3]);
+
+        let err = borrow_stream(&mut &buf[..5], &Layout::DEFAULT, &[1, 2]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Truncated);
+        assert_eq!(err.tag(), Some(1));
+        assert_eq!(err.offset(), Some(4));
+    }
+
+    #[test]
+    fn test_borrowed_field() {
+        let buf = [4, 6, 0, 2, 0, 0, 0, b'h', b'i'];
+        let field = borrow_record(&mut buf.as_slice(), &Layout::DEFAULT).unwrap();
+
+        let value: &str = field.decode("value").unwrap();
+        assert_eq!(value, "hi");
+        assert!(std::ptr::eq(value.as_bytes(), &buf[7..]));
+
+        let err = field.decode::<u8>("value").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LengthMismatch);
+        assert_eq!(err.offset(), Some(4));
+
+        let raw = RawField::from(field);
+        assert_eq!(raw.value, &buf[3..]);
+        assert_eq!(raw.offset, 3);
+    }
+}
@@ -0,0 +1,318 @@
+//! Bounds on what decoding untrusted input may cost.
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+use crate::deser::Deserialize;
+use crate::{ByteOrder, Config, Error, ErrorKind};
+
+/// Limits enforced while decoding, so that a short input cannot make the
+/// decoder allocate, read or recurse without bound.
+///
+/// Lengths and counts read from the input are checked before anything is
+/// allocated for them, and values are read in chunks as they arrive rather
+/// than into a buffer of the announced size.
+///
+/// ```
+/// use tlv::{DecodeLimits, ErrorKind};
+///
+/// let limits = DecodeLimits::DEFAULT.with_max_total_bytes(4);
+/// let input = [0; 8];
+///
+/// let err = tlv::deserialize_with_limits::<u64, _>(&mut &input[..], limits).unwrap_err();
+/// assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DecodeLimits {
+    /// Number of bytes read from the reader for the whole value.
+    pub max_total_bytes: u64,
+    /// Number of elements of a single collection.
+    pub max_elements: usize,
+    /// Length of a single string or byte string.
+    pub max_string_length: usize,
+    /// Nesting of records and collections within each other.
+    pub max_depth: usize,
+}
+
+impl DecodeLimits {
+    /// Limits used by [`Deserialize::deserialize`].
+    pub const DEFAULT: Self = Self {
+        max_total_bytes: 64 * 1024 * 1024,
+        max_elements: 1 << 24,
+        max_string_length: 16 * 1024 * 1024,
+        max_depth: 64,
+    };
+
+    pub const fn with_max_total_bytes(self, max_total_bytes: u64) -> Self {
+        Self {
+            max_total_bytes,
+            ..self
+        }
+    }
+
+    pub const fn with_max_elements(self, max_elements: usize) -> Self {
+        Self {
+            max_elements,
+            ..self
+        }
+    }
+
+    pub const fn with_max_string_length(self, max_string_length: usize) -> Self {
+        Self {
+            max_string_length,
+            ..self
+        }
+    }
+
+    pub const fn with_max_depth(self, max_depth: usize) -> Self {
+        Self { max_depth, ..self }
+    }
+}
+
+impl Default for DecodeLimits {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// State of a decode checked against [`DecodeLimits`], handed down to
+/// [`Deserialize::deserialize_limited`]. It also carries the byte order
+/// integers are read in.
+#[derive(Debug, Clone)]
+pub struct Limiter {
+    limits: DecodeLimits,
+    depth: usize,
+    byte_order: ByteOrder,
+}
+
+impl Limiter {
+    /// A little-endian decode within `limits`.
+    pub const fn new(limits: DecodeLimits) -> Self {
+        Self {
+            limits,
+            depth: 0,
+            byte_order: ByteOrder::Little,
+        }
+    }
+
+    /// A decode as `config` asks.
+    pub const fn from_config(config: &Config) -> Self {
+        Self::new(config.limits).with_byte_order(config.byte_order)
+    }
+
+    /// Returns the same state, reading integers in `byte_order`.
+    pub const fn with_byte_order(self, byte_order: ByteOrder) -> Self {
+        Self { byte_order, ..self }
+    }
+
+    pub fn limits(&self) -> &DecodeLimits {
+        &self.limits
+    }
+
+    pub fn byte_order(&self) -> ByteOrder {
+        self.byte_order
+    }
+
+    /// Number of records and collections being decoded around the current
+    /// value.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// Checks the number of elements a collection announces.
+    pub fn check_elements(&self, count: usize) -> crate::Result<()> {
+        match count <= self.limits.max_elements {
+            true => Ok(()),
+            false => Err(ErrorKind::LimitExceeded.into()),
+        }
+    }
+
+    /// Checks the length a string or a byte string announces.
+    pub fn check_length(&self, len: usize) -> crate::Result<()> {
+        match len <= self.limits.max_string_length {
+            true => Ok(()),
+            false => Err(ErrorKind::LimitExceeded.into()),
+        }
+    }
+
+    /// Runs `decode` one level deeper, failing if that is deeper than
+    /// allowed.
+    pub fn nested<T>(
+        &mut self,
+        decode: impl FnOnce(&mut Self) -> crate::Result<T>,
+    ) -> crate::Result<T> {
+        self.enter()?;
+        let decoded = decode(self);
+        self.leave();
+        decoded
+    }
+
+    /// Goes one level deeper, failing if that is deeper than allowed. Every
+    /// successful call has to be matched by a call to [`leave`](Self::leave),
+    /// [`nested`](Self::nested) does both around a closure.
+    pub fn enter(&mut self) -> crate::Result<()> {
+        if self.depth >= self.limits.max_depth {
+            return Err(ErrorKind::LimitExceeded.into());
+        }
+
+        self.depth += 1;
+        Ok(())
+    }
+
+    /// Goes back one level up.
+    pub fn leave(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+    }
+}
+
+impl Default for Limiter {
+    fn default() -> Self {
+        Self::new(DecodeLimits::DEFAULT)
+    }
+}
+
+/// Decodes a `T` from `reader` within `limits`.
+///
+/// Reading more than [`max_total_bytes`](DecodeLimits::max_total_bytes)
+/// fails with [`ErrorKind::LimitExceeded`], whatever the error the decoder
+/// would have reported when cut short.
+pub fn deserialize_with_limits<T, R>(reader: &mut R, limits: DecodeLimits) -> crate::Result<T>
+where
+    T: Deserialize,
+    R: crate::io::Read,
+{
+    deserialize_with_config(reader, &Config::DEFAULT.with_limits(limits))
+}
+
+/// Decodes a `T` from `reader` as `config` asks, see
+/// [`deserialize_with_limits`].
+pub fn deserialize_with_config<T, R>(reader: &mut R, config: &Config) -> crate::Result<T>
+where
+    T: Deserialize,
+    R: crate::io::Read,
+{
+    let max_total_bytes = config.limits.max_total_bytes;
+    let mut budget = Budget {
+        inner: reader,
+        remaining: max_total_bytes,
+        exceeded: false,
+    };
+
+    let decoded = T::deserialize_limited(&mut budget, &mut Limiter::from_config(config));
+    match budget.exceeded {
+        true => Err(Error::new(ErrorKind::LimitExceeded).with_offset(max_total_bytes)),
+        false => decoded,
+    }
+}
+
+/// Reader ending after a number of bytes.
+///
+/// Asked for more, it checks whether the inner reader has more to give and
+/// records whether it did, as a decoder seeing the end of its input may not
+/// fail, e.g. when reading records until the end.
+struct Budget<R> {
+    inner: R,
+    remaining: u64,
+    exceeded: bool,
+}
+
+impl<R> crate::io::Read for Budget<R>
+where
+    R: crate::io::Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> crate::io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        if self.remaining == 0 {
+            let mut probe = [0; 1];
+            self.exceeded |= self.inner.read(&mut probe)? != 0;
+            return Ok(0);
+        }
+
+        let len = buf
+            .len()
+            .min(self.remaining.try_into().unwrap_or(usize::MAX));
+        let read = self.inner.read(&mut buf[..len])?;
+        self.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+/// Bytes preallocated for a collection at most, whatever the number of
+/// elements it announces: the rest is allocated as elements are decoded.
+#[cfg(feature = "alloc")]
+const PREALLOCATION: usize = 64 * 1024;
+
+/// Capacity to preallocate for `count` elements of type `T`.
+#[cfg(feature = "alloc")]
+pub(crate) fn capacity<T>(count: usize) -> usize {
+    count.min(PREALLOCATION / core::mem::size_of::<T>().max(1))
+}
+
+/// Reads a value of `len` bytes in chunks, so that memory grows with the
+/// bytes actually read rather than with the length announced.
+#[cfg(feature = "alloc")]
+pub(crate) fn read_bytes<R>(reader: &mut R, len: usize) -> crate::io::Result<Vec<u8>>
+where
+    R: crate::io::Read + ?Sized,
+{
+    let mut buf = Vec::with_capacity(len.min(PREALLOCATION));
+    while buf.len() < len {
+        let start = buf.len();
+        let end = len.min(start + start.max(PREALLOCATION));
+        buf.resize(end, 0);
+        reader.read_exact(&mut buf[start..])?;
+    }
+    Ok(buf)
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn test_read_bytes() {
+        let input = vec![7; 3 * PREALLOCATION];
+        let buf = read_bytes(&mut input.as_slice(), input.len()).unwrap();
+        assert_eq!(buf, input);
+
+        // a huge length costs nothing until bytes arrive
+        let err = read_bytes(&mut [1, 2].as_slice(), usize::MAX).unwrap_err();
+        assert_eq!(err.kind(), crate::io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_nested() {
+        let mut limiter = Limiter::new(DecodeLimits::DEFAULT.with_max_depth(1));
+
+        let depth = limiter.nested(|limiter| Ok(limiter.depth())).unwrap();
+        assert_eq!(depth, 1);
+        assert_eq!(limiter.depth(), 0);
+
+        let err = limiter
+            .nested(|limiter| limiter.nested(|_| Ok(())))
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+        assert_eq!(limiter.depth(), 0);
+    }
+
+    #[test]
+    fn test_total_bytes() {
+        let limits = DecodeLimits::DEFAULT.with_max_total_bytes(4);
+
+        let value: u32 = deserialize_with_limits(&mut [1, 0, 0, 0].as_slice(), limits).unwrap();
+        assert_eq!(value, 1);
+
+        let err = deserialize_with_limits::<u64, _>(&mut [0; 8].as_slice(), limits).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+        assert_eq!(err.offset(), Some(4));
+
+        // the end of a short input is still an end, not an excess
+        let err = deserialize_with_limits::<u64, _>(&mut [0; 4].as_slice(), limits).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Truncated);
+    }
+}
@@ -1,42 +1,605 @@
-use std::collections::BTreeMap;
+#[cfg(feature = "alloc")]
+use alloc::{collections::BTreeMap, vec::Vec};
 
+#[cfg(feature = "alloc")]
+use crate::{Error, ErrorKind, Layout};
+
+use crate::deser::limits::Limiter;
+
+pub(crate) mod borrow;
+pub(crate) mod limits;
+pub(crate) mod reader;
 pub(crate) mod utils;
 
+/// Decodes a value from a reader.
+///
+/// Types whose decoding allocates or recurses, collections and derived
+/// types, implement [`deserialize_limited`](Self::deserialize_limited) and
+/// have `deserialize` call
+/// [`deserialize_with_limits`](crate::deserialize_with_limits) with the
+/// [default limits](crate::DecodeLimits::DEFAULT). Other types only need
+/// `deserialize`.
 pub trait Deserialize {
-    fn deserialize<R>(reader: &mut R) -> std::io::Result<Self>
+    fn deserialize<R>(reader: &mut R) -> crate::Result<Self>
     where
-        R: std::io::Read,
+        R: crate::io::Read,
         Self: Sized;
+
+    /// Decodes a value nested in another one, within the limits `limiter`
+    /// keeps track of. The default implementation ignores them.
+    fn deserialize_limited<R>(reader: &mut R, _limiter: &mut Limiter) -> crate::Result<Self>
+    where
+        R: crate::io::Read,
+        Self: Sized,
+    {
+        Self::deserialize(reader)
+    }
 }
 
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct RawField {
-    pub id: u8,
-    pub len: u16,
+    pub id: u64,
+    pub len: usize,
     pub value: Vec<u8>,
+    /// Position of the value in the input the record was extracted from.
+    pub offset: u64,
 }
 
+#[cfg(feature = "alloc")]
 impl RawField {
-    pub fn new(id: u8, len: u16, value: Vec<u8>) -> Self {
-        Self { id, len, value }
+    pub fn new(id: u64, len: usize, value: Vec<u8>) -> Self {
+        Self {
+            id,
+            len,
+            value,
+            offset: 0,
+        }
+    }
+
+    /// Decodes the value of the record as the field named `field`.
+    ///
+    /// The whole value has to be consumed, errors are reported in the
+    /// context of the record.
+    pub fn decode<T>(&self, field: &'static str) -> crate::Result<T>
+    where
+        T: Deserialize,
+    {
+        self.decode_limited(field, &mut Limiter::default())
+    }
+
+    /// Decodes the value of the record like [`decode`](Self::decode), one
+    /// level deeper than `limiter`.
+    pub fn decode_limited<T>(&self, field: &'static str, limiter: &mut Limiter) -> crate::Result<T>
+    where
+        T: Deserialize,
+    {
+        self.decode_with(field, limiter, T::deserialize_limited)
+    }
+
+    /// Decodes the value of the record with `decode`, one level deeper than
+    /// `limiter`.
+    pub fn decode_with<'a, T, F>(
+        &'a self,
+        field: &'static str,
+        limiter: &mut Limiter,
+        decode: F,
+    ) -> crate::Result<T>
+    where
+        F: FnOnce(&mut &'a [u8], &mut Limiter) -> crate::Result<T>,
+    {
+        let mut value = self.value.as_slice();
+
+        limiter
+            .nested(|limiter| decode(&mut value, limiter))
+            .and_then(|decoded| match value.len() {
+                0 => Ok(decoded),
+                _ => Err(Error::new(ErrorKind::LengthMismatch)
+                    .with_offset((self.value.len() - value.len()) as u64)),
+            })
+            .map_err(|err| err.within(self.id, field, self.offset))
+    }
+}
+
+/// Records a derived type does not know, kept by a `#[tlv(unknown)]` field
+/// when the type is decoded and written back when it is encoded.
+///
+/// `Vec<RawField>` keeps the records in the order they were read.
+/// `BTreeMap<u64, Vec<u8>>` keeps their values by tag, and fails with
+/// [`ErrorKind::DuplicateTag`] on a tag read twice.
+#[cfg(feature = "alloc")]
+pub trait UnknownRecords: Default {
+    /// Adds a record that was read with a tag the type does not know.
+    fn insert(&mut self, record: RawField) -> crate::Result<()>;
+
+    /// The tags and values of the records, in the order they are written.
+    fn records(&self) -> impl Iterator<Item = (u64, &[u8])>;
+}
+
+#[cfg(feature = "alloc")]
+impl UnknownRecords for Vec<RawField> {
+    fn insert(&mut self, record: RawField) -> crate::Result<()> {
+        self.push(record);
+        Ok(())
+    }
+
+    fn records(&self) -> impl Iterator<Item = (u64, &[u8])> {
+        self.iter()
+            .map(|record| (record.id, record.value.as_slice()))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl UnknownRecords for BTreeMap<u64, Vec<u8>> {
+    fn insert(&mut self, record: RawField) -> crate::Result<()> {
+        if self.contains_key(&record.id) {
+            return Err(Error::new(ErrorKind::DuplicateTag)
+                .with_tag(record.id)
+                .with_offset(record.offset));
+        }
+        BTreeMap::insert(self, record.id, record.value);
+        Ok(())
+    }
+
+    fn records(&self) -> impl Iterator<Item = (u64, &[u8])> {
+        self.iter().map(|(id, value)| (*id, value.as_slice()))
     }
 }
 
+/// Reader which keeps track of how many bytes were read through it.
+pub(crate) struct Position<R> {
+    pub(crate) inner: R,
+    pub(crate) offset: u64,
+}
+
+impl<R> crate::io::Read for Position<R>
+where
+    R: crate::io::Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> crate::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.offset += read as u64;
+        Ok(read)
+    }
+}
+
+/// Skips up to `len` bytes of `reader` and returns how many were skipped,
+/// fewer only if the reader ended before.
+pub(crate) fn skip<R>(reader: &mut R, len: u64) -> crate::io::Result<u64>
+where
+    R: crate::io::Read + ?Sized,
+{
+    let mut buf = [0; 512];
+    let mut skipped = 0;
+    while skipped < len {
+        let chunk = buf.len().min((len - skipped) as usize);
+        match reader.read(&mut buf[..chunk]) {
+            Ok(0) => break,
+            Ok(read) => skipped += read as u64,
+            Err(err) if err.kind() == crate::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(skipped)
+}
+
+/// Reads the length and the value of a record whose tag was just read,
+/// `start` is the position of the record.
+#[cfg(feature = "alloc")]
+fn read_record<R>(
+    reader: &mut Position<R>,
+    start: u64,
+    layout: &Layout,
+    id: u64,
+) -> crate::Result<RawField>
+where
+    R: crate::io::Read,
+{
+    let len = layout
+        .read_length(reader)
+        .map_err(|err| err.with_tag(id).with_offset(start))?;
+    let offset = reader.offset;
+
+    let buf = limits::read_bytes(reader, len)
+        .map_err(|err| Error::from(err).with_tag(id).with_offset(offset))?;
+
+    Ok(RawField {
+        offset,
+        ..RawField::new(id, len, buf)
+    })
+}
+
+/// Reads a single record, whatever its tag.
+#[cfg(feature = "alloc")]
+pub fn extract_record(
+    reader: &mut impl crate::io::Read,
+    layout: &Layout,
+) -> crate::Result<RawField> {
+    let mut reader = Position {
+        inner: reader,
+        offset: 0,
+    };
+
+    let id = layout
+        .read_tag(&mut reader)
+        .map_err(|err| err.with_offset(0))?;
+
+    read_record(&mut reader, 0, layout, id)
+}
+
+#[cfg(feature = "alloc")]
 pub fn extract_raw<const LENGTH: usize>(
-    reader: &mut impl std::io::Read,
-    tags: [u8; LENGTH],
-) -> std::io::Result<BTreeMap<u8, RawField>> {
+    reader: &mut impl crate::io::Read,
+    layout: &Layout,
+    tags: [u64; LENGTH],
+) -> crate::Result<BTreeMap<u64, RawField>> {
+    let mut reader = Position {
+        inner: reader,
+        offset: 0,
+    };
     let mut gathered = BTreeMap::new();
 
-    for _id in tags {
-        let id = u8::deserialize(reader)?;
-        let len = u16::deserialize(reader)?;
+    for _ in tags {
+        let start = reader.offset;
+        let id = layout
+            .read_tag(&mut reader)
+            .map_err(|err| err.with_offset(start))?;
 
-        let mut buf = vec![0; len as usize];
+        if !tags.contains(&id) {
+            return Err(Error::new(ErrorKind::UnexpectedTag)
+                .with_tag(id)
+                .with_offset(start));
+        }
+        if gathered.contains_key(&id) {
+            return Err(Error::new(ErrorKind::DuplicateTag)
+                .with_tag(id)
+                .with_offset(start));
+        }
 
-        reader.read_exact(&mut buf)?;
-
-        gathered.insert(id, RawField::new(id, len, buf));
+        gathered.insert(id, read_record(&mut reader, start, layout, id)?);
     }
 
     Ok(gathered)
 }
+
+/// Records gathered by [`extract_stream`], or by
+/// [`borrow_stream`](crate::borrow_stream) with borrowed records.
+#[cfg(feature = "alloc")]
+#[derive(Debug)]
+pub struct Extracted<F = RawField> {
+    /// Records with one of the requested tags, keyed by tag.
+    pub fields: BTreeMap<u64, F>,
+    /// Tags of the records that were not requested and were skipped, in the
+    /// order they were encountered.
+    pub skipped: Vec<u64>,
+    /// Records that were not requested and were kept rather than skipped,
+    /// by [`extract_stream_with_unknown`] and
+    /// [`extract_bolt_with_unknown`], in the order they were encountered.
+    pub unknown: Vec<F>,
+}
+
+/// Reads records until the reader is exhausted.
+///
+/// Unlike [`extract_raw`] the records may come in any order, any of the
+/// `tags` may be absent, and records with a tag that is not in `tags` are
+/// skipped without being buffered. As the end of the message is the end of
+/// the reader, this is meant for top-level messages or for values that are
+/// already bounded by the length of an enclosing record.
+#[cfg(feature = "alloc")]
+pub fn extract_stream(
+    reader: &mut impl crate::io::Read,
+    layout: &Layout,
+    tags: &[u64],
+) -> crate::Result<Extracted> {
+    read_stream(reader, layout, false, |id, _| Ok(tags.contains(&id)))
+}
+
+/// Reads records like [`extract_stream`], keeping the records with a tag
+/// that is not in `tags` in [`Extracted::unknown`] instead of skipping them.
+#[cfg(feature = "alloc")]
+pub fn extract_stream_with_unknown(
+    reader: &mut impl crate::io::Read,
+    layout: &Layout,
+    tags: &[u64],
+) -> crate::Result<Extracted> {
+    read_stream(reader, layout, true, |id, _| Ok(tags.contains(&id)))
+}
+
+/// Reads records like [`extract_stream`], failing with
+/// [`ErrorKind::UnexpectedTag`] at the first record with a tag that is not in
+/// `tags` instead of skipping it, before its value is read.
+#[cfg(feature = "alloc")]
+pub fn extract_stream_deny_unknown(
+    reader: &mut impl crate::io::Read,
+    layout: &Layout,
+    tags: &[u64],
+) -> crate::Result<Extracted> {
+    read_stream(reader, layout, false, |id, start| {
+        deny_unknown(tags.contains(&id), id, start)
+    })
+}
+
+/// Reads a BOLT #1 TLV stream until the reader is exhausted, see
+/// [`bolt`](crate::bolt).
+///
+/// Like [`extract_stream`], except that the types have to be strictly
+/// increasing and that only records of unknown odd types are skipped.
+#[cfg(feature = "alloc")]
+pub fn extract_bolt(
+    reader: &mut impl crate::io::Read,
+    layout: &Layout,
+    tags: &[u64],
+) -> crate::Result<Extracted> {
+    let mut types = crate::bolt::Types::new(tags);
+    read_stream(reader, layout, false, |id, start| types.check(id, start))
+}
+
+/// Reads a BOLT #1 TLV stream like [`extract_bolt`], keeping the records of
+/// unknown odd types in [`Extracted::unknown`] instead of skipping them.
+#[cfg(feature = "alloc")]
+pub fn extract_bolt_with_unknown(
+    reader: &mut impl crate::io::Read,
+    layout: &Layout,
+    tags: &[u64],
+) -> crate::Result<Extracted> {
+    let mut types = crate::bolt::Types::new(tags);
+    read_stream(reader, layout, true, |id, start| types.check(id, start))
+}
+
+/// Reads a BOLT #1 TLV stream like [`extract_bolt`], failing with
+/// [`ErrorKind::UnexpectedTag`] at the first record of an unknown type, odd
+/// ones included, before its value is read.
+#[cfg(feature = "alloc")]
+pub fn extract_bolt_deny_unknown(
+    reader: &mut impl crate::io::Read,
+    layout: &Layout,
+    tags: &[u64],
+) -> crate::Result<Extracted> {
+    let mut types = crate::bolt::Types::new(tags);
+    read_stream(reader, layout, false, |id, start| {
+        deny_unknown(types.check(id, start)?, id, start)
+    })
+}
+
+/// Reads the records of an ASN.1 DER value until the reader is exhausted,
+/// in the order they come, see [`der`](crate::der).
+///
+/// Tags have to be in their DER form, the records are left to the caller
+/// to take in order with [`Records::take_tagged`](crate::der::Records::take_tagged).
+#[cfg(feature = "alloc")]
+pub fn extract_der(
+    reader: &mut impl crate::io::Read,
+    layout: &Layout,
+) -> crate::Result<crate::der::Records> {
+    let mut reader = Position {
+        inner: reader,
+        offset: 0,
+    };
+    let mut records = crate::der::Records::new();
+
+    loop {
+        let start = reader.offset;
+        let Some(id) = layout
+            .read_tag_or_eof(&mut reader)
+            .map_err(|err| err.with_offset(start))?
+        else {
+            break;
+        };
+        crate::der::check_tag(id).map_err(|err| err.with_tag(id).with_offset(start))?;
+
+        let field = read_record(&mut reader, start, layout, id)?;
+        crate::der::check_length(id, start, field.len, field.offset)
+            .map_err(|err| err.with_tag(id).with_offset(start))?;
+        records.push(id, start, field);
+    }
+
+    Ok(records)
+}
+
+/// `known` callback of the `*_deny_unknown` extractors: the record of tag
+/// `id` starting at `start` is an error unless it is `known`.
+#[cfg(feature = "alloc")]
+pub(crate) fn deny_unknown(known: bool, id: u64, start: u64) -> crate::Result<bool> {
+    match known {
+        true => Ok(true),
+        false => Err(Error::new(ErrorKind::UnexpectedTag)
+            .with_tag(id)
+            .with_offset(start)),
+    }
+}
+
+/// Reads records until the reader is exhausted, reading those for which
+/// `known` returns `true` and skipping the others, or reading them as
+/// unknown if `keep` is set. `known` is given the tag and the position of
+/// each record.
+#[cfg(feature = "alloc")]
+fn read_stream(
+    reader: &mut impl crate::io::Read,
+    layout: &Layout,
+    keep: bool,
+    mut known: impl FnMut(u64, u64) -> crate::Result<bool>,
+) -> crate::Result<Extracted> {
+    let mut reader = Position {
+        inner: reader,
+        offset: 0,
+    };
+    let mut extracted = Extracted {
+        fields: BTreeMap::new(),
+        skipped: Vec::new(),
+        unknown: Vec::new(),
+    };
+
+    loop {
+        let start = reader.offset;
+        let Some(id) = layout
+            .read_tag_or_eof(&mut reader)
+            .map_err(|err| err.with_offset(start))?
+        else {
+            break;
+        };
+
+        if !known(id, start)? {
+            if keep {
+                let field = read_record(&mut reader, start, layout, id)?;
+                extracted.unknown.push(field);
+                continue;
+            }
+
+            let len = layout
+                .read_length(&mut reader)
+                .map_err(|err| err.with_tag(id).with_offset(start))?;
+            let offset = reader.offset;
+            let skipped = skip(&mut reader, len as u64)
+                .map_err(|err| Error::from(err).with_tag(id).with_offset(offset))?;
+            if skipped != len as u64 {
+                return Err(Error::new(ErrorKind::Truncated)
+                    .with_tag(id)
+                    .with_offset(offset));
+            }
+            extracted.skipped.push(id);
+            continue;
+        }
+
+        if extracted.fields.contains_key(&id) {
+            return Err(Error::new(ErrorKind::DuplicateTag)
+                .with_tag(id)
+                .with_offset(start));
+        }
+
+        let field = read_record(&mut reader, start, layout, id)?;
+        extracted.fields.insert(id, field);
+    }
+
+    Ok(extracted)
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+
+    /// Reads its bytes, then fails.
+    struct Failing<'a>(&'a [u8]);
+
+    impl crate::io::Read for Failing<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> crate::io::Result<usize> {
+            match self.0.is_empty() {
+                true => Err(crate::io::ErrorKind::Other.into()),
+                false => self.0.read(buf),
+            }
+        }
+    }
+
+    #[test]
+    fn test_extract_raw() {
+        let buf = [2, 1, 0, 7, 1, 0, 0];
+
+        let fields = extract_raw(&mut buf.as_slice(), &Layout::DEFAULT, [1, 2]).unwrap();
+
+        assert_eq!(fields[&2].value, [7]);
+        assert_eq!(fields[&2].offset, 3);
+        assert_eq!(fields[&1].offset, 7);
+    }
+
+    #[test]
+    fn test_extract_record() {
+        let buf = [5, 2, 0, 1, 2, 6];
+        let mut reader = buf.as_slice();
+
+        let field = extract_record(&mut reader, &Layout::DEFAULT).unwrap();
+
+        assert_eq!(
+            field,
+            RawField {
+                offset: 3,
+                ..RawField::new(5, 2, vec![1, 2])
+            }
+        );
+        assert_eq!(reader, [6]);
+    }
+
+    #[test]
+    fn test_extract_raw_errors() {
+        let layout = Layout::DEFAULT;
+
+        let unexpected = [1, 0, 0, 3, 0, 0];
+        let err = extract_raw(&mut unexpected.as_slice(), &layout, [1, 2]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedTag);
+        assert_eq!(err.tag(), Some(3));
+        assert_eq!(err.offset(), Some(3));
+
+        let truncated = [1, 2, 0, 7];
+        let err = extract_raw(&mut truncated.as_slice(), &layout, [1]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Truncated);
+        assert_eq!(err.tag(), Some(1));
+    }
+
+    #[test]
+    fn test_decode() {
+        let field = RawField {
+            offset: 10,
+            ..RawField::new(4, 3, vec![1, 2, 3])
+        };
+
+        assert_eq!(field.decode::<[u8; 3]>("value").unwrap(), [1, 2, 3]);
+
+        let err = field.decode::<u16>("value").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LengthMismatch);
+        assert_eq!(err.tag(), Some(4));
+        assert_eq!(err.field(), Some("value"));
+        assert_eq!(err.offset(), Some(12));
+
+        let err = field.decode::<u32>("value").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Truncated);
+        assert_eq!(err.offset(), Some(10));
+    }
+
+    #[test]
+    fn test_extract_stream() {
+        #[rustfmt::skip]
+        let buf = [
+            3, 1, 0, 7,
+            9, 2, 0, 1, 2,
+            1, 0, 0,
+            11, 0, 0,
+        ];
+
+        let extracted = extract_stream(&mut buf.as_slice(), &Layout::DEFAULT, &[1, 2, 3]).unwrap();
+
+        assert_eq!(extracted.fields.keys().copied().collect::<Vec<_>>(), [1, 3]);
+        assert_eq!(extracted.fields[&3].value, [7]);
+        assert!(extracted.fields[&1].value.is_empty());
+        assert_eq!(extracted.skipped, [9, 11]);
+    }
+
+    #[test]
+    fn test_extract_stream_errors() {
+        let layout = Layout::DEFAULT;
+
+        let duplicate = [1, 1, 0, 7, 1, 1, 0, 7];
+        let err = extract_stream(&mut duplicate.as_slice(), &layout, &[1]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::DuplicateTag);
+        assert_eq!(err.offset(), Some(4));
+
+        // a skipped record is reported with its tag and the position of its
+        // value, as one that is read
+        let truncated_unknown = [9, 4, 0, 1, 2];
+        let err = extract_stream(&mut truncated_unknown.as_slice(), &layout, &[1]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Truncated);
+        assert_eq!(err.tag(), Some(9));
+        assert_eq!(err.offset(), Some(3));
+
+        let err = extract_stream(&mut Failing(&[9, 4, 0, 1]), &layout, &[1]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Io);
+        assert_eq!(err.tag(), Some(9));
+        assert_eq!(err.offset(), Some(3));
+
+        // the length of a record is reported with its tag, skipped or not
+        for tags in [&[1][..], &[9]] {
+            let truncated_length = [1, 1, 0, 7, 9, 4];
+            let err = extract_stream(&mut truncated_length.as_slice(), &layout, tags).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Truncated);
+            assert_eq!(err.tag(), Some(9));
+            assert_eq!(err.offset(), Some(4));
+        }
+    }
+}
@@ -0,0 +1,302 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "alloc")]
+use crate::deser::limits::read_bytes;
+use crate::deser::{skip, Position};
+use crate::io::Read;
+#[cfg(feature = "alloc")]
+use crate::RawField;
+use crate::{Error, Layout};
+
+/// Header of a record read by [`TlvReader::next_header`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub tag: u64,
+    /// Length of the value.
+    pub len: usize,
+    /// Position of the value in the input.
+    pub offset: u64,
+}
+
+/// Reads records one at a time from any reader.
+///
+/// Only the header of a record is read up front. Its value can then be read
+/// whole with [`read_value`](Self::read_value), incrementally through the
+/// bounded reader returned by [`value`](Self::value), or skipped with
+/// [`skip_value`](Self::skip_value). Whatever is left of a value when the
+/// next header is requested is skipped, so memory use does not depend on the
+/// size of the input.
+///
+/// As an [`Iterator`] it yields every record as a [`RawField`], until the
+/// end of the input or the first error.
+///
+/// ```
+/// use tlv::{Deserialize, TlvReader};
+///
+/// let buf = [1, 1, 0, 7, 2, 2, 0, 8, 9];
+/// let mut reader = TlvReader::new(buf.as_slice());
+///
+/// let header = reader.next_header().unwrap().unwrap();
+/// assert_eq!((header.tag, header.len, header.offset), (1, 1, 3));
+/// assert_eq!(u8::deserialize(&mut reader.value()).unwrap(), 7);
+///
+/// let header = reader.next_header().unwrap().unwrap();
+/// assert_eq!(header.tag, 2);
+/// assert_eq!(reader.skip_value().unwrap(), 2);
+///
+/// assert!(reader.next_header().unwrap().is_none());
+/// assert_eq!(reader.offset(), 9);
+/// ```
+pub struct TlvReader<R> {
+    reader: Position<R>,
+    layout: Layout,
+    /// Header of the current record.
+    current: Option<Header>,
+    /// Bytes of the current value that were not read yet.
+    remaining: u64,
+    /// Set once the iterator returned an error.
+    #[cfg(feature = "alloc")]
+    failed: bool,
+}
+
+impl<R> TlvReader<R>
+where
+    R: Read,
+{
+    /// Reads records with the default [`Layout`].
+    pub fn new(reader: R) -> Self {
+        Self::with_layout(reader, Layout::DEFAULT)
+    }
+
+    pub fn with_layout(reader: R, layout: Layout) -> Self {
+        Self {
+            reader: Position {
+                inner: reader,
+                offset: 0,
+            },
+            layout,
+            current: None,
+            remaining: 0,
+            #[cfg(feature = "alloc")]
+            failed: false,
+        }
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    /// Number of bytes consumed from the underlying reader.
+    pub fn offset(&self) -> u64 {
+        self.reader.offset
+    }
+
+    /// Reads the header of the next record, skipping whatever is left of the
+    /// current value. Returns `None` at the end of the input.
+    pub fn next_header(&mut self) -> crate::Result<Option<Header>> {
+        self.skip_value()?;
+        self.current = None;
+
+        let start = self.reader.offset;
+        let Some(tag) = self
+            .layout
+            .read_tag_or_eof(&mut self.reader)
+            .map_err(|err| err.with_offset(start))?
+        else {
+            return Ok(None);
+        };
+        let len = self
+            .layout
+            .read_length(&mut self.reader)
+            .map_err(|err| err.with_tag(tag).with_offset(start))?;
+
+        let header = Header {
+            tag,
+            len,
+            offset: self.reader.offset,
+        };
+        self.current = Some(header);
+        self.remaining = len as u64;
+
+        Ok(Some(header))
+    }
+
+    /// Reader over what is left of the current value. It ends with the
+    /// value, and fails if the input ends before it.
+    pub fn value(&mut self) -> ValueReader<'_, R> {
+        ValueReader { reader: self }
+    }
+
+    /// Reads what is left of the current value.
+    #[cfg(feature = "alloc")]
+    pub fn read_value(&mut self) -> crate::Result<Vec<u8>> {
+        let remaining = self.remaining as usize;
+        read_bytes(&mut self.value(), remaining).map_err(|err| self.truncated(err.into()))
+    }
+
+    /// Skips what is left of the current value without buffering it, and
+    /// returns the number of bytes skipped.
+    pub fn skip_value(&mut self) -> crate::Result<u64> {
+        let remaining = self.remaining;
+        skip(&mut self.value(), remaining).map_err(|err| self.truncated(err.into()))
+    }
+
+    /// Reads the next record as a whole.
+    #[cfg(feature = "alloc")]
+    pub fn next_record(&mut self) -> crate::Result<Option<RawField>> {
+        let Some(header) = self.next_header()? else {
+            return Ok(None);
+        };
+        let value = self.read_value()?;
+
+        Ok(Some(RawField {
+            offset: header.offset,
+            ..RawField::new(header.tag, header.len, value)
+        }))
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader.inner
+    }
+
+    /// Places an error raised while reading the current value at the point
+    /// the input ended.
+    fn truncated(&self, err: Error) -> Error {
+        match self.current {
+            Some(header) => err.with_tag(header.tag).with_offset(self.reader.offset),
+            None => err,
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<R> Iterator for TlvReader<R>
+where
+    R: Read,
+{
+    type Item = crate::Result<RawField>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+
+        let record = self.next_record();
+        self.failed = record.is_err();
+        record.transpose()
+    }
+}
+
+/// Reader over the value of the current record of a [`TlvReader`].
+pub struct ValueReader<'a, R> {
+    reader: &'a mut TlvReader<R>,
+}
+
+impl<R> ValueReader<'_, R> {
+    /// Bytes of the value that were not read yet.
+    pub fn remaining(&self) -> u64 {
+        self.reader.remaining
+    }
+}
+
+impl<R> Read for ValueReader<'_, R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> crate::io::Result<usize> {
+        if self.reader.remaining == 0 {
+            return Ok(0);
+        }
+
+        let max = buf.len().min(self.reader.remaining as usize);
+        let read = self.reader.reader.read(&mut buf[..max])?;
+        if read == 0 {
+            return Err(crate::io::ErrorKind::UnexpectedEof.into());
+        }
+
+        self.reader.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::{Deserialize, ErrorKind, IntEncoding};
+
+    #[test]
+    fn test_records() {
+        let buf = [1, 1, 0, 7, 2, 2, 0, 8, 9];
+
+        let records = TlvReader::new(buf.as_slice())
+            .collect::<crate::Result<Vec<_>>>()
+            .unwrap();
+
+        assert_eq!(
+            records,
+            [
+                RawField {
+                    offset: 3,
+                    ..RawField::new(1, 1, vec![7])
+                },
+                RawField {
+                    offset: 7,
+                    ..RawField::new(2, 2, vec![8, 9])
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_value_reader() {
+        let layout = Layout::DEFAULT.with_length(IntEncoding::U32);
+        let buf = [5, 6, 0, 0, 0, 1, 0, 2, 0, 0, 0, 6, 0, 0, 0, 0, 0];
+        let mut reader = TlvReader::with_layout(buf.as_slice(), layout);
+
+        assert_eq!(reader.next_header().unwrap().unwrap().len, 6);
+
+        let mut value = reader.value();
+        assert_eq!(u16::deserialize(&mut value).unwrap(), 1);
+        assert_eq!(value.remaining(), 4);
+        assert_eq!(u32::deserialize(&mut value).unwrap(), 2);
+        assert!(u8::deserialize(&mut value).is_err());
+
+        // the header of the next record is read right after the value
+        assert_eq!(reader.next_header().unwrap().unwrap().tag, 6);
+        assert_eq!(reader.offset(), 16);
+    }
+
+    #[test]
+    fn test_skip_unread_value() {
+        let buf = [1, 3, 0, 1, 2, 3, 2, 0, 0];
+        let mut reader = TlvReader::new(buf.as_slice());
+
+        reader.next_header().unwrap();
+        let mut first = [0; 1];
+        reader.value().read_exact(&mut first).unwrap();
+
+        let header = reader.next_header().unwrap().unwrap();
+        assert_eq!((header.tag, header.offset), (2, 9));
+        assert_eq!(reader.read_value().unwrap(), []);
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn test_truncated() {
+        let buf = [1, 1, 0, 7, 2, 4, 0, 8];
+
+        let mut records = TlvReader::new(buf.as_slice());
+        assert!(records.next().unwrap().is_ok());
+
+        let err = records.next().unwrap().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Truncated);
+        assert_eq!(err.tag(), Some(2));
+        assert_eq!(err.offset(), Some(8));
+        assert!(records.next().is_none());
+
+        let err = TlvReader::new([1, 1].as_slice()).next_header().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Truncated);
+        assert_eq!(err.tag(), Some(1));
+    }
+}
@@ -1,19 +1,36 @@
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "alloc")]
+use crate::deser::limits::{read_bytes, Limiter};
 use crate::deser::Deserialize;
 
-pub fn deserialize<T, R>(reader: &mut R) -> std::io::Result<T>
+pub fn deserialize<T, R>(reader: &mut R) -> crate::Result<T>
 where
     T: Deserialize,
-    R: std::io::Read,
+    R: crate::io::Read,
 {
     T::deserialize(reader)
 }
 
-pub fn deserialize_bytes<R>(reader: &mut R) -> std::io::Result<Vec<u8>>
+/// Reads bytes written by [`serialize_bytes`](crate::serialize_bytes): a
+/// `u32` length followed by that many bytes.
+#[cfg(feature = "alloc")]
+pub fn deserialize_bytes<R>(reader: &mut R) -> crate::Result<Vec<u8>>
+where
+    R: crate::io::Read,
+{
+    deserialize_bytes_limited(reader, &mut Limiter::default())
+}
+
+/// Reads bytes like [`deserialize_bytes`], checking their length against
+/// the limits of `limiter`.
+#[cfg(feature = "alloc")]
+pub fn deserialize_bytes_limited<R>(reader: &mut R, limiter: &mut Limiter) -> crate::Result<Vec<u8>>
 where
-    R: std::io::Read,
+    R: crate::io::Read,
 {
-    let len = u32::deserialize(reader)? as usize;
-    let mut buf = vec![0; len];
-    reader.read_exact(&mut buf)?;
-    Ok(buf)
+    let len = u32::deserialize_limited(reader, limiter)? as usize;
+    limiter.check_length(len)?;
+    Ok(read_bytes(reader, len)?)
 }
@@ -0,0 +1,238 @@
+use core::fmt;
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Cause of an [`Error`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// A record required by the type is not present.
+    MissingTag,
+    /// The same tag was encountered more than once.
+    DuplicateTag,
+    /// A record with a tag the type does not know was encountered.
+    UnexpectedTag,
+    /// A record came after one with a greater tag, where tags have to be
+    /// increasing.
+    UnorderedTag,
+    /// The length of a record does not match the length of its value.
+    LengthMismatch,
+    /// A string is not valid UTF-8.
+    InvalidUtf8,
+    /// A `char` is not a Unicode scalar value.
+    InvalidChar,
+    /// A value does not fit into its type or into its encoding.
+    OutOfRange,
+    /// A variable-length integer is not in its shortest form.
+    NonCanonical,
+    /// The input uses a form its encoding allows but the crate does not
+    /// support, e.g. a BER indefinite length.
+    Unsupported,
+    /// The input ended in the middle of a value.
+    Truncated,
+    /// The underlying reader or writer failed.
+    Io,
+    /// The containers of a [`TlvWriter`](crate::TlvWriter) were not closed
+    /// in the order they were opened.
+    Unbalanced,
+    /// A frame is longer than its codec allows.
+    FrameTooLarge,
+    /// The input asks for more than the [`DecodeLimits`](crate::DecodeLimits)
+    /// of the decode allow.
+    LimitExceeded,
+}
+
+impl ErrorKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::MissingTag => "missing tag",
+            ErrorKind::DuplicateTag => "duplicate tag",
+            ErrorKind::UnexpectedTag => "unexpected tag",
+            ErrorKind::UnorderedTag => "unordered tag",
+            ErrorKind::LengthMismatch => "length mismatch",
+            ErrorKind::InvalidUtf8 => "invalid utf-8",
+            ErrorKind::InvalidChar => "invalid char",
+            ErrorKind::OutOfRange => "value out of range",
+            ErrorKind::NonCanonical => "non-canonical encoding",
+            ErrorKind::Unsupported => "unsupported encoding",
+            ErrorKind::Truncated => "truncated input",
+            ErrorKind::Io => "i/o error",
+            ErrorKind::Unbalanced => "unbalanced containers",
+            ErrorKind::FrameTooLarge => "frame too large",
+            ErrorKind::LimitExceeded => "decode limit exceeded",
+        }
+    }
+}
+
+/// Error returned by serialization and deserialization.
+///
+/// Besides its [`ErrorKind`] an error carries, when known, the tag and the
+/// name of the field being processed and the byte offset at which it
+/// happened, counted from the start of the outermost value.
+#[derive(Debug)]
+pub struct Error {
+    kind: ErrorKind,
+    tag: Option<u64>,
+    field: Option<&'static str>,
+    offset: Option<u64>,
+    source: Option<crate::io::Error>,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind) -> Self {
+        Self {
+            kind,
+            tag: None,
+            field: None,
+            offset: None,
+            source: None,
+        }
+    }
+
+    pub fn with_tag(mut self, tag: u64) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
+    pub fn with_field(mut self, field: &'static str) -> Self {
+        self.field = Some(field);
+        self
+    }
+
+    pub fn with_offset(mut self, offset: u64) -> Self {
+        self.offset = Some(offset);
+        self
+    }
+
+    /// Places an error raised while processing the value of a record into
+    /// the context of that record.
+    ///
+    /// `offset` is the position of the value, the offset of the error is
+    /// made relative to it. The tag and the field are only set if a nested
+    /// record has not set them already, so the innermost one is reported.
+    pub fn within(mut self, tag: u64, field: &'static str, offset: u64) -> Self {
+        if self.field.is_none() && self.tag.is_none_or(|inner| inner == tag) {
+            self.tag = Some(tag);
+            self.field = Some(field);
+        }
+        self.advance(offset)
+    }
+
+    /// Moves the offset of the error forward by `by` bytes.
+    pub(crate) fn advance(mut self, by: u64) -> Self {
+        self.offset = Some(by + self.offset.unwrap_or(0));
+        self
+    }
+
+    /// Sets the tag unless a nested record has set one already.
+    pub(crate) fn or_tag(mut self, tag: u64) -> Self {
+        self.tag.get_or_insert(tag);
+        self
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn tag(&self) -> Option<u64> {
+        self.tag
+    }
+
+    pub fn field(&self) -> Option<&'static str> {
+        self.field
+    }
+
+    pub fn offset(&self) -> Option<u64> {
+        self.offset
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.kind.as_str())?;
+        if let Some(source) = &self.source {
+            write!(f, ": {source}")?;
+        }
+        if let Some(tag) = self.tag {
+            write!(f, ", tag {tag}")?;
+        }
+        if let Some(field) = self.field {
+            write!(f, " (field `{field}`)")?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, " at offset {offset}")?;
+        }
+        Ok(())
+    }
+}
+
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        self.source
+            .as_ref()
+            .map(|err| err as &(dyn core::error::Error + 'static))
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Self::new(kind)
+    }
+}
+
+impl From<crate::io::Error> for Error {
+    fn from(err: crate::io::Error) -> Self {
+        match err.kind() {
+            crate::io::ErrorKind::UnexpectedEof => Self::new(ErrorKind::Truncated),
+            _ => Self {
+                source: Some(err),
+                ..Self::new(ErrorKind::Io)
+            },
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<Error> for std::io::Error {
+    fn from(err: Error) -> Self {
+        match err.kind {
+            ErrorKind::Io => match err.source {
+                Some(source) => source,
+                None => std::io::Error::other(err.kind.as_str()),
+            },
+            ErrorKind::Truncated => std::io::Error::new(std::io::ErrorKind::UnexpectedEof, err),
+            _ => std::io::Error::new(std::io::ErrorKind::InvalidData, err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_within() {
+        let err = Error::new(ErrorKind::Truncated)
+            .within(2, "inner", 3)
+            .within(1, "outer", 10);
+
+        assert_eq!(err.kind(), ErrorKind::Truncated);
+        assert_eq!(err.tag(), Some(2));
+        assert_eq!(err.field(), Some("inner"));
+        assert_eq!(err.offset(), Some(13));
+        assert_eq!(
+            err.to_string(),
+            "truncated input, tag 2 (field `inner`) at offset 13"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_from_io() {
+        let eof = std::io::Error::from(std::io::ErrorKind::UnexpectedEof);
+        assert_eq!(Error::from(eof).kind(), ErrorKind::Truncated);
+
+        let other = std::io::Error::from(std::io::ErrorKind::BrokenPipe);
+        assert_eq!(Error::from(other).kind(), ErrorKind::Io);
+    }
+}
@@ -0,0 +1,287 @@
+#[cfg(feature = "alloc")]
+use alloc::{borrow::Cow, collections::BTreeMap, string::String, vec::Vec};
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+#[cfg(feature = "alloc")]
+use crate::deser::limits::capacity;
+use crate::varint::{BigSize, Leb128, QuicVarInt, ZigZag};
+use crate::{borrow_bytes_limited, BorrowDeserialize, Deserialize, ErrorKind, Limiter};
+#[cfg(feature = "alloc")]
+use crate::{Config, Serialize};
+
+/// Types that never borrow are decoded by their `Deserialize` impl, with
+/// the input as the reader.
+macro_rules! impl_borrow_deserialize_owned {
+    ($($ty:ty),*) => {
+        $(
+            impl<'de> BorrowDeserialize<'de> for $ty {
+                fn borrow_deserialize(input: &mut &'de [u8]) -> crate::Result<Self> {
+                    Self::deserialize(input)
+                }
+
+                fn borrow_deserialize_limited(
+                    input: &mut &'de [u8],
+                    limiter: &mut Limiter,
+                ) -> crate::Result<Self> {
+                    Self::deserialize_limited(input, limiter)
+                }
+            }
+        )*
+    };
+}
+
+impl_borrow_deserialize_owned!(bool, char, f32, f64, usize, isize);
+impl_borrow_deserialize_owned!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+impl_borrow_deserialize_owned!(Leb128, ZigZag, BigSize, QuicVarInt, crate::ber::Tag);
+#[cfg(feature = "alloc")]
+impl_borrow_deserialize_owned!(String);
+
+impl<'de, const LENGTH: usize> BorrowDeserialize<'de> for [u8; LENGTH] {
+    fn borrow_deserialize(input: &mut &'de [u8]) -> crate::Result<Self> {
+        Self::deserialize(input)
+    }
+}
+
+impl<'de, T> BorrowDeserialize<'de> for Option<T>
+where
+    T: BorrowDeserialize<'de>,
+{
+    fn borrow_deserialize(input: &mut &'de [u8]) -> crate::Result<Self> {
+        Self::borrow_deserialize_limited(input, &mut Limiter::default())
+    }
+
+    fn borrow_deserialize_limited(
+        input: &mut &'de [u8],
+        limiter: &mut Limiter,
+    ) -> crate::Result<Self> {
+        let has_value = u8::borrow_deserialize(input)?;
+        if has_value == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(T::borrow_deserialize_limited(input, limiter)?))
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'de, T> BorrowDeserialize<'de> for Vec<T>
+where
+    T: BorrowDeserialize<'de>,
+{
+    fn borrow_deserialize(input: &mut &'de [u8]) -> crate::Result<Self> {
+        Self::borrow_deserialize_limited(input, &mut Limiter::default())
+    }
+
+    fn borrow_deserialize_limited(
+        input: &mut &'de [u8],
+        limiter: &mut Limiter,
+    ) -> crate::Result<Self> {
+        let len = u32::borrow_deserialize_limited(input, limiter)? as usize;
+        limiter.check_elements(len)?;
+        limiter.nested(|limiter| {
+            let mut vec = Vec::with_capacity(capacity::<T>(len));
+            for _ in 0..len {
+                vec.push(T::borrow_deserialize_limited(input, limiter)?);
+            }
+            Ok(vec)
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'de, K, V> BorrowDeserialize<'de> for HashMap<K, V>
+where
+    K: BorrowDeserialize<'de> + Eq + core::hash::Hash,
+    V: BorrowDeserialize<'de>,
+{
+    fn borrow_deserialize(input: &mut &'de [u8]) -> crate::Result<Self> {
+        Self::borrow_deserialize_limited(input, &mut Limiter::default())
+    }
+
+    fn borrow_deserialize_limited(
+        input: &mut &'de [u8],
+        limiter: &mut Limiter,
+    ) -> crate::Result<Self> {
+        let len = u32::borrow_deserialize_limited(input, limiter)? as usize;
+        limiter.check_elements(len)?;
+        limiter.nested(|limiter| {
+            let mut map = HashMap::with_capacity(capacity::<(K, V)>(len));
+            for _ in 0..len {
+                let key = K::borrow_deserialize_limited(input, limiter)?;
+                let value = V::borrow_deserialize_limited(input, limiter)?;
+                map.insert(key, value);
+            }
+            Ok(map)
+        })
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'de, K, V> BorrowDeserialize<'de> for BTreeMap<K, V>
+where
+    K: BorrowDeserialize<'de> + Ord,
+    V: BorrowDeserialize<'de>,
+{
+    fn borrow_deserialize(input: &mut &'de [u8]) -> crate::Result<Self> {
+        Self::borrow_deserialize_limited(input, &mut Limiter::default())
+    }
+
+    fn borrow_deserialize_limited(
+        input: &mut &'de [u8],
+        limiter: &mut Limiter,
+    ) -> crate::Result<Self> {
+        let len = u32::borrow_deserialize_limited(input, limiter)? as usize;
+        limiter.check_elements(len)?;
+        limiter.nested(|limiter| {
+            let mut map = BTreeMap::new();
+            for _ in 0..len {
+                let key = K::borrow_deserialize_limited(input, limiter)?;
+                let value = V::borrow_deserialize_limited(input, limiter)?;
+                map.insert(key, value);
+            }
+            Ok(map)
+        })
+    }
+}
+
+impl<'de> BorrowDeserialize<'de> for &'de [u8] {
+    fn borrow_deserialize(input: &mut &'de [u8]) -> crate::Result<Self> {
+        Self::borrow_deserialize_limited(input, &mut Limiter::default())
+    }
+
+    fn borrow_deserialize_limited(
+        input: &mut &'de [u8],
+        limiter: &mut Limiter,
+    ) -> crate::Result<Self> {
+        borrow_bytes_limited(input, limiter)
+    }
+}
+
+impl<'de> BorrowDeserialize<'de> for &'de str {
+    fn borrow_deserialize(input: &mut &'de [u8]) -> crate::Result<Self> {
+        Self::borrow_deserialize_limited(input, &mut Limiter::default())
+    }
+
+    fn borrow_deserialize_limited(
+        input: &mut &'de [u8],
+        limiter: &mut Limiter,
+    ) -> crate::Result<Self> {
+        core::str::from_utf8(borrow_bytes_limited(input, limiter)?)
+            .map_err(|_| ErrorKind::InvalidUtf8.into())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'de> BorrowDeserialize<'de> for Cow<'de, [u8]> {
+    fn borrow_deserialize(input: &mut &'de [u8]) -> crate::Result<Self> {
+        Self::borrow_deserialize_limited(input, &mut Limiter::default())
+    }
+
+    fn borrow_deserialize_limited(
+        input: &mut &'de [u8],
+        limiter: &mut Limiter,
+    ) -> crate::Result<Self> {
+        borrow_bytes_limited(input, limiter).map(Cow::Borrowed)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'de> BorrowDeserialize<'de> for Cow<'de, str> {
+    fn borrow_deserialize(input: &mut &'de [u8]) -> crate::Result<Self> {
+        Self::borrow_deserialize_limited(input, &mut Limiter::default())
+    }
+
+    fn borrow_deserialize_limited(
+        input: &mut &'de [u8],
+        limiter: &mut Limiter,
+    ) -> crate::Result<Self> {
+        <&str>::borrow_deserialize_limited(input, limiter).map(Cow::Borrowed)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Serialize for Cow<'_, [u8]> {
+    fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        self.serialize_with(writer, &Config::DEFAULT)
+    }
+
+    fn serialize_with<W>(&self, writer: &mut W, config: &Config) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        self.as_ref().serialize_with(writer, config)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.as_ref().serialized_length()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Serialize for Cow<'_, str> {
+    fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        self.serialize_with(writer, &Config::DEFAULT)
+    }
+
+    fn serialize_with<W>(&self, writer: &mut W, config: &Config) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        self.as_ref().serialize_with(writer, config)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.as_ref().serialized_length()
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_borrowed_bytes() {
+        let buf = [2, 0, 0, 0, 0xaa, 0xbb, 1];
+        let mut input = buf.as_slice();
+
+        let bytes = <&[u8]>::borrow_deserialize(&mut input).unwrap();
+        assert_eq!(bytes, [0xaa, 0xbb]);
+        assert!(std::ptr::eq(bytes, &buf[4..6]));
+        assert_eq!(input, [1]);
+
+        let err = <&[u8]>::borrow_deserialize(&mut [3, 0, 0, 0, 0].as_slice()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Truncated);
+    }
+
+    #[test]
+    fn test_borrowed_str() {
+        let mut buf = Vec::new();
+        Cow::Borrowed("tlv").serialize(&mut buf).unwrap();
+        assert_eq!(buf, [3, 0, 0, 0, b't', b'l', b'v']);
+
+        let value = Cow::<str>::borrow_deserialize(&mut buf.as_slice()).unwrap();
+        assert!(matches!(value, Cow::Borrowed("tlv")));
+
+        let err = <&str>::borrow_deserialize(&mut [1, 0, 0, 0, 0xff].as_slice()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidUtf8);
+    }
+
+    #[test]
+    fn test_borrowed_containers() {
+        let mut buf = Vec::new();
+        vec![Some("a"), None].serialize(&mut buf).unwrap();
+
+        let value = Vec::<Option<&str>>::borrow_deserialize(&mut buf.as_slice()).unwrap();
+        assert_eq!(value, [Some("a"), None]);
+
+        let err = Vec::<&str>::borrow_deserialize(&mut [0xff; 4].as_slice()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+    }
+}
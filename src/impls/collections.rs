@@ -0,0 +1,370 @@
+//! Impls for the collections of `alloc`, and for `HashMap` with `std`.
+
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use crate::deser::limits::capacity;
+use crate::{
+    deserialize_bytes_limited, deserialize_with_limits, serialize_bytes_with,
+    serialized_bytes_length, Config, DecodeLimits, Deserialize, ErrorKind, Limiter, Serialize,
+};
+
+/// Converts the number of elements of a collection into its `u32` prefix.
+fn count(len: usize) -> crate::Result<u32> {
+    u32::try_from(len).map_err(|_| ErrorKind::OutOfRange.into())
+}
+
+impl<T> Serialize for Vec<T>
+where
+    T: Serialize,
+{
+    fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        self.serialize_with(writer, &Config::DEFAULT)
+    }
+
+    fn serialize_with<W>(&self, writer: &mut W, config: &Config) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        let mut len = 0;
+        len += count(self.len())?.serialize_with(writer, config)?;
+        for item in self {
+            len += item.serialize_with(writer, config)?;
+        }
+        Ok(len)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.iter()
+            .map(Serialize::serialized_length)
+            .fold(core::mem::size_of::<u32>(), usize::saturating_add)
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K, V> Serialize for HashMap<K, V>
+where
+    K: Serialize,
+    V: Serialize,
+{
+    fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        self.serialize_with(writer, &Config::DEFAULT)
+    }
+
+    fn serialize_with<W>(&self, writer: &mut W, config: &Config) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        let mut len = 0;
+        len += count(self.len())?.serialize_with(writer, config)?;
+        for (key, value) in self {
+            len += key.serialize_with(writer, config)?;
+            len += value.serialize_with(writer, config)?;
+        }
+        Ok(len)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.iter()
+            .map(|(key, value)| {
+                key.serialized_length()
+                    .saturating_add(value.serialized_length())
+            })
+            .fold(core::mem::size_of::<u32>(), usize::saturating_add)
+    }
+}
+
+impl<K, V> Serialize for BTreeMap<K, V>
+where
+    K: Serialize,
+    V: Serialize,
+{
+    fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        self.serialize_with(writer, &Config::DEFAULT)
+    }
+
+    fn serialize_with<W>(&self, writer: &mut W, config: &Config) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        let mut len = 0;
+        len += count(self.len())?.serialize_with(writer, config)?;
+        for (key, value) in self {
+            len += key.serialize_with(writer, config)?;
+            len += value.serialize_with(writer, config)?;
+        }
+        Ok(len)
+    }
+
+    fn serialized_length(&self) -> usize {
+        self.iter()
+            .map(|(key, value)| {
+                key.serialized_length()
+                    .saturating_add(value.serialized_length())
+            })
+            .fold(core::mem::size_of::<u32>(), usize::saturating_add)
+    }
+}
+
+impl<T> Deserialize for Vec<T>
+where
+    T: Deserialize,
+{
+    fn deserialize<R>(reader: &mut R) -> crate::Result<Self>
+    where
+        R: crate::io::Read,
+    {
+        deserialize_with_limits(reader, DecodeLimits::DEFAULT)
+    }
+
+    fn deserialize_limited<R>(reader: &mut R, limiter: &mut Limiter) -> crate::Result<Self>
+    where
+        R: crate::io::Read,
+    {
+        let len = u32::deserialize_limited(reader, limiter)? as usize;
+        limiter.check_elements(len)?;
+        limiter.nested(|limiter| {
+            let mut vec = Vec::with_capacity(capacity::<T>(len));
+            for _ in 0..len {
+                vec.push(T::deserialize_limited(reader, limiter)?);
+            }
+            Ok(vec)
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K, V> Deserialize for HashMap<K, V>
+where
+    K: Deserialize + Eq + core::hash::Hash,
+    V: Deserialize,
+{
+    fn deserialize<R>(reader: &mut R) -> crate::Result<Self>
+    where
+        R: crate::io::Read,
+    {
+        deserialize_with_limits(reader, DecodeLimits::DEFAULT)
+    }
+
+    fn deserialize_limited<R>(reader: &mut R, limiter: &mut Limiter) -> crate::Result<Self>
+    where
+        R: crate::io::Read,
+    {
+        let len = u32::deserialize_limited(reader, limiter)? as usize;
+        limiter.check_elements(len)?;
+        limiter.nested(|limiter| {
+            let mut map = HashMap::with_capacity(capacity::<(K, V)>(len));
+            for _ in 0..len {
+                let key = K::deserialize_limited(reader, limiter)?;
+                let value = V::deserialize_limited(reader, limiter)?;
+                map.insert(key, value);
+            }
+            Ok(map)
+        })
+    }
+}
+
+impl<K, V> Deserialize for BTreeMap<K, V>
+where
+    K: Deserialize + Ord,
+    V: Deserialize,
+{
+    fn deserialize<R>(reader: &mut R) -> crate::Result<Self>
+    where
+        R: crate::io::Read,
+    {
+        deserialize_with_limits(reader, DecodeLimits::DEFAULT)
+    }
+
+    fn deserialize_limited<R>(reader: &mut R, limiter: &mut Limiter) -> crate::Result<Self>
+    where
+        R: crate::io::Read,
+    {
+        let len = u32::deserialize_limited(reader, limiter)? as usize;
+        limiter.check_elements(len)?;
+        limiter.nested(|limiter| {
+            let mut map = BTreeMap::new();
+            for _ in 0..len {
+                let key = K::deserialize_limited(reader, limiter)?;
+                let value = V::deserialize_limited(reader, limiter)?;
+                map.insert(key, value);
+            }
+            Ok(map)
+        })
+    }
+}
+
+impl Serialize for String {
+    fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        self.serialize_with(writer, &Config::DEFAULT)
+    }
+
+    fn serialize_with<W>(&self, writer: &mut W, config: &Config) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        serialize_bytes_with(self.as_bytes(), writer, config)
+    }
+
+    fn serialized_length(&self) -> usize {
+        serialized_bytes_length(self.as_bytes())
+    }
+}
+
+impl Deserialize for String {
+    fn deserialize<R>(reader: &mut R) -> crate::Result<Self>
+    where
+        R: crate::io::Read,
+    {
+        deserialize_with_limits(reader, DecodeLimits::DEFAULT)
+    }
+
+    fn deserialize_limited<R>(reader: &mut R, limiter: &mut Limiter) -> crate::Result<Self>
+    where
+        R: crate::io::Read,
+    {
+        let buf = deserialize_bytes_limited(reader, limiter)?;
+        String::from_utf8(buf).map_err(|_| ErrorKind::InvalidUtf8.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ByteOrder;
+
+    #[test]
+    fn test_vec() {
+        let mut buf = Vec::new();
+        vec![1u8, 2, 3].serialize(&mut buf).unwrap();
+        assert_eq!(buf, vec![3, 0, 0, 0, 1, 2, 3]);
+        //                   ^- length is 4 bytes
+        assert_eq!(
+            Vec::<u8>::deserialize(&mut buf.as_slice()).unwrap(),
+            vec![1, 2, 3]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_hash_map() {
+        let mut buf = Vec::new();
+        let mut map = HashMap::new();
+        map.insert(1u8, 2u8);
+        map.insert(3, 4);
+        map.serialize(&mut buf).unwrap();
+        // iteration order of a `HashMap` is unspecified
+        assert_eq!(buf[..4], [2, 0, 0, 0]);
+        //                    ^- length is 4 bytes
+        assert!(
+            buf[4..] == [1, 2, 3, 4] || buf[4..] == [3, 4, 1, 2],
+            "{buf:?}"
+        );
+        assert_eq!(
+            HashMap::<u8, u8>::deserialize(&mut buf.as_slice()).unwrap(),
+            map
+        );
+    }
+
+    #[test]
+    fn test_btree_map() {
+        let mut buf = Vec::new();
+        let mut map = BTreeMap::new();
+        map.insert(1u8, 2u8);
+        map.insert(3, 4);
+        map.serialize(&mut buf).unwrap();
+        assert_eq!(buf, vec![2, 0, 0, 0, 1, 2, 3, 4]);
+        //                   ^- length is 4 bytes
+        assert_eq!(
+            BTreeMap::<u8, u8>::deserialize(&mut buf.as_slice()).unwrap(),
+            map
+        );
+    }
+
+    #[test]
+    fn test_serialized_length() {
+        assert_eq!(
+            vec![1u8, 2, 3].serialized_length(),
+            4 + 3,
+            "length (4 bytes) + 3 bytes"
+        );
+        let mut map = BTreeMap::new();
+        map.insert(1u8, 2u8);
+        map.insert(3, 4);
+        assert_eq!(map.serialized_length(), 8);
+        assert_eq!(
+            "abc".to_string().serialized_length(),
+            4 + 3,
+            "length (4 bytes) + 3 bytes"
+        );
+    }
+
+    #[test]
+    fn test_string() {
+        let mut buf = Vec::new();
+        let written = "abc".to_string().serialize(&mut buf).unwrap();
+        assert_eq!(written, buf.len());
+        assert_eq!(buf, vec![3, 0, 0, 0, b'a', b'b', b'c']);
+        assert_eq!(String::deserialize(&mut buf.as_slice()).unwrap(), "abc");
+    }
+
+    #[test]
+    fn test_big_endian() {
+        let config = Config::DEFAULT.with_byte_order(ByteOrder::Big);
+        let value = vec!["ab".to_string()];
+        let mut buf = Vec::new();
+        crate::serialize_with_config(&value, &mut buf, &config).unwrap();
+        assert_eq!(buf, vec![0, 0, 0, 1, 0, 0, 0, 2, b'a', b'b']);
+
+        let decoded: Vec<String> =
+            crate::deserialize_with_config(&mut buf.as_slice(), &config).unwrap();
+        assert_eq!(decoded, value);
+    }
+
+    #[test]
+    fn test_limits() {
+        // five bytes announcing four billion elements allocate nothing
+        let huge = [0xff, 0xff, 0xff, 0xff, 0];
+        let err = Vec::<u8>::deserialize(&mut huge.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+        let err = String::deserialize(&mut huge.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+
+        let limits = DecodeLimits::DEFAULT.with_max_elements(usize::MAX);
+        let err = deserialize_with_limits::<Vec<u64>, _>(&mut huge.as_slice(), limits).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Truncated);
+
+        let nested = vec![vec![vec![1u8]]];
+        let mut buf = Vec::new();
+        nested.serialize(&mut buf).unwrap();
+        let limits = DecodeLimits::DEFAULT.with_max_depth(2);
+        let err = deserialize_with_limits::<Vec<Vec<Vec<u8>>>, _>(&mut buf.as_slice(), limits)
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+        let limits = DecodeLimits::DEFAULT.with_max_depth(3);
+        let value: Vec<Vec<Vec<u8>>> =
+            deserialize_with_limits(&mut buf.as_slice(), limits).unwrap();
+        assert_eq!(value, nested);
+
+        let limits = DecodeLimits::DEFAULT.with_max_string_length(2);
+        let err =
+            deserialize_with_limits::<String, _>(&mut [3, 0, 0, 0, 1, 2, 3].as_slice(), limits)
+                .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LimitExceeded);
+    }
+}
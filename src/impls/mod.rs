@@ -1,19 +1,24 @@
-use crate::{Deserialize, Serialize};
-use std::collections::{BTreeMap, HashMap};
-
+use crate::{
+    deserialize_with_limits, serialize_bytes_with, serialized_bytes_length, Config, DecodeLimits,
+    Deserialize, Limiter, Serialize,
+};
+
+mod borrow;
+#[cfg(feature = "alloc")]
+mod collections;
 pub mod num;
 
 impl<const LENGTH: usize> Serialize for [u8; LENGTH] {
-    fn serialize<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
     where
-        W: std::io::Write,
+        W: crate::io::Write,
     {
         writer.write_all(self)?;
         Ok(LENGTH)
     }
 
-    fn serialized_length(&self) -> u16 {
-        LENGTH as u16
+    fn serialized_length(&self) -> usize {
+        LENGTH
     }
 }
 
@@ -21,15 +26,22 @@ impl<T> Serialize for Option<T>
 where
     T: Serialize,
 {
-    fn serialize<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        self.serialize_with(writer, &Config::DEFAULT)
+    }
+
+    fn serialize_with<W>(&self, writer: &mut W, config: &Config) -> crate::Result<usize>
     where
-        W: std::io::Write,
+        W: crate::io::Write,
     {
         match self {
             Some(value) => {
                 let mut len = 0;
                 len += 1u8.serialize(writer)?;
-                len += value.serialize(writer)?;
+                len += value.serialize_with(writer, config)?;
                 Ok(len)
             }
             None => {
@@ -39,99 +51,18 @@ where
         }
     }
 
-    fn serialized_length(&self) -> u16 {
+    fn serialized_length(&self) -> usize {
         match self {
-            Some(value) => 1 + value.serialized_length(),
+            Some(value) => value.serialized_length().saturating_add(1),
             None => 1,
         }
     }
 }
 
-impl<T> Serialize for Vec<T>
-where
-    T: Serialize,
-{
-    fn serialize<W>(&self, writer: &mut W) -> std::io::Result<usize>
-    where
-        W: std::io::Write,
-    {
-        let mut len = 0;
-        len += (self.len() as u32).serialize(writer)?;
-        for item in self {
-            len += item.serialize(writer)?;
-        }
-        Ok(len)
-    }
-
-    fn serialized_length(&self) -> u16 {
-        let mut len = std::mem::size_of::<u32>() as u16;
-        for item in self {
-            len += item.serialized_length();
-        }
-        len
-    }
-}
-
-impl<K, V> Serialize for HashMap<K, V>
-where
-    K: Serialize,
-    V: Serialize,
-{
-    fn serialize<W>(&self, writer: &mut W) -> std::io::Result<usize>
-    where
-        W: std::io::Write,
-    {
-        let mut len = 0;
-        len += (self.len() as u32).serialize(writer)?;
-        for (key, value) in self {
-            len += key.serialize(writer)?;
-            len += value.serialize(writer)?;
-        }
-        Ok(len)
-    }
-
-    fn serialized_length(&self) -> u16 {
-        let mut len = std::mem::size_of::<u32>() as u16;
-        for (key, value) in self {
-            len += key.serialized_length();
-            len += value.serialized_length();
-        }
-        len
-    }
-}
-
-impl<K, V> Serialize for BTreeMap<K, V>
-where
-    K: Serialize,
-    V: Serialize,
-{
-    fn serialize<W>(&self, writer: &mut W) -> std::io::Result<usize>
-    where
-        W: std::io::Write,
-    {
-        let mut len = 0;
-        len += (self.len() as u32).serialize(writer)?;
-        for (key, value) in self {
-            len += key.serialize(writer)?;
-            len += value.serialize(writer)?;
-        }
-        Ok(len)
-    }
-
-    fn serialized_length(&self) -> u16 {
-        let mut len = std::mem::size_of::<u32>() as u16;
-        for (key, value) in self {
-            len += key.serialized_length();
-            len += value.serialized_length();
-        }
-        len
-    }
-}
-
 impl<const LENGTH: usize> Deserialize for [u8; LENGTH] {
-    fn deserialize<R>(reader: &mut R) -> std::io::Result<Self>
+    fn deserialize<R>(reader: &mut R) -> crate::Result<Self>
     where
-        R: std::io::Read,
+        R: crate::io::Read,
     {
         let mut buf = [0; LENGTH];
         reader.read_exact(&mut buf)?;
@@ -143,107 +74,67 @@ impl<T> Deserialize for Option<T>
 where
     T: Deserialize,
 {
-    fn deserialize<R>(reader: &mut R) -> std::io::Result<Self>
+    fn deserialize<R>(reader: &mut R) -> crate::Result<Self>
+    where
+        R: crate::io::Read,
+    {
+        deserialize_with_limits(reader, DecodeLimits::DEFAULT)
+    }
+
+    fn deserialize_limited<R>(reader: &mut R, limiter: &mut Limiter) -> crate::Result<Self>
     where
-        R: std::io::Read,
+        R: crate::io::Read,
     {
         let has_value = u8::deserialize(reader)?;
         if has_value == 0 {
             Ok(None)
         } else {
-            Ok(Some(T::deserialize(reader)?))
+            Ok(Some(T::deserialize_limited(reader, limiter)?))
         }
     }
 }
 
-impl<T> Deserialize for Vec<T>
-where
-    T: Deserialize,
-{
-    fn deserialize<R>(reader: &mut R) -> std::io::Result<Self>
+impl Serialize for &[u8] {
+    fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
     where
-        R: std::io::Read,
+        W: crate::io::Write,
     {
-        let len = u32::deserialize(reader)? as usize;
-        let mut vec = Vec::with_capacity(len);
-        for _ in 0..len {
-            vec.push(T::deserialize(reader)?);
-        }
-        Ok(vec)
+        self.serialize_with(writer, &Config::DEFAULT)
     }
-}
 
-impl<K, V> Deserialize for HashMap<K, V>
-where
-    K: Deserialize + Eq + std::hash::Hash,
-    V: Deserialize,
-{
-    fn deserialize<R>(reader: &mut R) -> std::io::Result<Self>
+    fn serialize_with<W>(&self, writer: &mut W, config: &Config) -> crate::Result<usize>
     where
-        R: std::io::Read,
+        W: crate::io::Write,
     {
-        let len = u32::deserialize(reader)? as usize;
-        let mut map = HashMap::with_capacity(len);
-        for _ in 0..len {
-            let key = K::deserialize(reader)?;
-            let value = V::deserialize(reader)?;
-            map.insert(key, value);
-        }
-        Ok(map)
+        serialize_bytes_with(self, writer, config)
     }
-}
 
-impl<K, V> Deserialize for BTreeMap<K, V>
-where
-    K: Deserialize + Ord,
-    V: Deserialize,
-{
-    fn deserialize<R>(reader: &mut R) -> std::io::Result<Self>
-    where
-        R: std::io::Read,
-    {
-        let len = u32::deserialize(reader)? as usize;
-        let mut map = BTreeMap::new();
-        for _ in 0..len {
-            let key = K::deserialize(reader)?;
-            let value = V::deserialize(reader)?;
-            map.insert(key, value);
-        }
-        Ok(map)
+    fn serialized_length(&self) -> usize {
+        serialized_bytes_length(self)
     }
 }
 
-impl Serialize for String {
-    fn serialize<W>(&self, writer: &mut W) -> std::io::Result<usize>
+impl Serialize for &str {
+    fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
     where
-        W: std::io::Write,
+        W: crate::io::Write,
     {
-        let bytes = self.as_bytes();
-        let len = bytes.len() as u32;
-        len.serialize(writer)?;
-        writer.write_all(bytes)?;
-        Ok(len as usize + bytes.len())
+        self.serialize_with(writer, &Config::DEFAULT)
     }
 
-    fn serialized_length(&self) -> u16 {
-        let bytes = self.as_bytes();
-        (std::mem::size_of::<u16>() + bytes.len()) as u16
-    }
-}
-
-impl Deserialize for String {
-    fn deserialize<R>(reader: &mut R) -> std::io::Result<Self>
+    fn serialize_with<W>(&self, writer: &mut W, config: &Config) -> crate::Result<usize>
     where
-        R: std::io::Read,
+        W: crate::io::Write,
     {
-        let len = u32::deserialize(reader)? as usize;
-        let mut buf = vec![0; len];
-        reader.read_exact(&mut buf)?;
-        String::from_utf8(buf).map_err(|_| std::io::ErrorKind::InvalidData.into())
+        serialize_bytes_with(self.as_bytes(), writer, config)
+    }
+
+    fn serialized_length(&self) -> usize {
+        serialized_bytes_length(self.as_bytes())
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "alloc"))]
 mod tests {
     use super::*;
 
@@ -280,45 +171,64 @@ mod tests {
     }
 
     #[test]
-    fn test_vec() {
+    fn test_signed() {
+        let mut buf = Vec::new();
+        (-2i16).serialize(&mut buf).unwrap();
+        (-1i128).serialize(&mut buf).unwrap();
+        assert_eq!(buf[..2], [0xfe, 0xff]);
+        assert_eq!(buf[2..], [0xff; 16]);
+
+        let mut reader = buf.as_slice();
+        assert_eq!(i16::deserialize(&mut reader).unwrap(), -2);
+        assert_eq!(i128::deserialize(&mut reader).unwrap(), -1);
+    }
+
+    #[test]
+    fn test_size() {
         let mut buf = Vec::new();
-        vec![1u8, 2, 3].serialize(&mut buf).unwrap();
-        assert_eq!(buf, vec![3, 0, 0, 0, 1, 2, 3]);
-        //                   ^- length is 4 bytes
-        assert_eq!(
-            Vec::<u8>::deserialize(&mut buf.as_slice()).unwrap(),
-            vec![1, 2, 3]
-        );
+        7usize.serialize(&mut buf).unwrap();
+        (-7isize).serialize(&mut buf).unwrap();
+        assert_eq!(buf.len(), 16);
+
+        let mut reader = buf.as_slice();
+        assert_eq!(usize::deserialize(&mut reader).unwrap(), 7);
+        assert_eq!(isize::deserialize(&mut reader).unwrap(), -7);
+
+        if core::mem::size_of::<usize>() < 8 {
+            let err = usize::deserialize(&mut [0xff; 8].as_slice()).unwrap_err();
+            assert_eq!(err.kind(), crate::ErrorKind::OutOfRange);
+        }
     }
 
     #[test]
-    fn test_hash_map() {
+    fn test_floats() {
         let mut buf = Vec::new();
-        let mut map = HashMap::new();
-        map.insert(1u8, 2u8);
-        map.insert(3, 4);
-        map.serialize(&mut buf).unwrap();
-        assert_eq!(buf, vec![2, 0, 0, 0, 1, 2, 3, 4]);
-        //                   ^- length is 4 bytes
-        assert_eq!(
-            HashMap::<u8, u8>::deserialize(&mut buf.as_slice()).unwrap(),
-            map
-        );
+        1.5f32.serialize(&mut buf).unwrap();
+        assert_eq!(buf, 1.5f32.to_le_bytes());
+        assert_eq!(f32::deserialize(&mut buf.as_slice()).unwrap(), 1.5);
+
+        // every NaN is written the same way
+        let mut buf = Vec::new();
+        f64::from_bits(0xfff0_0000_0000_0001)
+            .serialize(&mut buf)
+            .unwrap();
+        (-f64::NAN).serialize(&mut buf).unwrap();
+        assert_eq!(buf[..8], [0, 0, 0, 0, 0, 0, 0xf8, 0x7f]);
+        assert_eq!(buf[..8], buf[8..]);
+        assert!(f64::deserialize(&mut buf.as_slice()).unwrap().is_nan());
     }
 
     #[test]
-    fn test_btree_map() {
+    fn test_char() {
         let mut buf = Vec::new();
-        let mut map = BTreeMap::new();
-        map.insert(1u8, 2u8);
-        map.insert(3, 4);
-        map.serialize(&mut buf).unwrap();
-        assert_eq!(buf, vec![2, 0, 0, 0, 1, 2, 3, 4]);
-        //                   ^- length is 4 bytes
-        assert_eq!(
-            BTreeMap::<u8, u8>::deserialize(&mut buf.as_slice()).unwrap(),
-            map
-        );
+        'é'.serialize(&mut buf).unwrap();
+        assert_eq!(buf, [0xe9, 0, 0, 0]);
+        assert_eq!(char::deserialize(&mut buf.as_slice()).unwrap(), 'é');
+
+        for invalid in [0xd800u32, 0x11_0000] {
+            let err = char::deserialize(&mut invalid.to_le_bytes().as_slice()).unwrap_err();
+            assert_eq!(err.kind(), crate::ErrorKind::InvalidChar);
+        }
     }
 
     #[test]
@@ -327,18 +237,10 @@ mod tests {
         assert_eq!(42u16.serialized_length(), 2);
         assert_eq!(42u32.serialized_length(), 4);
         assert_eq!(42u64.serialized_length(), 8);
-        assert_eq!(
-            vec![1u8, 2, 3].serialized_length(),
-            4 + 3,
-            "length (4 bytes) + 3 bytes"
-        );
-        let mut map = HashMap::new();
-        map.insert(1u8, 2u8);
-        map.insert(3, 4);
-        assert_eq!(map.serialized_length(), 8);
-        let mut map = BTreeMap::new();
-        map.insert(1u8, 2u8);
-        map.insert(3, 4);
-        assert_eq!(map.serialized_length(), 8);
+        assert_eq!(42i128.serialized_length(), 16);
+        assert_eq!(42usize.serialized_length(), 8);
+        assert_eq!(1.0f32.serialized_length(), 4);
+        assert_eq!('a'.serialized_length(), 4);
+        assert_eq!(Some(42u16).serialized_length(), 3);
     }
 }
@@ -1,103 +1,214 @@
-use crate::{Deserialize, Serialize};
+use crate::{ByteOrder, Config, Deserialize, ErrorKind, Limiter, Serialize};
 
 impl Serialize for bool {
-    fn serialize<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
     where
-        W: std::io::Write,
+        W: crate::io::Write,
     {
         (if *self { 1u8 } else { 0u8 }).serialize(writer)
     }
-}
 
-impl Serialize for u8 {
-    fn serialize<W>(&self, writer: &mut W) -> std::io::Result<usize>
-    where
-        W: std::io::Write,
-    {
-        writer.write_all(&[*self])?;
-        Ok(1)
+    fn serialized_length(&self) -> usize {
+        core::mem::size_of::<Self>()
     }
 }
 
-impl Serialize for u16 {
-    fn serialize<W>(&self, writer: &mut W) -> std::io::Result<usize>
+impl Deserialize for bool {
+    fn deserialize<R>(reader: &mut R) -> crate::Result<Self>
     where
-        W: std::io::Write,
+        R: crate::io::Read,
     {
-        writer.write_all(&self.to_le_bytes())?;
-        Ok(2)
+        Ok(u8::deserialize(reader)? != 0)
     }
 }
 
-impl Serialize for u32 {
-    fn serialize<W>(&self, writer: &mut W) -> std::io::Result<usize>
-    where
-        W: std::io::Write,
-    {
-        writer.write_all(&self.to_le_bytes())?;
-        Ok(4)
-    }
+/// Integers are written as their bytes in the configured byte order,
+/// little-endian by default, signed ones in two's complement.
+macro_rules! impl_int {
+    ($($ty:ty),*) => {
+        $(
+            impl Serialize for $ty {
+                fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
+                where
+                    W: crate::io::Write,
+                {
+                    self.serialize_with(writer, &Config::DEFAULT)
+                }
+
+                fn serialize_with<W>(&self, writer: &mut W, config: &Config) -> crate::Result<usize>
+                where
+                    W: crate::io::Write,
+                {
+                    match config.byte_order {
+                        ByteOrder::Little => writer.write_all(&self.to_le_bytes())?,
+                        ByteOrder::Big => writer.write_all(&self.to_be_bytes())?,
+                    }
+                    Ok(core::mem::size_of::<Self>())
+                }
+
+                fn serialized_length(&self) -> usize {
+                    core::mem::size_of::<Self>()
+                }
+            }
+
+            impl Deserialize for $ty {
+                fn deserialize<R>(reader: &mut R) -> crate::Result<Self>
+                where
+                    R: crate::io::Read,
+                {
+                    Self::deserialize_limited(reader, &mut Limiter::default())
+                }
+
+                fn deserialize_limited<R>(reader: &mut R, limiter: &mut Limiter) -> crate::Result<Self>
+                where
+                    R: crate::io::Read,
+                {
+                    let mut buf = [0; core::mem::size_of::<$ty>()];
+                    reader.read_exact(&mut buf)?;
+                    Ok(match limiter.byte_order() {
+                        ByteOrder::Little => <$ty>::from_le_bytes(buf),
+                        ByteOrder::Big => <$ty>::from_be_bytes(buf),
+                    })
+                }
+            }
+        )*
+    };
 }
 
-impl Serialize for u64 {
-    fn serialize<W>(&self, writer: &mut W) -> std::io::Result<usize>
-    where
-        W: std::io::Write,
-    {
-        writer.write_all(&self.to_le_bytes())?;
-        Ok(8)
-    }
+impl_int!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+/// `usize` and `isize` are written as `u64` and `i64` whatever the platform,
+/// a value that does not fit on either end is [`ErrorKind::OutOfRange`].
+macro_rules! impl_size {
+    ($($ty:ty => $wire:ty),*) => {
+        $(
+            impl Serialize for $ty {
+                fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
+                where
+                    W: crate::io::Write,
+                {
+                    self.serialize_with(writer, &Config::DEFAULT)
+                }
+
+                fn serialize_with<W>(&self, writer: &mut W, config: &Config) -> crate::Result<usize>
+                where
+                    W: crate::io::Write,
+                {
+                    <$wire>::try_from(*self)
+                        .map_err(|_| crate::Error::from(ErrorKind::OutOfRange))?
+                        .serialize_with(writer, config)
+                }
+
+                fn serialized_length(&self) -> usize {
+                    core::mem::size_of::<$wire>()
+                }
+            }
+
+            impl Deserialize for $ty {
+                fn deserialize<R>(reader: &mut R) -> crate::Result<Self>
+                where
+                    R: crate::io::Read,
+                {
+                    Self::deserialize_limited(reader, &mut Limiter::default())
+                }
+
+                fn deserialize_limited<R>(reader: &mut R, limiter: &mut Limiter) -> crate::Result<Self>
+                where
+                    R: crate::io::Read,
+                {
+                    <$ty>::try_from(<$wire>::deserialize_limited(reader, limiter)?)
+                        .map_err(|_| ErrorKind::OutOfRange.into())
+                }
+            }
+        )*
+    };
 }
 
-impl Deserialize for bool {
-    fn deserialize<R>(reader: &mut R) -> std::io::Result<Self>
-    where
-        R: std::io::Read,
-    {
-        Ok(u8::deserialize(reader)? != 0)
-    }
+impl_size!(usize => u64, isize => i64);
+
+/// Floats are written as the bytes of their IEEE 754 binary representation
+/// in the configured byte order, little-endian by default. Every NaN is written as the canonical quiet NaN, so equal
+/// values always have equal encodings, while any NaN is accepted on decode.
+macro_rules! impl_float {
+    ($($ty:ty => $nan:expr),*) => {
+        $(
+            impl Serialize for $ty {
+                fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
+                where
+                    W: crate::io::Write,
+                {
+                    self.serialize_with(writer, &Config::DEFAULT)
+                }
+
+                fn serialize_with<W>(&self, writer: &mut W, config: &Config) -> crate::Result<usize>
+                where
+                    W: crate::io::Write,
+                {
+                    let bits = if self.is_nan() { $nan } else { self.to_bits() };
+                    bits.serialize_with(writer, config)
+                }
+
+                fn serialized_length(&self) -> usize {
+                    core::mem::size_of::<Self>()
+                }
+            }
+
+            impl Deserialize for $ty {
+                fn deserialize<R>(reader: &mut R) -> crate::Result<Self>
+                where
+                    R: crate::io::Read,
+                {
+                    Self::deserialize_limited(reader, &mut Limiter::default())
+                }
+
+                fn deserialize_limited<R>(reader: &mut R, limiter: &mut Limiter) -> crate::Result<Self>
+                where
+                    R: crate::io::Read,
+                {
+                    Deserialize::deserialize_limited(reader, limiter).map(<$ty>::from_bits)
+                }
+            }
+        )*
+    };
 }
 
-impl Deserialize for u8 {
-    fn deserialize<R>(reader: &mut R) -> std::io::Result<Self>
+impl_float!(f32 => 0x7fc0_0000u32, f64 => 0x7ff8_0000_0000_0000u64);
+
+/// A `char` is written as its scalar value, a `u32`.
+impl Serialize for char {
+    fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
     where
-        R: std::io::Read,
+        W: crate::io::Write,
     {
-        let mut buf = [0; 1];
-        reader.read_exact(&mut buf)?;
-        Ok(buf[0])
+        self.serialize_with(writer, &Config::DEFAULT)
     }
-}
 
-impl Deserialize for u16 {
-    fn deserialize<R>(reader: &mut R) -> std::io::Result<Self>
+    fn serialize_with<W>(&self, writer: &mut W, config: &Config) -> crate::Result<usize>
     where
-        R: std::io::Read,
+        W: crate::io::Write,
     {
-        let mut buf = [0; 2];
-        reader.read_exact(&mut buf)?;
-        Ok(u16::from_le_bytes(buf))
+        u32::from(*self).serialize_with(writer, config)
+    }
+
+    fn serialized_length(&self) -> usize {
+        core::mem::size_of::<u32>()
     }
 }
 
-impl Deserialize for u32 {
-    fn deserialize<R>(reader: &mut R) -> std::io::Result<Self>
+/// Surrogates and values past `char::MAX` are [`ErrorKind::InvalidChar`].
+impl Deserialize for char {
+    fn deserialize<R>(reader: &mut R) -> crate::Result<Self>
     where
-        R: std::io::Read,
+        R: crate::io::Read,
     {
-        let mut buf = [0; 4];
-        reader.read_exact(&mut buf)?;
-        Ok(u32::from_le_bytes(buf))
+        Self::deserialize_limited(reader, &mut Limiter::default())
     }
-}
 
-impl Deserialize for u64 {
-    fn deserialize<R>(reader: &mut R) -> std::io::Result<Self>
+    fn deserialize_limited<R>(reader: &mut R, limiter: &mut Limiter) -> crate::Result<Self>
     where
-        R: std::io::Read,
+        R: crate::io::Read,
     {
-        let mut buf = [0; 8];
-        reader.read_exact(&mut buf)?;
-        Ok(u64::from_le_bytes(buf))
+        char::from_u32(u32::deserialize_limited(reader, limiter)?)
+            .ok_or_else(|| ErrorKind::InvalidChar.into())
     }
 }
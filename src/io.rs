@@ -0,0 +1,210 @@
+//! The readers and writers values are decoded from and encoded to.
+//!
+//! With the `std` feature these are the traits of `std::io`, so every reader
+//! and writer of the standard library can be used as is. Without it they are
+//! minimal stand-ins with the methods the crate relies on, implemented for
+//! byte slices and, with the `alloc` feature, for `Vec<u8>`. Code written
+//! against this module works the same either way.
+
+#[cfg(feature = "std")]
+pub use std::io::{Error, ErrorKind, Read, Result, Write};
+
+#[cfg(not(feature = "std"))]
+pub use self::core_io::{Error, ErrorKind, Read, Result, Write};
+
+#[cfg(not(feature = "std"))]
+mod core_io {
+    use core::fmt;
+
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    /// Cause of an [`Error`], a subset of the one of `std::io`.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+    #[non_exhaustive]
+    pub enum ErrorKind {
+        /// The reader ended before the buffer could be filled.
+        UnexpectedEof,
+        /// The writer stopped accepting bytes.
+        WriteZero,
+        /// The operation was interrupted and can be retried.
+        Interrupted,
+        /// Any other failure of the underlying reader or writer.
+        Other,
+    }
+
+    /// Error of a [`Read`] or [`Write`] implementation.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Error {
+        kind: ErrorKind,
+    }
+
+    impl Error {
+        pub fn kind(&self) -> ErrorKind {
+            self.kind
+        }
+    }
+
+    impl From<ErrorKind> for Error {
+        fn from(kind: ErrorKind) -> Self {
+            Self { kind }
+        }
+    }
+
+    impl fmt::Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(match self.kind {
+                ErrorKind::UnexpectedEof => "unexpected end of file",
+                ErrorKind::WriteZero => "write zero",
+                ErrorKind::Interrupted => "operation interrupted",
+                ErrorKind::Other => "other error",
+            })
+        }
+    }
+
+    impl core::error::Error for Error {}
+
+    pub trait Read {
+        /// Reads some bytes into `buf` and returns how many, `0` at the end
+        /// of the input.
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize>;
+
+        /// Fills `buf`, failing with [`ErrorKind::UnexpectedEof`] if the
+        /// input ends before.
+        fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+            while !buf.is_empty() {
+                match self.read(buf) {
+                    Ok(0) => return Err(ErrorKind::UnexpectedEof.into()),
+                    Ok(read) => buf = &mut buf[read..],
+                    Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            Ok(())
+        }
+    }
+
+    pub trait Write {
+        /// Writes some bytes of `buf` and returns how many.
+        fn write(&mut self, buf: &[u8]) -> Result<usize>;
+
+        fn flush(&mut self) -> Result<()>;
+
+        /// Writes all of `buf`, failing with [`ErrorKind::WriteZero`] if the
+        /// writer stops accepting bytes before.
+        fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+            while !buf.is_empty() {
+                match self.write(buf) {
+                    Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                    Ok(written) => buf = &buf[written..],
+                    Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                    Err(err) => return Err(err),
+                }
+            }
+            Ok(())
+        }
+    }
+
+    impl<R> Read for &mut R
+    where
+        R: Read + ?Sized,
+    {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            (**self).read(buf)
+        }
+
+        fn read_exact(&mut self, buf: &mut [u8]) -> Result<()> {
+            (**self).read_exact(buf)
+        }
+    }
+
+    impl<W> Write for &mut W
+    where
+        W: Write + ?Sized,
+    {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            (**self).write(buf)
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            (**self).flush()
+        }
+
+        fn write_all(&mut self, buf: &[u8]) -> Result<()> {
+            (**self).write_all(buf)
+        }
+    }
+
+    /// Reading advances the slice past the bytes read.
+    impl Read for &[u8] {
+        fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+            let len = buf.len().min(self.len());
+            let (read, rest) = self.split_at(len);
+            buf[..len].copy_from_slice(read);
+            *self = rest;
+            Ok(len)
+        }
+    }
+
+    /// Writing fills the slice from the front and advances it past the
+    /// bytes written, a full slice accepts no more bytes.
+    impl Write for &mut [u8] {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            let len = buf.len().min(self.len());
+            let (written, rest) = core::mem::take(self).split_at_mut(len);
+            written.copy_from_slice(&buf[..len]);
+            *self = rest;
+            Ok(len)
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    impl Write for alloc::vec::Vec<u8> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(all(test, not(feature = "std")))]
+mod tests {
+    use super::*;
+    use crate::{Deserialize, ErrorKind as TlvErrorKind, Serialize};
+
+    #[test]
+    fn test_slices() {
+        let mut buf = [0; 7];
+        let mut writer = buf.as_mut_slice();
+        7u16.serialize(&mut writer).unwrap();
+        "a".serialize(&mut writer).unwrap();
+        assert!(writer.is_empty());
+
+        let err = 1u8.serialize(&mut writer).unwrap_err();
+        assert_eq!(err.kind(), TlvErrorKind::Io);
+
+        let mut reader = buf.as_slice();
+        assert_eq!(u16::deserialize(&mut reader).unwrap(), 7);
+        assert_eq!(u32::deserialize(&mut reader).unwrap(), 1);
+        let err = u16::deserialize(&mut reader).unwrap_err();
+        assert_eq!(err.kind(), TlvErrorKind::Truncated);
+    }
+
+    #[test]
+    fn test_read_exact() {
+        let mut reader: &[u8] = &[1, 2, 3];
+        let mut buf = [0; 2];
+        reader.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [1, 2]);
+
+        let err = reader.read_exact(&mut buf).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,434 @@
+use crate::{ber, varint, Config, Error, ErrorKind, Serialize};
+
+/// Order of the bytes of the integers on the wire: of the tags and lengths
+/// of records, and of the integer values in them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ByteOrder {
+    /// Least significant byte first, the default.
+    #[default]
+    Little,
+    /// Most significant byte first, the network byte order.
+    Big,
+}
+
+/// Encoding of an integer in the header of a TLV record, i.e. of a tag
+/// or of a length.
+///
+/// The fixed-size encodings follow the byte order of the [`Layout`]. The
+/// variable-length ones, see [`varint`](crate::varint), define their own and
+/// must be in their shortest form.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntEncoding {
+    U8,
+    U16,
+    U32,
+    U64,
+    /// Unsigned LEB128, 1 to 10 bytes.
+    Leb128,
+    /// Lightning BigSize, 1, 3, 5 or 9 bytes.
+    BigSize,
+    /// QUIC variable-length integer, 1, 2, 4 or 8 bytes, up to 62 bits.
+    Quic,
+    /// BER identifier octets, 1 to 8 bytes, see [`ber::Tag`](crate::ber::Tag).
+    /// The integer is the octets read as big-endian, e.g. `0x9f02`, and has
+    /// to be a well-formed tag.
+    BerTag,
+    /// BER definite length: below `0x80` a single byte, else `0x80` plus the
+    /// number of bytes of the big-endian length that follows. Unlike the
+    /// other encodings, a length in more bytes than needed is also read.
+    BerLength,
+}
+
+impl IntEncoding {
+    /// Number of bytes the encoding occupies on the wire, at most for the
+    /// variable-length encodings.
+    pub const fn size(&self) -> usize {
+        match self {
+            IntEncoding::U8 => 1,
+            IntEncoding::U16 => 2,
+            IntEncoding::U32 => 4,
+            IntEncoding::U64 => 8,
+            IntEncoding::Leb128 => varint::MAX_SIZE,
+            IntEncoding::BigSize => 9,
+            IntEncoding::Quic => 8,
+            IntEncoding::BerTag => 8,
+            IntEncoding::BerLength => 9,
+        }
+    }
+
+    /// The largest value that can be represented.
+    pub const fn max(&self) -> u64 {
+        match self {
+            IntEncoding::U8 => u8::MAX as u64,
+            IntEncoding::U16 => u16::MAX as u64,
+            IntEncoding::U32 => u32::MAX as u64,
+            IntEncoding::U64
+            | IntEncoding::Leb128
+            | IntEncoding::BigSize
+            | IntEncoding::BerTag
+            | IntEncoding::BerLength => u64::MAX,
+            IntEncoding::Quic => varint::QUIC_MAX,
+        }
+    }
+
+    /// Whether the size of an encoded integer depends on its value.
+    pub const fn is_variable(&self) -> bool {
+        matches!(
+            self,
+            IntEncoding::Leb128
+                | IntEncoding::BigSize
+                | IntEncoding::Quic
+                | IntEncoding::BerTag
+                | IntEncoding::BerLength
+        )
+    }
+
+    /// Number of bytes `value` occupies on the wire.
+    pub fn encoded_size(&self, value: u64) -> usize {
+        varint::encoded_size(*self, value)
+    }
+
+    /// Writes `value`, which has to fit into the encoding, and be a
+    /// well-formed tag for [`BerTag`](Self::BerTag).
+    pub(crate) fn write<W>(
+        &self,
+        writer: &mut W,
+        value: u64,
+        order: ByteOrder,
+    ) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        if value > self.max() || (*self == IntEncoding::BerTag && !ber::is_tag(value)) {
+            return Err(Error::new(ErrorKind::OutOfRange));
+        }
+
+        if self.is_variable() {
+            let mut buf = [0; varint::MAX_SIZE];
+            let size = varint::encode(*self, value, &mut buf);
+            writer.write_all(&buf[..size])?;
+            return Ok(size);
+        }
+
+        let size = self.size();
+        match order {
+            ByteOrder::Little => writer.write_all(&value.to_le_bytes()[..size])?,
+            ByteOrder::Big => writer.write_all(&value.to_be_bytes()[8 - size..])?,
+        }
+        Ok(size)
+    }
+
+    pub(crate) fn read<R>(&self, reader: &mut R, order: ByteOrder) -> crate::Result<u64>
+    where
+        R: crate::io::Read,
+    {
+        let mut buf = [0; varint::MAX_SIZE];
+        let len = self.read_rest(reader, &mut buf, 0)?;
+        self.decode(&buf[..len], order)
+    }
+
+    /// Reads the bytes of an integer into `buf` past the `len` already
+    /// there, and returns the number of bytes it has in total.
+    fn read_rest<R>(
+        &self,
+        reader: &mut R,
+        buf: &mut [u8; varint::MAX_SIZE],
+        mut len: usize,
+    ) -> crate::Result<usize>
+    where
+        R: crate::io::Read,
+    {
+        loop {
+            match varint::remaining(*self, &buf[..len]) {
+                0 => return Ok(len),
+                more => {
+                    reader.read_exact(&mut buf[len..len + more])?;
+                    len += more;
+                }
+            }
+        }
+    }
+
+    /// Decodes the bytes of an integer, as delimited by
+    /// [`varint::remaining`].
+    pub(crate) fn decode(&self, bytes: &[u8], order: ByteOrder) -> crate::Result<u64> {
+        if self.is_variable() {
+            return varint::decode(*self, bytes);
+        }
+
+        let mut buf = [0; 8];
+        let size = bytes.len();
+        Ok(match order {
+            ByteOrder::Little => {
+                buf[..size].copy_from_slice(bytes);
+                u64::from_le_bytes(buf)
+            }
+            ByteOrder::Big => {
+                buf[8 - size..].copy_from_slice(bytes);
+                u64::from_be_bytes(buf)
+            }
+        })
+    }
+}
+
+/// Wire layout of a TLV record header.
+///
+/// Both the derived `Serialize` and `Deserialize` implementations of a type
+/// and [`extract_raw`](crate::extract_raw) go through the same layout, so
+/// whatever is written can be read back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    /// Encoding of the record tag.
+    pub tag: IntEncoding,
+    /// Encoding of the record length.
+    pub length: IntEncoding,
+    /// Byte order of the tag, of the length and of the integers of the
+    /// values written by [`write_record`](Self::write_record).
+    pub byte_order: ByteOrder,
+}
+
+impl Layout {
+    /// `u8` tags followed by `u16` lengths, little-endian.
+    pub const DEFAULT: Layout = Layout::new(IntEncoding::U8, IntEncoding::U16);
+
+    /// A little-endian layout with the given tag and length encodings.
+    pub const fn new(tag: IntEncoding, length: IntEncoding) -> Self {
+        Self {
+            tag,
+            length,
+            byte_order: ByteOrder::Little,
+        }
+    }
+
+    /// Returns the same layout with a different tag encoding.
+    pub const fn with_tag(self, tag: IntEncoding) -> Self {
+        Self { tag, ..self }
+    }
+
+    /// Returns the same layout with a different length encoding.
+    pub const fn with_length(self, length: IntEncoding) -> Self {
+        Self { length, ..self }
+    }
+
+    /// Returns the same layout with a different byte order.
+    pub const fn with_byte_order(self, byte_order: ByteOrder) -> Self {
+        Self { byte_order, ..self }
+    }
+
+    /// Number of bytes a record tagged `tag` with a value of `length` bytes
+    /// occupies.
+    pub fn record_length(&self, tag: u64, length: usize) -> usize {
+        let header = self.tag.encoded_size(tag) + self.length.encoded_size(length as u64);
+        header.saturating_add(length)
+    }
+
+    pub fn write_tag<W>(&self, writer: &mut W, tag: u64) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        self.tag
+            .write(writer, tag, self.byte_order)
+            .map_err(|err| err.with_tag(tag))
+    }
+
+    pub fn read_tag<R>(&self, reader: &mut R) -> crate::Result<u64>
+    where
+        R: crate::io::Read,
+    {
+        self.tag.read(reader, self.byte_order)
+    }
+
+    /// Reads a tag, or returns `None` if the reader is exhausted before the
+    /// first byte of it.
+    pub fn read_tag_or_eof<R>(&self, reader: &mut R) -> crate::Result<Option<u64>>
+    where
+        R: crate::io::Read,
+    {
+        let mut buf = [0; varint::MAX_SIZE];
+        loop {
+            match reader.read(&mut buf[..1]) {
+                Ok(0) => return Ok(None),
+                Ok(_) => break,
+                Err(err) if err.kind() == crate::io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        let len = self.tag.read_rest(reader, &mut buf, 1)?;
+
+        self.tag.decode(&buf[..len], self.byte_order).map(Some)
+    }
+
+    /// Writes a whole record: the tag, the length of `value` and `value`
+    /// itself, in the byte order of the layout.
+    ///
+    /// Fails with [`ErrorKind::LengthMismatch`] if `value` writes a different
+    /// number of bytes than its `serialized_length` announced.
+    pub fn write_record<W, T>(&self, writer: &mut W, tag: u64, value: &T) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+        T: Serialize,
+    {
+        self.write_record_with(writer, tag, value.serialized_length(), |writer, config| {
+            value.serialize_with(writer, config)
+        })
+    }
+
+    /// Writes a whole record like [`write_record`](Self::write_record), whose
+    /// value of `length` bytes is written by `serialize` instead of a
+    /// [`Serialize`] impl. `serialize` is given the configuration of the
+    /// layout.
+    pub fn write_record_with<W, F>(
+        &self,
+        writer: &mut W,
+        tag: u64,
+        length: usize,
+        serialize: F,
+    ) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+        F: FnOnce(&mut W, &Config) -> crate::Result<usize>,
+    {
+        let mut written = self.write_tag(writer, tag)?;
+        written += self
+            .write_length(writer, length)
+            .map_err(|err| err.with_tag(tag))?;
+
+        let header = written;
+        let config = Config::DEFAULT.with_byte_order(self.byte_order);
+        let value_written =
+            serialize(writer, &config).map_err(|err| err.advance(header as u64).or_tag(tag))?;
+        if value_written != length {
+            return Err(Error::new(ErrorKind::LengthMismatch)
+                .with_tag(tag)
+                .with_offset(header as u64));
+        }
+
+        Ok(written + value_written)
+    }
+
+    /// Writes a record whose value is already encoded, e.g. the value of a
+    /// [`RawField`](crate::RawField) extracted earlier, back as it was.
+    pub fn write_raw<W>(&self, writer: &mut W, tag: u64, value: &[u8]) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        let mut written = self.write_tag(writer, tag)?;
+        written += self
+            .write_length(writer, value.len())
+            .map_err(|err| err.with_tag(tag))?;
+        writer.write_all(value)?;
+
+        Ok(written + value.len())
+    }
+
+    /// Writes a record length, failing with [`ErrorKind::OutOfRange`] if it
+    /// does not fit into the length encoding.
+    pub fn write_length<W>(&self, writer: &mut W, length: usize) -> crate::Result<usize>
+    where
+        W: crate::io::Write,
+    {
+        let length = u64::try_from(length).map_err(|_| ErrorKind::OutOfRange)?;
+        self.length.write(writer, length, self.byte_order)
+    }
+
+    pub fn read_length<R>(&self, reader: &mut R) -> crate::Result<usize>
+    where
+        R: crate::io::Read,
+    {
+        let length = self.length.read(reader, self.byte_order)?;
+        usize::try_from(length).map_err(|_| ErrorKind::OutOfRange.into())
+    }
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_widths() {
+        for (encoding, expected) in [
+            (IntEncoding::U8, vec![7]),
+            (IntEncoding::U16, vec![7, 0]),
+            (IntEncoding::U32, vec![7, 0, 0, 0]),
+        ] {
+            let layout = Layout::DEFAULT.with_tag(encoding);
+            let mut buf = Vec::new();
+            assert_eq!(layout.write_tag(&mut buf, 7).unwrap(), encoding.size());
+            assert_eq!(buf, expected);
+            assert_eq!(layout.read_tag(&mut buf.as_slice()).unwrap(), 7);
+        }
+    }
+
+    #[test]
+    fn test_big_endian() {
+        let layout =
+            Layout::new(IntEncoding::U16, IntEncoding::U32).with_byte_order(ByteOrder::Big);
+        let mut buf = Vec::new();
+        layout.write_record(&mut buf, 0x102, &0x0304u16).unwrap();
+        assert_eq!(buf, [1, 2, 0, 0, 0, 2, 3, 4]);
+
+        let mut reader = buf.as_slice();
+        assert_eq!(layout.read_tag(&mut reader).unwrap(), 0x102);
+        assert_eq!(layout.read_length(&mut reader).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_read_tag_or_eof() {
+        let layout = Layout::DEFAULT.with_tag(IntEncoding::U16);
+        assert_eq!(
+            layout.read_tag_or_eof(&mut [1, 2].as_slice()).unwrap(),
+            Some(0x201)
+        );
+        assert_eq!(layout.read_tag_or_eof(&mut [].as_slice()).unwrap(), None);
+        assert!(layout.read_tag_or_eof(&mut [1].as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_length_does_not_fit() {
+        let layout = Layout::DEFAULT.with_length(IntEncoding::U8);
+        let mut buf = Vec::new();
+
+        assert_eq!(layout.write_record(&mut buf, 1, &[0u8; 255]).unwrap(), 257);
+        assert_eq!(layout.record_length(1, 255), 257);
+
+        let err = layout.write_record(&mut buf, 2, &[0u8; 256]).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::OutOfRange);
+        assert_eq!(err.tag(), Some(2));
+    }
+
+    #[test]
+    fn test_tag_does_not_fit() {
+        let mut buf = Vec::new();
+        let err = Layout::DEFAULT.write_tag(&mut buf, 256).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::OutOfRange);
+        assert_eq!(err.tag(), Some(256));
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_write_record_with() {
+        let layout = Layout::DEFAULT.with_byte_order(ByteOrder::Big);
+        let mut buf = Vec::new();
+        let written = layout
+            .write_record_with(&mut buf, 1, 2, |writer, config| {
+                0x0102u16.serialize_with(writer, config)
+            })
+            .unwrap();
+        assert_eq!(written, 5);
+        assert_eq!(buf, [1, 0, 2, 1, 2]);
+
+        let err = layout
+            .write_record_with(&mut buf, 2, 1, |writer, config| {
+                0u16.serialize_with(writer, config)
+            })
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::LengthMismatch);
+        assert_eq!(err.tag(), Some(2));
+    }
+}
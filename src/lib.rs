@@ -1,12 +1,95 @@
-pub use crate::deser::utils::{deserialize, deserialize_bytes};
-pub use crate::deser::{extract_raw, Deserialize, RawField};
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
-pub use crate::ser::utils::{serialize, serialize_bytes};
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+pub use crate::config::Config;
+#[cfg(feature = "alloc")]
+pub use crate::deser::borrow::{
+    borrow_bolt, borrow_bolt_deny_unknown, borrow_bolt_with_unknown, borrow_der, borrow_raw,
+    borrow_stream, borrow_stream_deny_unknown, borrow_stream_with_unknown,
+};
+pub use crate::deser::borrow::{
+    borrow_bytes, borrow_bytes_limited, borrow_record, BorrowDeserialize, BorrowedField,
+};
+pub use crate::deser::limits::{
+    deserialize_with_config, deserialize_with_limits, DecodeLimits, Limiter,
+};
+pub use crate::deser::reader::{Header, TlvReader, ValueReader};
+pub use crate::deser::utils::deserialize;
+#[cfg(feature = "alloc")]
+pub use crate::deser::utils::{deserialize_bytes, deserialize_bytes_limited};
+pub use crate::deser::Deserialize;
+#[cfg(feature = "alloc")]
+pub use crate::deser::{
+    extract_bolt, extract_bolt_deny_unknown, extract_bolt_with_unknown, extract_der, extract_raw,
+    extract_record, extract_stream, extract_stream_deny_unknown, extract_stream_with_unknown,
+    Extracted, RawField, UnknownRecords,
+};
+
+pub use crate::ser::utils::{
+    serialize, serialize_bytes, serialize_bytes_with, serialize_with_config,
+    serialized_bytes_length,
+};
+#[cfg(feature = "alloc")]
+pub use crate::ser::writer::TlvWriter;
 pub use crate::ser::Serialize;
 
+pub use crate::error::{Error, ErrorKind, Result};
+pub use crate::layout::{ByteOrder, IntEncoding, Layout};
+
+#[cfg(feature = "codec")]
+pub use crate::codec::TlvCodec;
+
+#[cfg(feature = "futures")]
+pub use crate::async_io::futures as async_futures;
+#[cfg(feature = "tokio")]
+pub use crate::async_io::tokio as async_tokio;
+#[cfg(any(feature = "tokio", feature = "futures"))]
+pub use crate::async_io::{
+    deserialize_with_config_async, deserialize_with_limits_async, extract_bolt_async,
+    extract_bolt_deny_unknown_async, extract_bolt_with_unknown_async, extract_der_async,
+    extract_raw_async, extract_record_async, extract_stream_async,
+    extract_stream_deny_unknown_async, extract_stream_with_unknown_async, AsyncDeserialize,
+    AsyncSerialize, AsyncSink, AsyncSource,
+};
+
+/// Version of the wire format described in `docs/wire-format.md`.
+pub const WIRE_FORMAT_VERSION: u32 = 1;
+
+/// Expands to its input when one of the async features is enabled, which
+/// lets the derives emit `AsyncSerialize` and `AsyncDeserialize` impls
+/// without knowing the features of this crate.
+#[doc(hidden)]
+#[cfg(any(feature = "tokio", feature = "futures"))]
+#[macro_export]
+macro_rules! __async {
+    ($($item:tt)*) => { $($item)* };
+}
+
+#[doc(hidden)]
+#[cfg(not(any(feature = "tokio", feature = "futures")))]
+#[macro_export]
+macro_rules! __async {
+    ($($item:tt)*) => {};
+}
+
+#[cfg(any(feature = "tokio", feature = "futures"))]
+mod async_io;
+pub mod ber;
+pub mod bolt;
+#[cfg(feature = "codec")]
+mod codec;
+mod config;
+#[cfg(feature = "alloc")]
+pub mod der;
 mod deser;
+mod error;
 pub mod impls;
+pub mod io;
+mod layout;
 mod ser;
+pub mod varint;
 
 #[cfg(feature = "derive")]
 pub use tlv_derive::*;
@@ -1,11 +1,52 @@
 pub(crate) mod utils;
+#[cfg(feature = "alloc")]
+pub(crate) mod writer;
+
+use crate::Config;
 
 pub trait Serialize: Sized {
-    fn serialize<W>(&self, writer: &mut W) -> std::io::Result<usize>
+    fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
+    where
+        W: crate::io::Write;
+
+    /// Encodes the value as `config` asks, for the options the value does
+    /// not fix itself. The default implementation ignores `config`, which
+    /// suits values without integers in them.
+    ///
+    /// Implementations overriding it have `serialize` call it with
+    /// [`Config::DEFAULT`], and pass `config` on to the values they hold.
+    fn serialize_with<W>(&self, writer: &mut W, _config: &Config) -> crate::Result<usize>
     where
-        W: std::io::Write;
+        W: crate::io::Write,
+    {
+        self.serialize(writer)
+    }
+
+    /// Number of bytes [`serialize`](Serialize::serialize) writes.
+    ///
+    /// The default implementation serializes the value into a sink that only
+    /// counts bytes, so it is always exact but does the work twice.
+    /// Implementations should override it when the length can be computed
+    /// directly, and must saturate instead of overflowing, so that a value
+    /// too large for its length field fails when it is written.
+    fn serialized_length(&self) -> usize {
+        let mut counter = Counter(0);
+        // if serialization fails, the actual write fails the same way
+        let _ = self.serialize(&mut counter);
+        counter.0
+    }
+}
+
+/// Writer which discards the data and only counts its length.
+pub(crate) struct Counter(pub(crate) usize);
+
+impl crate::io::Write for Counter {
+    fn write(&mut self, buf: &[u8]) -> crate::io::Result<usize> {
+        self.0 = self.0.saturating_add(buf.len());
+        Ok(buf.len())
+    }
 
-    fn serialized_length(&self) -> u16 {
-        std::mem::size_of::<Self>() as u16
+    fn flush(&mut self) -> crate::io::Result<()> {
+        Ok(())
     }
 }
@@ -1,19 +1,55 @@
 pub use crate::ser::Serialize;
+use crate::{Config, ErrorKind};
 
-pub fn serialize<T, W>(value: &T, writer: &mut W) -> std::io::Result<usize>
+pub fn serialize<T, W>(value: &T, writer: &mut W) -> crate::Result<usize>
 where
     T: Serialize,
-    W: std::io::Write,
+    W: crate::io::Write,
 {
     value.serialize(writer)
 }
 
-pub fn serialize_bytes<W>(bytes: &[u8], writer: &mut W) -> std::io::Result<usize>
+/// Encodes `value` as `config` asks.
+pub fn serialize_with_config<T, W>(
+    value: &T,
+    writer: &mut W,
+    config: &Config,
+) -> crate::Result<usize>
 where
-    W: std::io::Write,
+    T: Serialize,
+    W: crate::io::Write,
+{
+    value.serialize_with(writer, config)
+}
+
+/// Writes `bytes` prefixed with their length as a `u32`.
+///
+/// This is the encoding of every byte string of the format: `Vec<u8>`,
+/// `&[u8]`, `String` and `&str`.
+pub fn serialize_bytes<W>(bytes: &[u8], writer: &mut W) -> crate::Result<usize>
+where
+    W: crate::io::Write,
 {
-    let len = bytes.len() as u16;
-    len.serialize(writer)?;
+    serialize_bytes_with(bytes, writer, &Config::DEFAULT)
+}
+
+/// Writes `bytes` like [`serialize_bytes`], with the length in the byte
+/// order of `config`.
+pub fn serialize_bytes_with<W>(
+    bytes: &[u8],
+    writer: &mut W,
+    config: &Config,
+) -> crate::Result<usize>
+where
+    W: crate::io::Write,
+{
+    let len = u32::try_from(bytes.len()).map_err(|_| ErrorKind::OutOfRange)?;
+    let written = len.serialize_with(writer, config)?;
     writer.write_all(bytes)?;
-    Ok(bytes.len())
+    Ok(written + bytes.len())
+}
+
+/// Number of bytes [`serialize_bytes`] writes for `bytes`.
+pub fn serialized_bytes_length(bytes: &[u8]) -> usize {
+    core::mem::size_of::<u32>().saturating_add(bytes.len())
 }
@@ -0,0 +1,378 @@
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::io::{Seek, SeekFrom};
+
+use crate::io::Write;
+use crate::ser::Counter;
+use crate::{Error, ErrorKind, Layout, Serialize};
+
+/// Writes the length of a container over its placeholder, `distance` bytes
+/// back from the current position of a seekable writer.
+type Patch<W> = fn(&mut W, u64, &[u8]) -> crate::io::Result<()>;
+
+#[cfg(feature = "std")]
+fn patch_seekable<W>(writer: &mut W, distance: u64, length: &[u8]) -> crate::io::Result<()>
+where
+    W: Write + Seek,
+{
+    writer.seek(SeekFrom::Current(-(distance as i64)))?;
+    writer.write_all(length)?;
+    writer.seek(SeekFrom::Current(distance as i64 - length.len() as i64))?;
+    Ok(())
+}
+
+/// A container opened by [`TlvWriter::begin`].
+struct Open {
+    tag: u64,
+    /// Position of the placeholder for the length.
+    length_at: u64,
+}
+
+/// Writes records, including containers whose length is only known once
+/// their content has been written.
+///
+/// A container is opened with [`begin`](Self::begin), filled with records or
+/// with raw bytes through the [`Write`] implementation, and closed with
+/// [`end`](Self::end), which writes its length over a placeholder:
+///
+/// - a writer created with [`new`](Self::new) keeps the outermost open
+///   container in memory and passes it on once it is closed,
+/// - a writer created with [`seekable`](Self::seekable) writes straight
+///   through and seeks back to the placeholder.
+///
+/// When neither is an option, [`container`](Self::container) measures the
+/// content in a first pass and writes it in a second one, without holding
+/// anything in memory.
+///
+/// ```
+/// use tlv::TlvWriter;
+///
+/// let mut writer = TlvWriter::new(Vec::new());
+/// writer.write_record(1, &7u8).unwrap();
+/// writer.begin(2).unwrap();
+/// writer.write_record(1, &8u8).unwrap();
+/// writer.write_record(2, &9u8).unwrap();
+/// writer.end().unwrap();
+///
+/// assert_eq!(
+///     writer.finish().unwrap(),
+///     [1, 1, 0, 7, 2, 8, 0, 1, 1, 0, 8, 2, 1, 0, 9]
+/// );
+/// ```
+pub struct TlvWriter<W> {
+    writer: W,
+    layout: Layout,
+    open: Vec<Open>,
+    /// Bytes written so far.
+    position: u64,
+    /// Content of the open containers, unless they are patched in place.
+    buffer: Vec<u8>,
+    /// Position of the first byte of `buffer`.
+    buffer_start: u64,
+    patch: Option<Patch<W>>,
+}
+
+impl<W> TlvWriter<W>
+where
+    W: Write,
+{
+    /// Writes records with the default [`Layout`], buffering open containers.
+    pub fn new(writer: W) -> Self {
+        Self::with_layout(writer, Layout::DEFAULT)
+    }
+
+    pub fn with_layout(writer: W, layout: Layout) -> Self {
+        Self {
+            writer,
+            layout,
+            open: Vec::new(),
+            position: 0,
+            buffer: Vec::new(),
+            buffer_start: 0,
+            patch: None,
+        }
+    }
+
+    /// Writes records with `layout`, writing open containers straight
+    /// through and seeking back to fill in their lengths.
+    ///
+    /// The size of a variable-length length is only known with the length,
+    /// so with such a length encoding open containers are buffered anyway.
+    #[cfg(feature = "std")]
+    pub fn seekable(writer: W, layout: Layout) -> Self
+    where
+        W: Seek,
+    {
+        Self {
+            patch: match layout.length.is_variable() {
+                true => None,
+                false => Some(patch_seekable::<W>),
+            },
+            ..Self::with_layout(writer, layout)
+        }
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    /// Number of bytes written so far, including the buffered ones.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Number of containers that are open.
+    pub fn depth(&self) -> usize {
+        self.open.len()
+    }
+
+    /// Writes a record holding `value`.
+    pub fn write_record<T>(&mut self, tag: u64, value: &T) -> crate::Result<usize>
+    where
+        T: Serialize,
+    {
+        let layout = self.layout;
+        let position = self.position;
+        layout
+            .write_record(self, tag, value)
+            .map_err(|err| err.advance(position))
+    }
+
+    /// Writes a record holding `value`, which is already encoded.
+    pub fn write_raw(&mut self, tag: u64, value: &[u8]) -> crate::Result<usize> {
+        let layout = self.layout;
+        layout.write_raw(self, tag, value)
+    }
+
+    /// Opens a container: writes its tag and a placeholder for its length.
+    pub fn begin(&mut self, tag: u64) -> crate::Result<()> {
+        let layout = self.layout;
+        layout.write_tag(self, tag)?;
+
+        if self.open.is_empty() && self.patch.is_none() {
+            self.buffer_start = self.position;
+        }
+        self.open.push(Open {
+            tag,
+            length_at: self.position,
+        });
+
+        let placeholder = vec![0; layout.length.size()];
+        self.write_all(&placeholder)?;
+        Ok(())
+    }
+
+    /// Closes the innermost open container and returns the length of its
+    /// value.
+    pub fn end(&mut self) -> crate::Result<usize> {
+        let open = self.open.pop().ok_or(ErrorKind::Unbalanced)?;
+        let start = open.length_at + self.layout.length.size() as u64;
+        let length = (self.position - start) as usize;
+
+        let mut encoded = Vec::new();
+        self.layout
+            .write_length(&mut encoded, length)
+            .map_err(|err| err.with_tag(open.tag).with_offset(open.length_at))?;
+
+        match self.patch {
+            Some(patch) => patch(&mut self.writer, self.position - open.length_at, &encoded)?,
+            None => {
+                // a variable-length length is shorter than its placeholder
+                let at = (open.length_at - self.buffer_start) as usize;
+                let placeholder = self.layout.length.size();
+                self.buffer
+                    .splice(at..at + placeholder, encoded.iter().copied());
+                self.position -= (placeholder - encoded.len()) as u64;
+
+                if self.open.is_empty() {
+                    self.writer.write_all(&self.buffer)?;
+                    self.buffer.clear();
+                }
+            }
+        }
+
+        Ok(length)
+    }
+
+    /// Writes a container whose content is written by `content`, which is
+    /// called twice: once to measure the content, once to write it.
+    ///
+    /// Nothing is buffered, which makes this the way to nest containers on
+    /// a writer that can neither seek nor hold the content in memory.
+    /// `content` has to write the same bytes both times.
+    pub fn container<F>(&mut self, tag: u64, mut content: F) -> crate::Result<usize>
+    where
+        F: FnMut(&mut TlvWriter<&mut dyn Write>) -> crate::Result<()>,
+    {
+        let mut counter = Counter(0);
+        content(&mut TlvWriter::with_layout(
+            &mut counter as &mut dyn Write,
+            self.layout,
+        ))?;
+        let length = counter.0;
+
+        let layout = self.layout;
+        let mut written = layout.write_tag(self, tag)?;
+        written += layout
+            .write_length(self, length)
+            .map_err(|err| err.with_tag(tag))?;
+
+        let start = self.position;
+        let mut writer = TlvWriter::with_layout(self as &mut dyn Write, layout);
+        content(&mut writer)
+            .and_then(|()| writer.finish().map(drop))
+            .map_err(|err| err.advance(start).or_tag(tag))?;
+
+        if self.position - start != length as u64 {
+            return Err(Error::new(ErrorKind::LengthMismatch)
+                .with_tag(tag)
+                .with_offset(start));
+        }
+
+        Ok(written + length)
+    }
+
+    /// Checks that every container was closed and returns the underlying
+    /// writer.
+    pub fn finish(mut self) -> crate::Result<W> {
+        if !self.open.is_empty() {
+            return Err(Error::new(ErrorKind::Unbalanced));
+        }
+
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W> Write for TlvWriter<W>
+where
+    W: Write,
+{
+    fn write(&mut self, buf: &[u8]) -> crate::io::Result<usize> {
+        let written = if self.open.is_empty() || self.patch.is_some() {
+            self.writer.write(buf)?
+        } else {
+            self.buffer.extend_from_slice(buf);
+            buf.len()
+        };
+
+        self.position += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> crate::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::IntEncoding;
+
+    fn nested<W: Write>(writer: &mut TlvWriter<W>) {
+        writer.begin(1).unwrap();
+        writer.write_record(2, &7u16).unwrap();
+        writer.begin(3).unwrap();
+        writer.write_all(&[1, 2, 3]).unwrap();
+        assert_eq!(writer.end().unwrap(), 3);
+        assert_eq!(writer.end().unwrap(), 11);
+        writer.write_record(4, &true).unwrap();
+    }
+
+    #[rustfmt::skip]
+    const NESTED: [u8; 18] = [
+        1, 11, 0,
+            2, 2, 0, 7, 0,
+            3, 3, 0, 1, 2, 3,
+        4, 1, 0, 1,
+    ];
+
+    #[test]
+    fn test_buffered() {
+        let mut writer = TlvWriter::new(Vec::new());
+        nested(&mut writer);
+        assert_eq!(writer.position(), 18);
+        assert_eq!(writer.finish().unwrap(), NESTED);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_seekable() {
+        let mut writer = TlvWriter::seekable(std::io::Cursor::new(Vec::new()), Layout::DEFAULT);
+        nested(&mut writer);
+        assert_eq!(writer.finish().unwrap().into_inner(), NESTED);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_variable_length() {
+        let layout = Layout::DEFAULT.with_length(IntEncoding::BigSize);
+        let expected = [1, 0xfd, 0x01, 0x05, 2, 0xfd, 0x00, 0xff, 3, 0, 4, 1, 1];
+
+        for mut writer in [
+            TlvWriter::with_layout(std::io::Cursor::new(Vec::new()), layout),
+            TlvWriter::seekable(std::io::Cursor::new(Vec::new()), layout),
+        ] {
+            writer.begin(1).unwrap();
+            writer.begin(2).unwrap();
+            writer.write_all(&[0; 255]).unwrap();
+            assert_eq!(writer.end().unwrap(), 255);
+            writer.begin(3).unwrap();
+            writer.end().unwrap();
+            assert_eq!(writer.end().unwrap(), 261);
+            writer.write_record(4, &true).unwrap();
+            assert_eq!(writer.position(), 4 + 261 + 3);
+
+            let buf = writer.finish().unwrap().into_inner();
+            assert_eq!(buf[..8], expected[..8]);
+            assert_eq!(buf[8 + 255..], expected[8..]);
+        }
+    }
+
+    #[test]
+    fn test_container() {
+        let mut buf = Vec::new();
+        let mut writer = TlvWriter::new(&mut buf);
+
+        let written = writer
+            .container(1, |writer| {
+                writer.write_record(2, &7u16)?;
+                writer.container(3, |writer| {
+                    writer.write_all(&[1, 2, 3])?;
+                    Ok(())
+                })?;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(written, 14);
+        writer.write_record(4, &true).unwrap();
+        writer.finish().unwrap();
+
+        assert_eq!(buf, NESTED);
+    }
+
+    #[test]
+    fn test_unbalanced() {
+        let mut writer = TlvWriter::new(Vec::new());
+        assert_eq!(writer.end().unwrap_err().kind(), ErrorKind::Unbalanced);
+
+        writer.begin(1).unwrap();
+        assert_eq!(writer.depth(), 1);
+        assert_eq!(writer.finish().unwrap_err().kind(), ErrorKind::Unbalanced);
+    }
+
+    #[test]
+    fn test_container_too_long() {
+        let layout = Layout::DEFAULT.with_length(IntEncoding::U8);
+        let mut writer = TlvWriter::with_layout(Vec::new(), layout);
+
+        writer.begin(1).unwrap();
+        writer.write_all(&[0; 256]).unwrap();
+        let err = writer.end().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::OutOfRange);
+        assert_eq!(err.tag(), Some(1));
+        assert_eq!(err.offset(), Some(1));
+    }
+}
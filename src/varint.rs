@@ -0,0 +1,431 @@
+//! Variable-length integers, usable as the tags and lengths of records
+//! through [`IntEncoding`] and as values through the wrappers of this module.
+//!
+//! Every encoding has a single valid form for each value, the shortest one:
+//! a decoder rejects anything else with [`ErrorKind::NonCanonical`], so that
+//! equal values always have equal encodings. The BER tags and lengths of
+//! [`ber`](crate::ber) go through the same functions, except that BER
+//! lengths are also read in a longer form, which only [`der`](crate::der)
+//! rejects.
+
+use crate::{Config, Deserialize, ErrorKind, IntEncoding, Limiter, Serialize};
+
+/// Number of bytes of the longest variable-length integer, a 64-bit LEB128.
+pub(crate) const MAX_SIZE: usize = 10;
+
+/// Limit of the values of a QUIC variable-length integer.
+pub(crate) const QUIC_MAX: u64 = (1 << 62) - 1;
+
+/// Encodes `value` into the front of `buf` and returns the number of bytes
+/// used. `value` has to be in the range of `encoding`.
+pub(crate) fn encode(encoding: IntEncoding, value: u64, buf: &mut [u8; MAX_SIZE]) -> usize {
+    match encoding {
+        IntEncoding::Leb128 => {
+            let mut value = value;
+            let mut len = 0;
+            loop {
+                let byte = (value & 0x7f) as u8;
+                value >>= 7;
+                if value == 0 {
+                    buf[len] = byte;
+                    return len + 1;
+                }
+                buf[len] = byte | 0x80;
+                len += 1;
+            }
+        }
+        IntEncoding::BigSize => {
+            let (prefix, size) = match value {
+                0..=0xfc => {
+                    buf[0] = value as u8;
+                    return 1;
+                }
+                0xfd..=0xffff => (0xfd, 2),
+                0x1_0000..=0xffff_ffff => (0xfe, 4),
+                _ => (0xff, 8),
+            };
+            buf[0] = prefix;
+            buf[1..=size].copy_from_slice(&value.to_be_bytes()[8 - size..]);
+            size + 1
+        }
+        IntEncoding::Quic => {
+            let (prefix, size) = match value {
+                0..=0x3f => (0x00, 1),
+                0x40..=0x3fff => (0x40, 2),
+                0x4000..=0x3fff_ffff => (0x80, 4),
+                _ => (0xc0, 8),
+            };
+            buf[..size].copy_from_slice(&value.to_be_bytes()[8 - size..]);
+            buf[0] |= prefix;
+            size
+        }
+        IntEncoding::BerTag => {
+            let size = encoded_size(encoding, value);
+            buf[..size].copy_from_slice(&value.to_be_bytes()[8 - size..]);
+            size
+        }
+        IntEncoding::BerLength => {
+            if value < 0x80 {
+                buf[0] = value as u8;
+                return 1;
+            }
+            let size = encoded_size(encoding, value) - 1;
+            buf[0] = 0x80 | size as u8;
+            buf[1..=size].copy_from_slice(&value.to_be_bytes()[8 - size..]);
+            size + 1
+        }
+        IntEncoding::U8 | IntEncoding::U16 | IntEncoding::U32 | IntEncoding::U64 => {
+            unreachable!("fixed-size encodings are not variable-length")
+        }
+    }
+}
+
+/// Number of bytes `encode` uses for `value`.
+pub(crate) fn encoded_size(encoding: IntEncoding, value: u64) -> usize {
+    match encoding {
+        IntEncoding::Leb128 => (64 - (value | 1).leading_zeros() as usize).div_ceil(7),
+        IntEncoding::BigSize => match value {
+            0..=0xfc => 1,
+            0xfd..=0xffff => 3,
+            0x1_0000..=0xffff_ffff => 5,
+            _ => 9,
+        },
+        IntEncoding::Quic => match value {
+            0..=0x3f => 1,
+            0x40..=0x3fff => 2,
+            0x4000..=0x3fff_ffff => 4,
+            _ => 8,
+        },
+        IntEncoding::BerTag => 8 - (value | 1).leading_zeros() as usize / 8,
+        IntEncoding::BerLength => match value {
+            0..=0x7f => 1,
+            _ => 1 + 8 - value.leading_zeros() as usize / 8,
+        },
+        IntEncoding::U8 | IntEncoding::U16 | IntEncoding::U32 | IntEncoding::U64 => encoding.size(),
+    }
+}
+
+/// Number of bytes still to read to complete an integer of which `read`
+/// was read so far, `0` once it is complete.
+pub(crate) fn remaining(encoding: IntEncoding, read: &[u8]) -> usize {
+    let Some(&first) = read.first() else {
+        return 1;
+    };
+
+    match encoding {
+        IntEncoding::Leb128 => match read[read.len() - 1] & 0x80 {
+            0x80 if read.len() < MAX_SIZE => 1,
+            _ => 0,
+        },
+        IntEncoding::BigSize => {
+            let size = match first {
+                0xfd => 3,
+                0xfe => 5,
+                0xff => 9,
+                _ => 1,
+            };
+            size - read.len()
+        }
+        IntEncoding::Quic => (1 << (first >> 6)) - read.len(),
+        // the low five bits all set announce subsequent octets, each but the
+        // last with its high bit set
+        IntEncoding::BerTag => match read[read.len() - 1] {
+            _ if first & 0x1f != 0x1f => 0,
+            _ if read.len() == 1 => 1,
+            last if last & 0x80 != 0 && read.len() < 8 => 1,
+            _ => 0,
+        },
+        IntEncoding::BerLength => match first {
+            0x81..=0x88 => 1 + (first & 0x7f) as usize - read.len(),
+            _ => 0,
+        },
+        IntEncoding::U8 | IntEncoding::U16 | IntEncoding::U32 | IntEncoding::U64 => {
+            encoding.size() - read.len()
+        }
+    }
+}
+
+/// Decodes a complete integer, as delimited by [`remaining`].
+pub(crate) fn decode(encoding: IntEncoding, bytes: &[u8]) -> crate::Result<u64> {
+    let value = match encoding {
+        IntEncoding::Leb128 => {
+            let last = bytes[bytes.len() - 1];
+            // the tenth byte only has room for the last bit of a `u64`
+            if last & 0x80 != 0 || (bytes.len() == MAX_SIZE && last > 1) {
+                return Err(ErrorKind::OutOfRange.into());
+            }
+
+            bytes.iter().enumerate().fold(0, |value, (i, byte)| {
+                value | u64::from(byte & 0x7f) << (7 * i)
+            })
+        }
+        IntEncoding::BigSize => match bytes.len() {
+            1 => u64::from(bytes[0]),
+            len => {
+                let mut buf = [0; 8];
+                buf[8 - (len - 1)..].copy_from_slice(&bytes[1..]);
+                u64::from_be_bytes(buf)
+            }
+        },
+        IntEncoding::Quic => {
+            let mut buf = [0; 8];
+            buf[8 - bytes.len()..].copy_from_slice(bytes);
+            buf[8 - bytes.len()] &= 0x3f;
+            u64::from_be_bytes(buf)
+        }
+        IntEncoding::BerTag => {
+            if bytes.len() > 1 && bytes[bytes.len() - 1] & 0x80 != 0 {
+                return Err(ErrorKind::OutOfRange.into());
+            }
+
+            let value = bytes
+                .iter()
+                .fold(0, |value, byte| value << 8 | u64::from(*byte));
+            // a tag number padded with a leading zero group
+            if !crate::ber::is_tag(value) {
+                return Err(ErrorKind::NonCanonical.into());
+            }
+            value
+        }
+        IntEncoding::BerLength => match bytes[0] {
+            0x80 => return Err(ErrorKind::Unsupported.into()),
+            0x00..=0x7f => u64::from(bytes[0]),
+            0x81..=0x88 => bytes[1..]
+                .iter()
+                .fold(0, |value, byte| value << 8 | u64::from(*byte)),
+            _ => return Err(ErrorKind::OutOfRange.into()),
+        },
+        IntEncoding::U8 | IntEncoding::U16 | IntEncoding::U32 | IntEncoding::U64 => {
+            unreachable!("fixed-size encodings are not variable-length")
+        }
+    };
+
+    // BER lengths may take more octets than needed, DER checks that they
+    // do not on its own
+    match encoding == IntEncoding::BerLength || encoded_size(encoding, value) == bytes.len() {
+        true => Ok(value),
+        false => Err(ErrorKind::NonCanonical.into()),
+    }
+}
+
+/// Maps a signed integer to an unsigned one so that values close to zero
+/// stay small: `0, -1, 1, -2, …` become `0, 1, 2, 3, …`.
+pub const fn zigzag_encode(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+/// Inverse of [`zigzag_encode`].
+pub const fn zigzag_decode(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+/// An unsigned LEB128 integer: seven bits per byte, least significant
+/// first, the high bit of each byte set when another one follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Leb128(pub u64);
+
+/// A signed integer, zig-zag mapped with [`zigzag_encode`] and written as a
+/// [`Leb128`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct ZigZag(pub i64);
+
+/// A Lightning BigSize integer: values below `0xfd` are a single byte,
+/// larger ones are `0xfd`, `0xfe` or `0xff` followed by a big-endian `u16`,
+/// `u32` or `u64`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct BigSize(pub u64);
+
+/// A QUIC variable-length integer (RFC 9000): a big-endian integer of 1, 2,
+/// 4 or 8 bytes whose two high bits give the size. Values are limited to 62
+/// bits, larger ones are [`ErrorKind::OutOfRange`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct QuicVarInt(pub u64);
+
+/// The wrappers are written in their own encoding, whatever the byte order.
+macro_rules! impl_varint {
+    ($($ty:ident: $encoding:ident, $to_wire:expr, $from_wire:expr;)*) => {
+        $(
+            impl $ty {
+                pub(crate) const ENCODING: IntEncoding = IntEncoding::$encoding;
+            }
+
+            impl Serialize for $ty {
+                fn serialize<W>(&self, writer: &mut W) -> crate::Result<usize>
+                where
+                    W: crate::io::Write,
+                {
+                    self.serialize_with(writer, &Config::DEFAULT)
+                }
+
+                fn serialize_with<W>(&self, writer: &mut W, config: &Config) -> crate::Result<usize>
+                where
+                    W: crate::io::Write,
+                {
+                    Self::ENCODING.write(writer, $to_wire(self.0), config.byte_order)
+                }
+
+                fn serialized_length(&self) -> usize {
+                    Self::ENCODING.encoded_size($to_wire(self.0))
+                }
+            }
+
+            impl Deserialize for $ty {
+                fn deserialize<R>(reader: &mut R) -> crate::Result<Self>
+                where
+                    R: crate::io::Read,
+                {
+                    Self::deserialize_limited(reader, &mut Limiter::default())
+                }
+
+                fn deserialize_limited<R>(reader: &mut R, limiter: &mut Limiter) -> crate::Result<Self>
+                where
+                    R: crate::io::Read,
+                {
+                    Self::ENCODING
+                        .read(reader, limiter.byte_order())
+                        .map(|value| Self($from_wire(value)))
+                }
+            }
+        )*
+    };
+}
+
+impl_varint! {
+    Leb128: Leb128, core::convert::identity, core::convert::identity;
+    ZigZag: Leb128, zigzag_encode, zigzag_decode;
+    BigSize: BigSize, core::convert::identity, core::convert::identity;
+    QuicVarInt: Quic, core::convert::identity, core::convert::identity;
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use alloc::vec::Vec;
+
+    use super::*;
+
+    fn encoded<T: Serialize>(value: T) -> Vec<u8> {
+        let mut buf = Vec::new();
+        let written = value.serialize(&mut buf).unwrap();
+        assert_eq!(written, buf.len());
+        assert_eq!(value.serialized_length(), buf.len());
+        buf
+    }
+
+    #[test]
+    fn test_leb128() {
+        for (value, expected) in [
+            (0, &[0x00][..]),
+            (127, &[0x7f]),
+            (128, &[0x80, 0x01]),
+            (624_485, &[0xe5, 0x8e, 0x26]),
+            (
+                u64::MAX,
+                &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01],
+            ),
+        ] {
+            assert_eq!(encoded(Leb128(value)), expected, "{value}");
+            assert_eq!(Leb128::deserialize(&mut &expected[..]).unwrap().0, value);
+        }
+
+        // padded with a trailing zero group
+        let err = Leb128::deserialize(&mut [0x80, 0x00].as_slice()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NonCanonical);
+        // past 64 bits
+        let mut past = [0xff; MAX_SIZE];
+        past[MAX_SIZE - 1] = 0x02;
+        let err = Leb128::deserialize(&mut past.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::OutOfRange);
+        let err = Leb128::deserialize(&mut [0x80; 11].as_slice()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::OutOfRange);
+        let err = Leb128::deserialize(&mut [0x80].as_slice()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Truncated);
+    }
+
+    #[test]
+    fn test_zigzag() {
+        for (value, expected) in [(0, 0), (-1, 1), (1, 2), (-2, 3), (i64::MAX, u64::MAX - 1)] {
+            assert_eq!(zigzag_encode(value), expected);
+            assert_eq!(zigzag_decode(expected), value);
+        }
+        assert_eq!(zigzag_decode(u64::MAX), i64::MIN);
+
+        assert_eq!(encoded(ZigZag(-65)), [0x81, 0x01]);
+        assert_eq!(
+            ZigZag::deserialize(&mut [0x81, 0x01].as_slice()).unwrap(),
+            ZigZag(-65)
+        );
+    }
+
+    #[test]
+    fn test_bigsize() {
+        // the test vectors of BOLT #1
+        for (value, expected) in [
+            (0, &[0x00][..]),
+            (252, &[0xfc]),
+            (253, &[0xfd, 0x00, 0xfd]),
+            (65535, &[0xfd, 0xff, 0xff]),
+            (65536, &[0xfe, 0x00, 0x01, 0x00, 0x00]),
+            (4_294_967_295, &[0xfe, 0xff, 0xff, 0xff, 0xff]),
+            (
+                4_294_967_296,
+                &[0xff, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x00],
+            ),
+            (
+                u64::MAX,
+                &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+            ),
+        ] {
+            assert_eq!(encoded(BigSize(value)), expected, "{value}");
+            assert_eq!(BigSize::deserialize(&mut &expected[..]).unwrap().0, value);
+        }
+
+        for non_canonical in [
+            &[0xfd, 0x00, 0xfc][..],
+            &[0xfe, 0x00, 0x00, 0xff, 0xff],
+            &[0xff, 0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff],
+        ] {
+            let err = BigSize::deserialize(&mut &non_canonical[..]).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::NonCanonical);
+        }
+
+        for truncated in [
+            &[0xfd, 0x00][..],
+            &[0xfe, 0xff, 0xff],
+            &[0xff, 0xff, 0xff, 0xff, 0xff],
+            &[],
+        ] {
+            let err = BigSize::deserialize(&mut &truncated[..]).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::Truncated);
+        }
+    }
+
+    #[test]
+    fn test_quic() {
+        // the examples of RFC 9000, appendix A.1
+        for (value, expected) in [
+            (
+                151_288_809_941_952_652,
+                &[0xc2, 0x19, 0x7c, 0x5e, 0xff, 0x14, 0xe8, 0x8c][..],
+            ),
+            (494_878_333, &[0x9d, 0x7f, 0x3e, 0x7d]),
+            (15_293, &[0x7b, 0xbd]),
+            (37, &[0x25]),
+        ] {
+            assert_eq!(encoded(QuicVarInt(value)), expected, "{value}");
+            assert_eq!(
+                QuicVarInt::deserialize(&mut &expected[..]).unwrap().0,
+                value
+            );
+        }
+
+        // valid for RFC 9000, but not the shortest form
+        let err = QuicVarInt::deserialize(&mut [0x40, 0x25].as_slice()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::NonCanonical);
+
+        let err = QuicVarInt(QUIC_MAX + 1)
+            .serialize(&mut Vec::new())
+            .unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::OutOfRange);
+        assert_eq!(encoded(QuicVarInt(QUIC_MAX)), [0xff; 8]);
+    }
+}
@@ -0,0 +1,280 @@
+//! Derived `AsyncSerialize` and `AsyncDeserialize` impls over the readers and
+//! writers of `tokio` and `futures`.
+
+#![cfg(all(feature = "derive", feature = "tokio", feature = "futures"))]
+
+use tlv::{async_futures, async_tokio, Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Message {
+    #[tlv(tag = 1)]
+    id: u32,
+    #[tlv(tag = 2)]
+    payload: Vec<u8>,
+    #[tlv(tag = 3)]
+    reply_to: Option<u32>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(mode = "stream")]
+struct Hello {
+    #[tlv(tag = 1)]
+    name: String,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(mode = "stream")]
+struct Relay {
+    #[tlv(tag = 1)]
+    name: String,
+    #[tlv(unknown)]
+    unknown: Vec<tlv::RawField>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Frame {
+    #[tlv(tag = 1)]
+    Message(Message),
+    #[tlv(tag = 2)]
+    Ping {
+        #[tlv(tag = 1)]
+        nonce: u64,
+    },
+    #[tlv(tag = 3)]
+    Close,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Session(u64);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(tag_encoding = "quic", length_encoding = "bigsize")]
+struct Compact {
+    #[tlv(tag = 1000)]
+    id: tlv::varint::BigSize,
+    #[tlv(tag = 2)]
+    payload: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(mode = "der")]
+struct Attribute {
+    #[tlv(explicit = 0)]
+    id: tlv::der::Integer,
+    value: Option<tlv::der::Utf8String>,
+}
+
+fn message() -> Message {
+    Message {
+        id: 7,
+        payload: vec![1, 2, 3],
+        reply_to: Some(6),
+    }
+}
+
+fn encode<T: Serialize>(value: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    value.serialize(&mut buf).unwrap();
+    buf
+}
+
+#[tokio::test]
+async fn test_tokio() {
+    let frames = [
+        Frame::Message(message()),
+        Frame::Ping { nonce: 9 },
+        Frame::Close,
+    ];
+
+    // a small buffer makes the values cross several reads and writes
+    let (mut client, mut server) = tokio::io::duplex(4);
+    let writer = tokio::spawn(async move {
+        for frame in &frames {
+            let written = async_tokio::serialize(frame, &mut client).await.unwrap();
+            assert_eq!(written, encode(frame).len());
+        }
+        frames
+    });
+
+    let mut received = Vec::new();
+    for _ in 0..3 {
+        received.push(
+            async_tokio::deserialize::<Frame, _>(&mut server)
+                .await
+                .unwrap(),
+        );
+    }
+
+    assert_eq!(received, writer.await.unwrap());
+}
+
+#[tokio::test]
+async fn test_tokio_varints() {
+    let compact = Compact {
+        id: tlv::varint::BigSize(70_000),
+        payload: vec![7; 300],
+    };
+    let buf = encode(&compact);
+
+    let (mut client, mut server) = tokio::io::duplex(3);
+    let writer = tokio::spawn(async move {
+        async_tokio::serialize(&compact, &mut client).await.unwrap();
+        compact
+    });
+
+    let decoded: Compact = async_tokio::deserialize(&mut server).await.unwrap();
+    assert_eq!(decoded, writer.await.unwrap());
+    assert_eq!(encode(&decoded), buf);
+}
+
+#[tokio::test]
+async fn test_tokio_stream_mode() {
+    let mut buf = encode(&Hello {
+        name: "tlv".to_string(),
+    });
+    // unknown records are skipped until the end of the input
+    buf.extend([9, 2, 0, 0xaa, 0xbb]);
+
+    let hello: Hello = async_tokio::deserialize(&mut buf.as_slice()).await.unwrap();
+    assert_eq!(hello.name, "tlv");
+
+    let err = async_tokio::deserialize::<Message, _>(&mut buf.as_slice())
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), tlv::ErrorKind::UnexpectedTag);
+}
+
+#[tokio::test]
+async fn test_tokio_unknown_records() {
+    let mut buf = encode(&Hello {
+        name: "tlv".to_string(),
+    });
+    buf.extend([9, 2, 0, 0xaa, 0xbb]);
+
+    let relay: Relay = async_tokio::deserialize(&mut buf.as_slice()).await.unwrap();
+    assert_eq!(relay.name, "tlv");
+    assert_eq!(relay.unknown.len(), 1);
+    assert_eq!(relay.unknown[0].value, [0xaa, 0xbb]);
+
+    let mut written = Vec::new();
+    async_tokio::serialize(&relay, &mut written).await.unwrap();
+    assert_eq!(written, buf);
+}
+
+#[tokio::test]
+async fn test_tokio_der_mode() {
+    let attribute = Attribute {
+        id: tlv::der::Integer(-2),
+        value: Some(tlv::der::Utf8String("cn".to_string())),
+    };
+    let buf = encode(&attribute);
+    assert_eq!(buf, [0xa0, 0x03, 0x02, 0x01, 0xfe, 0x0c, 0x02, b'c', b'n']);
+
+    let decoded: Attribute = async_tokio::deserialize(&mut buf.as_slice()).await.unwrap();
+    assert_eq!(decoded, attribute);
+
+    // the records come in the order of the fields
+    let swapped = [&buf[5..], &buf[..5]].concat();
+    let err = async_tokio::deserialize::<Attribute, _>(&mut swapped.as_slice())
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), tlv::ErrorKind::MissingTag);
+}
+
+#[test]
+fn test_futures() {
+    futures::executor::block_on(async {
+        let mut cursor = futures::io::Cursor::new(Vec::new());
+        async_futures::serialize(&message(), &mut cursor)
+            .await
+            .unwrap();
+        async_futures::serialize(&Session(5), &mut cursor)
+            .await
+            .unwrap();
+        assert_eq!(
+            cursor.get_ref()[..],
+            [encode(&message()), encode(&Session(5))].concat()
+        );
+
+        cursor.set_position(0);
+        let decoded: Message = async_futures::deserialize(&mut cursor).await.unwrap();
+        assert_eq!(decoded, message());
+        let session: Session = async_futures::deserialize(&mut cursor).await.unwrap();
+        assert_eq!(session, Session(5));
+    });
+}
+
+#[tokio::test]
+async fn test_tokio_limits() {
+    let limits = tlv::DecodeLimits::DEFAULT;
+
+    // a record announcing more than allowed fails before it is read
+    let oversized = [2, 0xff, 0xff, 0];
+    let err = async_tokio::deserialize_with_limits::<Message, _>(
+        &mut oversized.as_slice(),
+        limits.with_max_string_length(16),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.kind(), tlv::ErrorKind::LimitExceeded);
+    assert_eq!(err.tag(), Some(2));
+    assert_eq!(err.offset(), Some(3));
+
+    // unknown records are only kept up to the number of elements allowed
+    let mut buf = encode(&Hello {
+        name: "tlv".to_string(),
+    });
+    for _ in 0..4 {
+        buf.extend([9, 1, 0, 0xaa]);
+    }
+    let relay: Relay =
+        async_tokio::deserialize_with_limits(&mut buf.as_slice(), limits.with_max_elements(4))
+            .await
+            .unwrap();
+    assert_eq!(relay.unknown.len(), 4);
+
+    buf.extend([9, 1, 0, 0xaa]);
+    let err = async_tokio::deserialize_with_limits::<Relay, _>(
+        &mut buf.as_slice(),
+        limits.with_max_elements(4),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.kind(), tlv::ErrorKind::LimitExceeded);
+    assert_eq!(err.tag(), Some(9));
+    assert_eq!(err.offset(), Some(buf.len() as u64 - 4));
+
+    // and the input as a whole is bounded, skipped records included
+    let err = async_tokio::deserialize_with_limits::<Hello, _>(
+        &mut buf.as_slice(),
+        limits.with_max_total_bytes(16),
+    )
+    .await
+    .unwrap_err();
+    assert_eq!(err.kind(), tlv::ErrorKind::LimitExceeded);
+    assert_eq!(err.offset(), Some(16));
+}
+
+#[test]
+fn test_futures_limits() {
+    futures::executor::block_on(async {
+        let buf = encode(&message());
+        let mut cursor = futures::io::Cursor::new(buf.clone());
+        let err = async_futures::deserialize_with_limits::<Message, _>(
+            &mut cursor,
+            tlv::DecodeLimits::DEFAULT.with_max_total_bytes(buf.len() as u64 - 1),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(err.kind(), tlv::ErrorKind::LimitExceeded);
+
+        cursor.set_position(0);
+        let decoded: Message = async_futures::deserialize_with_limits(
+            &mut cursor,
+            tlv::DecodeLimits::DEFAULT.with_max_total_bytes(buf.len() as u64),
+        )
+        .await
+        .unwrap();
+        assert_eq!(decoded, message());
+    });
+}
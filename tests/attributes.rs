@@ -0,0 +1,172 @@
+//! Domain types mapped onto records with the `default`, `skip`, `with` and
+//! `required` field attributes.
+
+#![cfg(all(feature = "alloc", feature = "derive"))]
+
+use std::time::Duration;
+
+use tlv::{BorrowDeserialize, Deserialize, ErrorKind, Serialize};
+
+fn hex(s: &str) -> Vec<u8> {
+    let s: String = s.split_whitespace().collect();
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+fn encoded<T: Serialize>(value: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    assert_eq!(value.serialize(&mut buf).unwrap(), buf.len());
+    assert_eq!(value.serialized_length(), buf.len());
+    buf
+}
+
+/// A `Duration` as a number of milliseconds.
+mod millis {
+    use std::time::Duration;
+
+    use tlv::{Config, Deserialize, Limiter, Serialize};
+
+    pub fn serialize<W>(value: &Duration, writer: &mut W, config: &Config) -> tlv::Result<usize>
+    where
+        W: tlv::io::Write,
+    {
+        (value.as_millis() as u32).serialize_with(writer, config)
+    }
+
+    pub fn serialized_length(_value: &Duration) -> usize {
+        4
+    }
+
+    pub fn deserialize<R>(reader: &mut R, limiter: &mut Limiter) -> tlv::Result<Duration>
+    where
+        R: tlv::io::Read,
+    {
+        u32::deserialize_limited(reader, limiter).map(|millis| Duration::from_millis(millis.into()))
+    }
+}
+
+fn three() -> u8 {
+    3
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, BorrowDeserialize)]
+#[tlv(mode = "stream")]
+struct Retry {
+    #[tlv(tag = 1, with = "millis")]
+    delay: Duration,
+    #[tlv(tag = 2, default = "three")]
+    attempts: u8,
+    #[tlv(tag = 3, default)]
+    jitter: u16,
+    #[tlv(tag = 4, required)]
+    reason: Option<u8>,
+    #[tlv(skip)]
+    attempted: u8,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Cached {
+    #[tlv(tag = 1)]
+    key: u8,
+    #[tlv(skip, default = "three")]
+    hits: u8,
+    #[tlv(tag = 2, with = "millis")]
+    ttl: Duration,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(mode = "bolt")]
+struct Hop {
+    #[tlv(tag = 2, required)]
+    amount: Option<u64>,
+    #[tlv(tag = 4, with = "millis")]
+    timeout: Option<Duration>,
+}
+
+#[test]
+fn test_stream_attributes() {
+    let retry = Retry {
+        delay: Duration::from_millis(500),
+        attempts: 2,
+        jitter: 7,
+        reason: None,
+        attempted: 1,
+    };
+    // the skipped field has no record
+    let input = hex("01 0400 f4010000 02 0100 02 03 0200 0700 04 0100 00");
+    assert_eq!(encoded(&retry), input);
+
+    let decoded = Retry::deserialize(&mut input.as_slice()).unwrap();
+    assert_eq!(decoded.delay, retry.delay);
+    assert_eq!(decoded.attempted, 0);
+    assert_eq!(
+        Retry::borrow_deserialize(&mut input.as_slice()).unwrap(),
+        decoded
+    );
+
+    // missing records take their defaults
+    let input = hex("01 0400 f4010000 04 0200 0109");
+    let decoded = Retry::deserialize(&mut input.as_slice()).unwrap();
+    assert_eq!(decoded.attempts, 3);
+    assert_eq!(decoded.jitter, 0);
+    assert_eq!(decoded.reason, Some(9));
+
+    // an optional field can still be required
+    let err = Retry::deserialize(&mut hex("01 0400 f4010000").as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::MissingTag);
+    assert_eq!(err.tag(), Some(4));
+    assert_eq!(err.field(), Some("reason"));
+
+    // errors of the module are reported in the record
+    let err = Retry::deserialize(&mut hex("04 0100 00 01 0200 f401").as_slice()).unwrap_err();
+    assert_eq!(err.tag(), Some(1));
+    assert_eq!(err.field(), Some("delay"));
+}
+
+#[test]
+fn test_exact_attributes() {
+    let cached = Cached {
+        key: 9,
+        hits: 12,
+        ttl: Duration::from_secs(1),
+    };
+    let input = hex("01 0100 09 02 0400 e8030000");
+    assert_eq!(encoded(&cached), input);
+
+    let decoded = Cached::deserialize(&mut input.as_slice()).unwrap();
+    assert_eq!(decoded.hits, 3);
+    assert_eq!(decoded.ttl, cached.ttl);
+}
+
+#[test]
+fn test_profile_attributes() {
+    let hop = Hop {
+        amount: Some(1000),
+        timeout: Some(Duration::from_millis(40)),
+    };
+    let input = hex("02 08 00000000000003e8 04 04 00000028");
+    assert_eq!(encoded(&hop), input);
+    assert_eq!(Hop::deserialize(&mut input.as_slice()).unwrap(), hop);
+
+    let hop = Hop {
+        amount: Some(1),
+        timeout: None,
+    };
+    let input = hex("02 08 0000000000000001");
+    assert_eq!(encoded(&hop), input);
+    assert_eq!(Hop::deserialize(&mut input.as_slice()).unwrap(), hop);
+
+    let err = Hop::deserialize(&mut hex("04 04 00000028").as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::MissingTag);
+
+    // a required `None` has no record to write
+    let hop = Hop {
+        amount: None,
+        timeout: None,
+    };
+    let err = hop.serialize(&mut Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::MissingTag);
+    assert_eq!(err.field(), Some("amount"));
+}
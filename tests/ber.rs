@@ -0,0 +1,139 @@
+//! EMV templates mapped to derived types with the BER-TLV profile.
+
+#![cfg(all(feature = "alloc", feature = "derive"))]
+
+use tlv::{Deserialize, ErrorKind, Serialize};
+
+fn hex(s: &str) -> Vec<u8> {
+    let s: String = s.split_whitespace().collect();
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+/// The response to the selection of the payment system environment.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(mode = "ber")]
+struct Select {
+    #[tlv(tag = 0x6f)]
+    fci: Fci,
+}
+
+/// File Control Information template.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(mode = "ber")]
+struct Fci {
+    #[tlv(tag = 0x84)]
+    df_name: Vec<u8>,
+    #[tlv(tag = 0xa5)]
+    proprietary: Proprietary,
+}
+
+/// FCI proprietary template.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(mode = "ber")]
+struct Proprietary {
+    #[tlv(tag = 0x88)]
+    sfi: Option<u8>,
+    #[tlv(tag = 0x5f2d)]
+    language_preference: Option<Vec<u8>>,
+}
+
+#[test]
+fn test_select_response() {
+    let input = hex("6f 1a
+            84 0e 315041592e5359532e4444463031
+            a5 08
+                88 01 02
+                5f2d 02 656e");
+
+    let expected = Select {
+        fci: Fci {
+            df_name: b"1PAY.SYS.DDF01".to_vec(),
+            proprietary: Proprietary {
+                sfi: Some(2),
+                language_preference: Some(b"en".to_vec()),
+            },
+        },
+    };
+
+    assert_eq!(
+        Select::deserialize(&mut input.as_slice()).unwrap(),
+        expected
+    );
+
+    let mut buf = Vec::new();
+    assert_eq!(expected.serialize(&mut buf).unwrap(), input.len());
+    assert_eq!(buf, input);
+    assert_eq!(expected.serialized_length(), input.len());
+}
+
+#[test]
+fn test_any_order_and_unknown_tags() {
+    // the language first, then a PDOL this template does not know
+    let input = hex("5f2d 02 656e 9f38 03 9f1a02 88 01 01");
+    let decoded = Proprietary::deserialize(&mut input.as_slice()).unwrap();
+    assert_eq!(
+        decoded,
+        Proprietary {
+            sfi: Some(1),
+            language_preference: Some(b"en".to_vec()),
+        }
+    );
+
+    // absent fields are left out
+    let empty = Proprietary {
+        sfi: None,
+        language_preference: None,
+    };
+    let mut buf = Vec::new();
+    empty.serialize(&mut buf).unwrap();
+    assert!(buf.is_empty());
+    assert_eq!(
+        Proprietary::deserialize(&mut buf.as_slice()).unwrap(),
+        empty
+    );
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(mode = "ber")]
+struct Record {
+    #[tlv(tag = 0x9f02)]
+    amount_authorised: [u8; 6],
+    #[tlv(tag = 0x9f4b)]
+    signed_dynamic_data: Vec<u8>,
+}
+
+#[test]
+fn test_long_form_lengths() {
+    let record = Record {
+        amount_authorised: [0, 0, 0, 0, 0x10, 0],
+        signed_dynamic_data: vec![0x6a; 0x90],
+    };
+
+    let mut buf = Vec::new();
+    record.serialize(&mut buf).unwrap();
+    assert_eq!(buf[..13], hex("9f02 06 000000001000 9f4b 81 90")[..]);
+    assert_eq!(buf.len(), 13 + 0x90);
+    assert_eq!(record.serialized_length(), buf.len());
+    assert_eq!(Record::deserialize(&mut buf.as_slice()).unwrap(), record);
+
+    // lengths are definite, but need not be in their shortest form
+    let err = Record::deserialize(&mut hex("9f02 80 000000001000 0000").as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Unsupported);
+    let padded = hex("9f02 8106 000000001000 9f4b 820001 6a");
+    let decoded = Record::deserialize(&mut padded.as_slice()).unwrap();
+    assert_eq!(decoded.amount_authorised, [0, 0, 0, 0, 0x10, 0]);
+    assert_eq!(decoded.signed_dynamic_data, [0x6a]);
+
+    // which DER requires
+    let input = hex("04 8106 000000001000");
+    let err = tlv::der::deserialize::<tlv::der::OctetString, _>(&mut input.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NonCanonical);
+    assert_eq!(err.tag(), Some(0x04));
+    assert_eq!(err.offset(), Some(0));
+    let input = hex("04 06 000000001000");
+    let decoded = tlv::der::deserialize::<tlv::der::OctetString, _>(&mut input.as_slice()).unwrap();
+    assert_eq!(decoded.0, [0, 0, 0, 0, 0x10, 0]);
+}
@@ -0,0 +1,357 @@
+//! The TLV test vectors of the appendix of BOLT #1, decoded with the two
+//! namespaces the appendix defines.
+
+#![cfg(all(feature = "alloc", feature = "derive"))]
+
+use tlv::bolt::{Tu32, Tu64};
+use tlv::{BorrowDeserialize, Deserialize, ErrorKind, Serialize};
+
+fn hex(s: &str) -> Vec<u8> {
+    let s: String = s.split_whitespace().collect();
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+const NODE_ID: &str = "023da092f6980e58d2c037173180e9a465476026ee50f96695963e8efe436f54eb";
+
+/// The `tlv3` record of `n1`: a node id and two amounts.
+#[derive(Debug, Clone, PartialEq)]
+struct Tlv3 {
+    node_id: [u8; 33],
+    amount_msat_1: u64,
+    amount_msat_2: u64,
+}
+
+impl Serialize for Tlv3 {
+    fn serialize<W>(&self, writer: &mut W) -> tlv::Result<usize>
+    where
+        W: tlv::io::Write,
+    {
+        writer.write_all(&self.node_id)?;
+        writer.write_all(&self.amount_msat_1.to_be_bytes())?;
+        writer.write_all(&self.amount_msat_2.to_be_bytes())?;
+        Ok(self.serialized_length())
+    }
+
+    fn serialized_length(&self) -> usize {
+        33 + 8 + 8
+    }
+}
+
+impl Deserialize for Tlv3 {
+    fn deserialize<R>(reader: &mut R) -> tlv::Result<Self>
+    where
+        R: tlv::io::Read,
+    {
+        let mut node_id = [0; 33];
+        let mut amount_msat_1 = [0; 8];
+        let mut amount_msat_2 = [0; 8];
+        reader.read_exact(&mut node_id)?;
+        reader.read_exact(&mut amount_msat_1)?;
+        reader.read_exact(&mut amount_msat_2)?;
+
+        Ok(Self {
+            node_id,
+            amount_msat_1: u64::from_be_bytes(amount_msat_1),
+            amount_msat_2: u64::from_be_bytes(amount_msat_2),
+        })
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[tlv(mode = "bolt")]
+struct N1 {
+    #[tlv(tag = 1)]
+    amount_msat: Option<Tu64>,
+    #[tlv(tag = 2)]
+    scid: Option<u64>,
+    #[tlv(tag = 3)]
+    tlv3: Option<Tlv3>,
+    #[tlv(tag = 254)]
+    cltv_delta: Option<u16>,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+#[tlv(mode = "bolt")]
+struct N2 {
+    #[tlv(tag = 0)]
+    amount_msat: Option<Tu64>,
+    #[tlv(tag = 11)]
+    cltv_expiry: Option<Tu32>,
+}
+
+#[test]
+fn test_decoding_failures() {
+    let invalid = [
+        // type truncated
+        "fd",
+        "fd 01",
+        // not minimally encoded type
+        "fd 0001 00",
+        // missing length
+        "fd 0101",
+        // length truncated
+        "0f fd",
+        "0f fd 26",
+        // missing value
+        "0f fd 2602",
+        // not minimally encoded length
+        "0f fd 0001 00",
+        // value truncated
+        "0f fd 0201 0000000000000000000000000000000000",
+        // unknown even types
+        "12 00",
+        "fd 0102 00",
+        "fe 01000002 00",
+        "ff 0100000000000002 00",
+    ];
+
+    for input in invalid {
+        N1::deserialize(&mut hex(input).as_slice()).expect_err(input);
+        N2::deserialize(&mut hex(input).as_slice()).expect_err(input);
+    }
+
+    let err = N1::deserialize(&mut hex("fd 0001 00").as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NonCanonical);
+    let err = N1::deserialize(&mut hex("12 00").as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedTag);
+    assert_eq!(err.tag(), Some(0x12));
+}
+
+#[test]
+fn test_n1_decoding_failures() {
+    let invalid = [
+        // greater than encoding length for tu64
+        "01 09 ffffffffffffffffff".to_string(),
+        // encoding for tu64 is not minimal
+        "01 01 00".to_string(),
+        "01 02 0001".to_string(),
+        "01 03 000100".to_string(),
+        "01 04 00010000".to_string(),
+        "01 05 0001000000".to_string(),
+        "01 06 000100000000".to_string(),
+        "01 07 00010000000000".to_string(),
+        "01 08 0001000000000000".to_string(),
+        // less and greater than encoding length for scid
+        "02 07 01010101010101".to_string(),
+        "02 09 010101010101010101".to_string(),
+        // less and greater than encoding length for tlv3
+        format!("03 21 {NODE_ID}"),
+        format!("03 29 {NODE_ID} 0000000000000001"),
+        format!("03 30 {NODE_ID} 000000000000000100000000000001"),
+        format!("03 32 {NODE_ID} 0000000000000001000000000000000001"),
+        // less and greater than encoding length for tlv4
+        "fd00fe 00".to_string(),
+        "fd00fe 01 01".to_string(),
+        "fd00fe 03 010101".to_string(),
+        // unknown even field for n1
+        "00 00".to_string(),
+    ];
+
+    for input in &invalid {
+        N1::deserialize(&mut hex(input).as_slice()).expect_err(input);
+    }
+
+    let err = N1::deserialize(&mut hex("01 09 ffffffffffffffffff").as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::OutOfRange);
+    assert_eq!(err.tag(), Some(1));
+    let err = N1::deserialize(&mut hex("01 02 0001").as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NonCanonical);
+}
+
+#[test]
+fn test_ordering_failures() {
+    let err = N1::deserialize(&mut hex("02 08 0000000000000226 01 01 2a").as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnorderedTag);
+    assert_eq!(err.tag(), Some(1));
+    assert_eq!(err.offset(), Some(10));
+
+    let input = hex("02 08 0000000000000231 02 08 0000000000000451");
+    let err = N1::deserialize(&mut input.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::DuplicateTag);
+
+    // unknown odd types are ordered too
+    let err = N1::deserialize(&mut hex("1f 00 0f 01 2a").as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnorderedTag);
+    let err = N1::deserialize(&mut hex("1f 00 1f 01 2a").as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::DuplicateTag);
+
+    let input = hex("ffffffffffffffffff 00 00 00");
+    let err = N2::deserialize(&mut input.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnorderedTag);
+}
+
+#[test]
+fn test_unknown_odd_types() {
+    for input in [
+        "",
+        "21 00",
+        "fd 0201 00",
+        "fd 00fd 00",
+        "fd 00ff 00",
+        "fe 02000001 00",
+        "ff 0200000000000001 00",
+    ] {
+        assert_eq!(
+            N1::deserialize(&mut hex(input).as_slice()).unwrap(),
+            N1::default()
+        );
+        assert_eq!(
+            N2::deserialize(&mut hex(input).as_slice()).unwrap(),
+            N2::default()
+        );
+    }
+}
+
+#[test]
+fn test_n1_decoding_successes() {
+    let amounts = [
+        ("01 00", 0),
+        ("01 01 01", 1),
+        ("01 02 0100", 256),
+        ("01 03 010000", 65536),
+        ("01 04 01000000", 16777216),
+        ("01 05 0100000000", 4294967296),
+        ("01 06 010000000000", 1099511627776),
+        ("01 07 01000000000000", 281474976710656),
+        ("01 08 0100000000000000", 72057594037927936),
+    ];
+
+    let mut vectors = amounts
+        .iter()
+        .map(|(input, amount)| {
+            let expected = N1 {
+                amount_msat: Some(Tu64(*amount)),
+                ..N1::default()
+            };
+            (input.to_string(), expected)
+        })
+        .collect::<Vec<_>>();
+
+    let mut node_id = [0; 33];
+    node_id.copy_from_slice(&hex(NODE_ID));
+    vectors.extend([
+        (
+            "02 08 0000000000000226".to_string(),
+            N1 {
+                scid: Some(550),
+                ..N1::default()
+            },
+        ),
+        (
+            format!("03 31 {NODE_ID} 0000000000000001 0000000000000002"),
+            N1 {
+                tlv3: Some(Tlv3 {
+                    node_id,
+                    amount_msat_1: 1,
+                    amount_msat_2: 2,
+                }),
+                ..N1::default()
+            },
+        ),
+        (
+            "fd00fe 02 0226".to_string(),
+            N1 {
+                cltv_delta: Some(550),
+                ..N1::default()
+            },
+        ),
+    ]);
+
+    for (input, expected) in vectors {
+        let input = hex(&input);
+        assert_eq!(N1::deserialize(&mut input.as_slice()).unwrap(), expected);
+
+        let mut buf = Vec::new();
+        assert_eq!(expected.serialize(&mut buf).unwrap(), input.len());
+        assert_eq!(buf, input);
+        assert_eq!(expected.serialized_length(), input.len());
+    }
+}
+
+#[test]
+fn test_records_in_order() {
+    let value = N2 {
+        amount_msat: Some(Tu64(0x0102)),
+        cltv_expiry: Some(Tu32(144)),
+    };
+
+    let mut buf = Vec::new();
+    value.serialize(&mut buf).unwrap();
+    assert_eq!(buf, hex("00 02 0102 0b 01 90"));
+    assert_eq!(N2::deserialize(&mut buf.as_slice()).unwrap(), value);
+
+    let value = N2 {
+        amount_msat: None,
+        cltv_expiry: Some(Tu32(0)),
+    };
+
+    let mut buf = Vec::new();
+    value.serialize(&mut buf).unwrap();
+    assert_eq!(buf, hex("0b 00"));
+    assert_eq!(N2::deserialize(&mut buf.as_slice()).unwrap(), value);
+}
+
+/// Fields declared out of order are still written in order of type, and
+/// byte strings take the whole value of their record.
+#[derive(Debug, PartialEq, Serialize, Deserialize, BorrowDeserialize)]
+#[tlv(mode = "bolt")]
+struct Payload {
+    #[tlv(tag = 8)]
+    payment_data: Option<Vec<u8>>,
+    #[tlv(tag = 2)]
+    amount_to_forward: Tu64,
+    #[tlv(tag = 5)]
+    metadata: Vec<u8>,
+}
+
+#[test]
+fn test_byte_strings() {
+    let value = Payload {
+        payment_data: Some(vec![0xaa, 0xbb]),
+        amount_to_forward: Tu64(1000),
+        metadata: vec![],
+    };
+
+    let mut buf = Vec::new();
+    value.serialize(&mut buf).unwrap();
+    assert_eq!(buf, hex("02 02 03e8 05 00 08 02 aabb"));
+    assert_eq!(value.serialized_length(), buf.len());
+    assert_eq!(Payload::deserialize(&mut buf.as_slice()).unwrap(), value);
+
+    let borrowed = Payload::borrow_deserialize(&mut buf.as_slice()).unwrap();
+    assert_eq!(borrowed, value);
+
+    // a field that is not an option is required
+    let err = Payload::deserialize(&mut hex("05 00").as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::MissingTag);
+}
+
+/// Byte strings are recognized whatever the path of their `Vec`.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(mode = "bolt")]
+struct QualifiedPayload {
+    #[tlv(tag = 5)]
+    metadata: std::vec::Vec<u8>,
+    #[tlv(tag = 8)]
+    payment_data: Option<::std::vec::Vec<u8>>,
+}
+
+#[test]
+fn test_qualified_byte_strings() {
+    let value = QualifiedPayload {
+        metadata: vec![0x01],
+        payment_data: Some(vec![0xaa, 0xbb]),
+    };
+
+    let mut buf = Vec::new();
+    value.serialize(&mut buf).unwrap();
+    assert_eq!(buf, hex("05 01 01 08 02 aabb"));
+    assert_eq!(value.serialized_length(), buf.len());
+    assert_eq!(
+        QualifiedPayload::deserialize(&mut buf.as_slice()).unwrap(),
+        value
+    );
+}
@@ -0,0 +1,190 @@
+//! Golden vectors of the wire format, see `docs/wire-format.md`.
+//!
+//! Every vector is checked in both directions: the value has to serialize to
+//! exactly these bytes, and the bytes have to deserialize back to the value.
+//! A failure here means the format changed and `WIRE_FORMAT_VERSION` has to
+//! be bumped.
+
+#![cfg(feature = "alloc")]
+
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+use tlv::{Deserialize, Layout, Serialize};
+
+fn hex(s: &str) -> Vec<u8> {
+    let s: String = s.split_whitespace().collect();
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+fn check<T>(value: T, expected: &str)
+where
+    T: Serialize + Deserialize + PartialEq + Debug,
+{
+    let expected = hex(expected);
+
+    let mut buf = Vec::new();
+    let written = value.serialize(&mut buf).unwrap();
+    assert_eq!(buf, expected, "serializing {value:?}");
+    assert_eq!(written, expected.len(), "written length of {value:?}");
+    assert_eq!(
+        value.serialized_length(),
+        expected.len(),
+        "length of {value:?}"
+    );
+
+    let mut reader = expected.as_slice();
+    assert_eq!(T::deserialize(&mut reader).unwrap(), value);
+    assert!(reader.is_empty(), "{value:?} did not consume its encoding");
+}
+
+#[test]
+fn test_version() {
+    assert_eq!(tlv::WIRE_FORMAT_VERSION, 1);
+}
+
+#[test]
+fn test_integers() {
+    check(0xabu8, "ab");
+    check(0xabcdu16, "cd ab");
+    check(0x0102_0304u32, "04 03 02 01");
+    check(0x0102_0304_0506_0708u64, "08 07 06 05 04 03 02 01");
+    check(u64::MAX, "ff ff ff ff ff ff ff ff");
+    check(true, "01");
+    check(false, "00");
+    check(-2i8, "fe");
+    check(-2i16, "fe ff");
+    check(i32::MIN, "00 00 00 80");
+    check(-2i64, "fe ff ff ff ff ff ff ff");
+    check(
+        0x0102_0304_0506_0708_090a_0b0c_0d0e_0f10u128,
+        "10 0f 0e 0d 0c 0b 0a 09 08 07 06 05 04 03 02 01",
+    );
+    check(-1i128, "ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff ff");
+    check(7usize, "07 00 00 00 00 00 00 00");
+    check(-7isize, "f9 ff ff ff ff ff ff ff");
+}
+
+#[test]
+fn test_floats_and_chars() {
+    check(1.5f32, "00 00 c0 3f");
+    check(-0.0f64, "00 00 00 00 00 00 00 80");
+    check(f64::INFINITY, "00 00 00 00 00 00 f0 7f");
+    check('A', "41 00 00 00");
+    check('\u{1f980}', "80 f9 01 00");
+
+    let mut buf = Vec::new();
+    f32::from_bits(0xffc0_0001).serialize(&mut buf).unwrap();
+    assert_eq!(buf, hex("00 00 c0 7f"), "NaN is canonical");
+}
+
+#[test]
+fn test_varints() {
+    use tlv::varint::{BigSize, Leb128, QuicVarInt, ZigZag};
+
+    check(Leb128(0), "00");
+    check(Leb128(300), "ac 02");
+    check(ZigZag(-1), "01");
+    check(ZigZag(150), "ac 02");
+    check(BigSize(0xfc), "fc");
+    check(BigSize(0xfd), "fd 00 fd");
+    check(BigSize(0x1_0000), "fe 00 01 00 00");
+    check(QuicVarInt(37), "25");
+    check(QuicVarInt(15293), "7b bd");
+}
+
+#[test]
+fn test_containers() {
+    check([1u8, 2, 3], "01 02 03");
+    check(None::<u16>, "00");
+    check(Some(0x0102u16), "01 02 01");
+    check(Vec::<u16>::new(), "00 00 00 00");
+    check(vec![1u16, 2], "02 00 00 00 01 00 02 00");
+    check(
+        BTreeMap::from([(2u8, true), (1, false)]),
+        "02 00 00 00 01 00 02 01",
+    );
+}
+
+#[test]
+fn test_bytes() {
+    check(vec![0xaau8, 0xbb], "02 00 00 00 aa bb");
+    check(String::from("tlv"), "03 00 00 00 74 6c 76");
+    check(String::new(), "00 00 00 00");
+
+    let mut buf = Vec::new();
+    tlv::serialize_bytes(&[0xaa, 0xbb], &mut buf).unwrap();
+    assert_eq!(buf, hex("02 00 00 00 aa bb"));
+    assert_eq!(tlv::serialized_bytes_length(&[0xaa, 0xbb]), buf.len());
+    assert_eq!(
+        tlv::deserialize_bytes(&mut buf.as_slice()).unwrap(),
+        [0xaa, 0xbb]
+    );
+
+    let mut str_buf = Vec::new();
+    "tlv".serialize(&mut str_buf).unwrap();
+    assert_eq!(str_buf, hex("03 00 00 00 74 6c 76"));
+}
+
+#[test]
+fn test_records() {
+    let mut buf = Vec::new();
+    Layout::DEFAULT.write_record(&mut buf, 1, &0x07u32).unwrap();
+    assert_eq!(buf, hex("01 04 00 07 00 00 00"));
+
+    let fields = tlv::extract_raw(&mut buf.as_slice(), &Layout::DEFAULT, [1]).unwrap();
+    assert_eq!(fields[&1].value, hex("07 00 00 00"));
+
+    let layout = Layout::new(tlv::IntEncoding::U16, tlv::IntEncoding::U32);
+    let mut buf = Vec::new();
+    layout.write_record(&mut buf, 0x0102, &0x07u8).unwrap();
+    assert_eq!(buf, hex("02 01 01 00 00 00 07"));
+
+    let layout = Layout::new(tlv::IntEncoding::BigSize, tlv::IntEncoding::Leb128);
+    let mut buf = Vec::new();
+    layout.write_record(&mut buf, 0xfd, &[0u8; 200]).unwrap();
+    assert_eq!(buf[..5], hex("fd 00 fd c8 01"));
+    assert_eq!(layout.record_length(0xfd, 200), buf.len());
+}
+
+#[cfg(feature = "derive")]
+mod derive {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Message {
+        #[tlv(tag = 1)]
+        id: u32,
+        #[tlv(tag = 2)]
+        payload: Vec<u8>,
+        #[tlv(tag = 3)]
+        description: Option<String>,
+    }
+
+    #[test]
+    fn test_message() {
+        check(
+            Message {
+                id: 7,
+                payload: vec![0xaa, 0xbb],
+                description: None,
+            },
+            "01 04 00 07 00 00 00
+             02 06 00 02 00 00 00 aa bb
+             03 01 00 00",
+        );
+        check(
+            Message {
+                id: 7,
+                payload: Vec::new(),
+                description: Some("hi".to_string()),
+            },
+            "01 04 00 07 00 00 00
+             02 04 00 00 00 00 00
+             03 07 00 01 02 00 00 00 68 69",
+        );
+    }
+}
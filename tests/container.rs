@@ -0,0 +1,124 @@
+//! Attributes configuring a whole derived type.
+
+#![cfg(all(feature = "alloc", feature = "derive"))]
+
+use tlv::{BorrowDeserialize, Deserialize, ErrorKind, Serialize};
+
+fn hex(s: &str) -> Vec<u8> {
+    let s: String = s.split_whitespace().collect();
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+fn encoded<T: Serialize>(value: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    assert_eq!(value.serialize(&mut buf).unwrap(), buf.len());
+    assert_eq!(value.serialized_length(), buf.len());
+    buf
+}
+
+/// A crate re-exporting `tlv`, whose users may not depend on it directly.
+mod reexport {
+    pub use tlv;
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(mode = "stream", order = "tag")]
+struct Sorted {
+    #[tlv(tag = 3)]
+    last: u8,
+    #[tlv(tag = 1)]
+    first: u8,
+    #[tlv(tag = 2)]
+    second: u8,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(mode = "stream", deny_unknown_fields)]
+struct Strict {
+    #[tlv(tag = 1)]
+    id: u8,
+    #[tlv(tag = 2)]
+    name: Option<u8>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(mode = "bolt", deny_unknown_fields)]
+struct StrictStream {
+    #[tlv(tag = 2)]
+    amount: Option<u64>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, BorrowDeserialize)]
+#[tlv(crate = "reexport::tlv", tag_encoding = "u16", byte_order = "big")]
+struct Reexported {
+    #[tlv(tag = 0x100)]
+    id: u16,
+}
+
+#[test]
+fn test_order() {
+    let sorted = Sorted {
+        last: 3,
+        first: 1,
+        second: 2,
+    };
+    let input = hex("01 0100 01 02 0100 02 03 0100 03");
+    assert_eq!(encoded(&sorted), input);
+    assert_eq!(Sorted::deserialize(&mut input.as_slice()).unwrap(), sorted);
+
+    // records are still read in any order
+    let input = hex("03 0100 03 01 0100 01 02 0100 02");
+    assert_eq!(Sorted::deserialize(&mut input.as_slice()).unwrap(), sorted);
+}
+
+#[test]
+fn test_deny_unknown_fields() {
+    let input = hex("01 0100 07 02 0100 00");
+    let strict = Strict::deserialize(&mut input.as_slice()).unwrap();
+    assert_eq!(strict, Strict { id: 7, name: None });
+
+    let input = hex("01 0100 07 09 0100 aa");
+    let err = Strict::deserialize(&mut input.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedTag);
+    assert_eq!(err.tag(), Some(9));
+    // the position of the record
+    assert_eq!(err.offset(), Some(4));
+
+    // decoding stops at the tag, the length and the value are not read
+    let input = hex("01 0100 07 09 ffff 01 0100 07");
+    let mut reader = input.as_slice();
+    let err = Strict::deserialize(&mut reader).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedTag);
+    assert_eq!(reader, hex("ffff 01 0100 07"));
+
+    // even the odd types a BOLT #1 stream may skip
+    let input = hex("02 08 0000000000000001 03 00");
+    let err = StrictStream::deserialize(&mut input.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedTag);
+    assert_eq!(err.tag(), Some(3));
+    assert_eq!(err.offset(), Some(10));
+
+    let input = hex("03 fe ffffffff");
+    let mut reader = input.as_slice();
+    let err = StrictStream::deserialize(&mut reader).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedTag);
+    assert_eq!(reader, hex("fe ffffffff"));
+}
+
+#[test]
+fn test_crate_path() {
+    let value = Reexported { id: 0x0102 };
+    let input = hex("0100 0002 0102");
+    assert_eq!(encoded(&value), input);
+    assert_eq!(
+        Reexported::deserialize(&mut input.as_slice()).unwrap(),
+        value
+    );
+    assert_eq!(
+        Reexported::borrow_deserialize(&mut input.as_slice()).unwrap(),
+        value
+    );
+}
@@ -0,0 +1,192 @@
+//! Parts of an X.509 certificate mapped to derived types in DER mode.
+
+#![cfg(all(feature = "alloc", feature = "derive"))]
+
+use tlv::der::{
+    self, BigInteger, Boolean, Integer, Null, ObjectIdentifier, OctetString, SequenceOf, Utf8String,
+};
+use tlv::{BorrowDeserialize, Deserialize, ErrorKind, Serialize};
+
+fn hex(s: &str) -> Vec<u8> {
+    let s: String = s.split_whitespace().collect();
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+fn encoded<T: der::Tagged + Serialize>(value: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    assert_eq!(der::serialize(value, &mut buf).unwrap(), buf.len());
+    assert_eq!(der::serialized_length(value), buf.len());
+    buf
+}
+
+/// `Extension ::= SEQUENCE { extnID OBJECT IDENTIFIER, critical BOOLEAN
+/// DEFAULT FALSE, extnValue OCTET STRING }`
+#[derive(Debug, PartialEq, Serialize, Deserialize, BorrowDeserialize)]
+#[tlv(mode = "der")]
+struct Extension {
+    extn_id: ObjectIdentifier,
+    critical: Option<Boolean>,
+    extn_value: OctetString,
+}
+
+/// `AlgorithmIdentifier ::= SEQUENCE { algorithm OBJECT IDENTIFIER,
+/// parameters ANY DEFINED BY algorithm OPTIONAL }`, with the parameters of
+/// the RSA algorithms.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(mode = "der")]
+struct AlgorithmIdentifier {
+    algorithm: ObjectIdentifier,
+    parameters: Option<Null>,
+}
+
+/// `CertificateSerialNumber ::= INTEGER`, of up to 20 octets.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(mode = "der")]
+struct SerialNumber(BigInteger);
+
+/// The start of a `TBSCertificate`, with its explicitly tagged version and
+/// its implicitly tagged unique identifiers, here a string for brevity.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(mode = "der")]
+struct TbsCertificate {
+    #[tlv(explicit = 0)]
+    version: Option<Integer>,
+    serial_number: SerialNumber,
+    signature: AlgorithmIdentifier,
+    #[tlv(implicit = 1)]
+    issuer_unique_id: Option<Utf8String>,
+    #[tlv(implicit = 3)]
+    extensions: Option<SequenceOf<Extension>>,
+}
+
+fn basic_constraints() -> Extension {
+    Extension {
+        extn_id: ObjectIdentifier(vec![2, 5, 29, 19]),
+        critical: Some(Boolean(true)),
+        extn_value: OctetString(hex("30 03 01 01 ff")),
+    }
+}
+
+#[test]
+fn test_extension() {
+    let input = hex("30 0f 06 03 551d13 01 01 ff 04 05 3003 0101ff");
+
+    let extension = basic_constraints();
+    assert_eq!(encoded(&extension), input);
+    assert_eq!(
+        der::deserialize::<Extension, _>(&mut input.as_slice()).unwrap(),
+        extension
+    );
+
+    // the contents of the sequence, borrowed
+    let contents = &input[2..];
+    let borrowed = Extension::borrow_deserialize(&mut &contents[..]).unwrap();
+    assert_eq!(borrowed, extension);
+
+    // an absent optional field has no record
+    let extension = Extension {
+        critical: None,
+        ..basic_constraints()
+    };
+    let input = hex("30 0c 06 03 551d13 04 05 3003 0101ff");
+    assert_eq!(encoded(&extension), input);
+    assert_eq!(
+        der::deserialize::<Extension, _>(&mut input.as_slice()).unwrap(),
+        extension
+    );
+}
+
+#[test]
+fn test_tagged_fields() {
+    let tbs = TbsCertificate {
+        version: Some(Integer(2)),
+        serial_number: SerialNumber(BigInteger::from(Integer(0x1000))),
+        signature: AlgorithmIdentifier {
+            algorithm: ObjectIdentifier(vec![1, 2, 840, 113549, 1, 1, 11]),
+            parameters: Some(Null),
+        },
+        issuer_unique_id: Some(Utf8String("ca".into())),
+        extensions: Some(SequenceOf(vec![basic_constraints()])),
+    };
+
+    let input = hex("30 2f
+            a0 03 02 01 02
+            02 02 1000
+            30 0d 06 09 2a864886f70d01010b 05 00
+            81 02 6361
+            a3 11 30 0f 06 03 551d13 01 01 ff 04 05 3003 0101ff");
+    assert_eq!(encoded(&tbs), input);
+    assert_eq!(
+        der::deserialize::<TbsCertificate, _>(&mut input.as_slice()).unwrap(),
+        tbs
+    );
+
+    // a version 1 certificate leaves the version out
+    let input = hex("30 14 02 01 01 30 0d 06 09 2a864886f70d01010b 05 00 a3 00");
+    let tbs = der::deserialize::<TbsCertificate, _>(&mut input.as_slice()).unwrap();
+    assert_eq!(tbs.version, None);
+    assert_eq!(tbs.serial_number.0.as_bytes(), [1]);
+    assert_eq!(tbs.extensions, Some(SequenceOf(vec![])));
+
+    // serial numbers beyond 128 bits
+    let input = hex("30 27
+            02 14 5a0f39e2c5d7a1b3e9f4c6d8a2b4c6d8e0f1a3b5
+            30 0d 06 09 2a864886f70d01010b 05 00 a3 00");
+    let tbs = der::deserialize::<TbsCertificate, _>(&mut input.as_slice()).unwrap();
+    assert_eq!(tbs.serial_number.0.as_bytes(), &input[4..24]);
+    assert_eq!(encoded(&tbs), input);
+}
+
+#[test]
+fn test_strict_decoding() {
+    let invalid = [
+        // fields out of order
+        (
+            "30 0f 01 01 ff 06 03 551d13 04 05 3003 0101ff",
+            ErrorKind::MissingTag,
+        ),
+        // a record no field takes
+        (
+            "30 10 06 03 551d13 04 05 3003 0101ff 05 00 05 00",
+            ErrorKind::UnexpectedTag,
+        ),
+        // a BER true
+        (
+            "30 0f 06 03 551d13 01 01 01 04 05 3003 0101ff",
+            ErrorKind::NonCanonical,
+        ),
+        // a long form length
+        (
+            "30 10 06 81 03 551d13 01 01 ff 04 05 3003 0101ff",
+            ErrorKind::NonCanonical,
+        ),
+        // a tag number below 31 in the long form
+        (
+            "30 10 1f 06 03 551d13 01 01 ff 04 05 3003 0101ff",
+            ErrorKind::NonCanonical,
+        ),
+        // an indefinite length
+        (
+            "30 80 06 03 551d13 04 05 3003 0101ff 0000",
+            ErrorKind::Unsupported,
+        ),
+    ];
+
+    for (input, kind) in invalid {
+        let err = der::deserialize::<Extension, _>(&mut hex(input).as_slice()).unwrap_err();
+        assert_eq!(err.kind(), kind, "{input}");
+    }
+
+    let input = hex("30 0e 06 03 551d13 04 05 3003 0101ff 05 00");
+    let err = der::deserialize::<Extension, _>(&mut input.as_slice()).unwrap_err();
+    assert_eq!(err.tag(), Some(0x05));
+
+    // a padded integer
+    let input = hex("30 13 02 02 0001 30 0d 06 09 2a864886f70d01010b 05 00");
+    let err = der::deserialize::<TbsCertificate, _>(&mut input.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NonCanonical);
+    assert_eq!(err.tag(), Some(0x02));
+}
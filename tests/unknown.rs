@@ -0,0 +1,200 @@
+//! Records no field knows about, kept in a `#[tlv(unknown)]` field and
+//! written back as they were read.
+
+#![cfg(all(feature = "alloc", feature = "derive"))]
+
+use std::collections::BTreeMap;
+
+use tlv::{BorrowDeserialize, Deserialize, ErrorKind, RawField, Serialize};
+
+fn hex(s: &str) -> Vec<u8> {
+    let s: String = s.split_whitespace().collect();
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+fn encoded<T: Serialize>(value: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    assert_eq!(value.serialize(&mut buf).unwrap(), buf.len());
+    assert_eq!(value.serialized_length(), buf.len());
+    buf
+}
+
+/// A newer version of a message, with a record its older version lacks.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(mode = "stream")]
+struct SettingsV2 {
+    #[tlv(tag = 1)]
+    id: u32,
+    #[tlv(tag = 2)]
+    retries: Option<u8>,
+    #[tlv(tag = 3)]
+    label: Vec<u8>,
+}
+
+/// The older version, passing the records it does not know on.
+#[derive(Debug, PartialEq, Serialize, Deserialize, BorrowDeserialize)]
+#[tlv(mode = "stream")]
+struct SettingsV1 {
+    #[tlv(tag = 1)]
+    id: u32,
+    #[tlv(unknown)]
+    unknown: Vec<RawField>,
+    #[tlv(tag = 2)]
+    retries: Option<u8>,
+}
+
+/// The unknown records of a tuple struct, by tag.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(mode = "stream")]
+struct Tagged(#[tlv(unknown)] BTreeMap<u64, Vec<u8>>, #[tlv(tag = 1)] u8);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(mode = "bolt")]
+struct Forward {
+    #[tlv(tag = 2)]
+    scid: Option<u64>,
+    #[tlv(tag = 6)]
+    cltv_delta: Option<u16>,
+    #[tlv(unknown)]
+    extra: BTreeMap<u64, Vec<u8>>,
+}
+
+/// A stream keeping its unknown records in the order they were added.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(mode = "bolt")]
+struct Onion {
+    #[tlv(tag = 4)]
+    amount: Option<u64>,
+    #[tlv(unknown)]
+    extra: Vec<RawField>,
+}
+
+#[test]
+fn test_stream_proxy() {
+    let v2 = SettingsV2 {
+        id: 7,
+        retries: Some(3),
+        label: b"eu".to_vec(),
+    };
+    let input = encoded(&v2);
+    assert_eq!(
+        input,
+        hex("01 0400 07000000 02 0200 0103 03 0600 02000000 6575")
+    );
+
+    let v1 = SettingsV1::deserialize(&mut input.as_slice()).unwrap();
+    assert_eq!(v1.id, 7);
+    assert_eq!(v1.retries, Some(3));
+    assert_eq!(v1.unknown.len(), 1);
+    assert_eq!(v1.unknown[0].id, 3);
+    assert_eq!(v1.unknown[0].value, hex("02000000 6575"));
+    assert_eq!(v1.unknown[0].offset, 15);
+
+    // the older version writes the record back after its own ones
+    assert_eq!(encoded(&v1), input);
+
+    let borrowed = SettingsV1::borrow_deserialize(&mut input.as_slice()).unwrap();
+    assert_eq!(borrowed, v1);
+
+    // records with the same unknown tag are all kept
+    let input = hex("01 0400 07000000 02 0100 00 09 0100 aa 09 0000");
+    let v1 = SettingsV1::deserialize(&mut input.as_slice()).unwrap();
+    assert_eq!(v1.unknown.len(), 2);
+    assert_eq!(encoded(&v1), input);
+}
+
+#[test]
+fn test_unknown_by_tag() {
+    let input = hex("05 0100 aa 01 0100 2a 03 0000");
+    let tagged = Tagged::deserialize(&mut input.as_slice()).unwrap();
+    assert_eq!(tagged.1, 42);
+    assert_eq!(tagged.0, BTreeMap::from([(3, vec![]), (5, vec![0xaa])]));
+
+    // written back in the order of their tags
+    assert_eq!(encoded(&tagged), hex("01 0100 2a 03 0000 05 0100 aa"));
+
+    let input = hex("01 0100 2a 05 0100 aa 05 0000");
+    let err = Tagged::deserialize(&mut input.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::DuplicateTag);
+    assert_eq!(err.tag(), Some(5));
+    assert_eq!(err.offset(), Some(11));
+}
+
+#[test]
+fn test_bolt_proxy() {
+    let input = hex("01 01 aa 02 08 0000000000000001 03 00 06 02 0028 0b 01 ff");
+    let forward = Forward::deserialize(&mut input.as_slice()).unwrap();
+    assert_eq!(forward.scid, Some(1));
+    assert_eq!(forward.cltv_delta, Some(40));
+    assert_eq!(
+        forward.extra,
+        BTreeMap::from([(1, vec![0xaa]), (3, vec![]), (11, vec![0xff])])
+    );
+
+    // the unknown records keep their place in the ascending order of types
+    assert_eq!(encoded(&forward), input);
+
+    let input = hex("01 01 aa 03 00 06 02 0028");
+    let forward = Forward::deserialize(&mut input.as_slice()).unwrap();
+    assert_eq!(forward.scid, None);
+    assert_eq!(encoded(&forward), input);
+
+    // unknown even types are still rejected
+    let err = Forward::deserialize(&mut hex("04 00").as_slice()).unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::UnexpectedTag);
+
+    // records with the type of a field are left out
+    let forward = Forward {
+        scid: None,
+        cltv_delta: None,
+        extra: BTreeMap::from([(2, vec![0]), (5, vec![])]),
+    };
+    assert_eq!(encoded(&forward), hex("05 00"));
+}
+
+#[test]
+fn test_bolt_unknown_order() {
+    let onion = Onion {
+        amount: Some(1),
+        extra: vec![
+            RawField::new(7, 1, vec![0xbb]),
+            RawField::new(5, 1, vec![0xaa]),
+            RawField::new(1, 0, vec![]),
+        ],
+    };
+
+    // written in the order of their types, whatever the order they are in
+    let buf = encoded(&onion);
+    assert_eq!(buf, hex("01 00 04 08 0000000000000001 05 01 aa 07 01 bb"));
+
+    let decoded = Onion::deserialize(&mut buf.as_slice()).unwrap();
+    assert_eq!(decoded.amount, Some(1));
+    let types = decoded
+        .extra
+        .iter()
+        .map(|record| record.id)
+        .collect::<Vec<_>>();
+    assert_eq!(types, [1, 5, 7]);
+}
+
+#[test]
+fn test_stream_unknown_known_tags() {
+    // records with the tag of a field are left out, as they would be read
+    // as that field
+    let settings = SettingsV1 {
+        id: 7,
+        unknown: vec![
+            RawField::new(1, 4, vec![0; 4]),
+            RawField::new(3, 1, vec![0x2a]),
+            RawField::new(2, 1, vec![1]),
+        ],
+        retries: None,
+    };
+
+    let buf = encoded(&settings);
+    assert_eq!(buf, hex("01 0400 07000000 02 0100 00 03 0100 2a"));
+    assert_eq!(SettingsV1::deserialize(&mut buf.as_slice()).unwrap().id, 7);
+}
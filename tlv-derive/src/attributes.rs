@@ -1,70 +1,1139 @@
-use quote::ToTokens;
-use syn::{punctuated::Punctuated, LitInt};
+use proc_macro2::{Ident, TokenStream};
+use quote::quote;
+use syn::{punctuated::Punctuated, LitInt, LitStr};
 
-pub(crate) fn parse_tlv_fields_attributes(
-    fields: &Punctuated<syn::Field, syn::Token![,]>,
-) -> Result<Vec<TlvFieldAttributes>, syn::Error> {
-    fields
-        .iter()
-        .flat_map(|field| field.attrs.to_owned())
-        .filter(|attr| attr.path.is_ident("tlv"))
-        .map(TryFrom::try_from)
-        .collect()
+/// Parses the attributes of the fields of a container, skipping the
+/// `#[tlv(unknown)]` field, and returns them with the index of their field.
+///
+/// Every field needs a tag unless it is skipped, which fits the tag encoding
+/// of the container and no other field has.
+pub(crate) fn parse_tlv_fields_attributes<'a>(
+    container: &TlvContainerAttributes,
+    fields: &'a Punctuated<syn::Field, syn::Token![,]>,
+) -> Result<Vec<(usize, &'a syn::Field, TlvFieldAttributes)>, syn::Error> {
+    let mut parsed = Vec::with_capacity(fields.len());
+    let mut tags = Tags::default();
+
+    for (index, field) in fields.iter().enumerate() {
+        if is_unknown_field(field)? {
+            continue;
+        }
+
+        let attributes = parse_tlv_field_attributes(container, field)?;
+        if let Some(tag) = &attributes.tag {
+            tags.insert(container, tag)?;
+        }
+        parsed.push((index, field, attributes));
+    }
+
+    Ok(parsed)
+}
+
+fn parse_tlv_field_attributes(
+    container: &TlvContainerAttributes,
+    field: &syn::Field,
+) -> Result<TlvFieldAttributes, syn::Error> {
+    let mut attributes = TlvFieldAttributes::default();
+    let mut skip = None;
+
+    for attr in field.attrs.iter().filter(|attr| attr.path.is_ident("tlv")) {
+        let list = match attr.parse_meta()? {
+            syn::Meta::List(list) => list,
+            meta => {
+                return Err(syn::Error::new_spanned(
+                    meta,
+                    "Attribute must be like #[tlv(tag = 1)]",
+                ))
+            }
+        };
+
+        for nested in list.nested.iter() {
+            let set = match nested {
+                syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    path,
+                    lit: syn::Lit::Int(lit),
+                    ..
+                })) if path.is_ident("tag") => attributes.tag.replace(lit.clone()).is_none(),
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("default") => {
+                    attributes.default.replace(FieldDefault::Trait).is_none()
+                }
+                syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    path,
+                    lit: syn::Lit::Str(lit),
+                    ..
+                })) if path.is_ident("default") => attributes
+                    .default
+                    .replace(FieldDefault::Path(lit.parse()?))
+                    .is_none(),
+                syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    path,
+                    lit: syn::Lit::Str(lit),
+                    ..
+                })) if path.is_ident("with") => attributes.with.replace(lit.parse()?).is_none(),
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("skip") => {
+                    skip.replace(path.clone()).is_none()
+                }
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("required") => {
+                    !std::mem::replace(&mut attributes.required, true)
+                }
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        nested,
+                        "Invalid attribute: only 'tag', 'default', 'skip', 'with', 'required' and 'unknown' are valid",
+                    ))
+                }
+            };
+
+            if !set {
+                return Err(syn::Error::new_spanned(
+                    nested,
+                    "Each attribute can only be given once",
+                ));
+            }
+        }
+    }
+
+    if let Some(skip) = skip {
+        if attributes.tag.is_some() || attributes.with.is_some() || attributes.required {
+            return Err(syn::Error::new_spanned(
+                skip,
+                "A skipped field has no record: it cannot be combined with 'tag', 'with' or 'required'",
+            ));
+        }
+        // a skipped field is always decoded to its default
+        attributes.default.get_or_insert(FieldDefault::Trait);
+        return Ok(attributes);
+    }
+
+    if attributes.tag.is_none() {
+        return Err(syn::Error::new_spanned(
+            field,
+            "Field must have a #[tlv(tag = ...)] attribute",
+        ));
+    }
+    if attributes.default.is_some() && attributes.required {
+        return Err(syn::Error::new_spanned(
+            field,
+            "A field is either required or has a default, not both",
+        ));
+    }
+    if attributes.default.is_some() && container.mode == Mode::Exact {
+        return Err(syn::Error::new_spanned(
+            field,
+            "#[tlv(default)] needs mode = \"stream\", \"bolt\" or \"ber\", where records can be missing",
+        ));
+    }
+
+    Ok(attributes)
+}
+
+/// The tags of the fields or variants of a container, checked one by one.
+#[derive(Default)]
+struct Tags(Vec<u64>);
+
+impl Tags {
+    /// Checks that `lit` is a tag the layout of `container` can write, that
+    /// is not reserved and was not inserted before, and inserts it.
+    fn insert(
+        &mut self,
+        container: &TlvContainerAttributes,
+        lit: &LitInt,
+    ) -> Result<(), syn::Error> {
+        let tag = lit.base10_parse::<u64>()?;
+
+        let encoding = container
+            .tag_encoding
+            .as_ref()
+            .map_or_else(|| "U8".to_string(), Ident::to_string);
+        let max = match encoding.as_str() {
+            "U8" => u8::MAX as u64,
+            "U16" => u16::MAX as u64,
+            "U32" => u32::MAX as u64,
+            "Quic" => (1 << 62) - 1,
+            _ => u64::MAX,
+        };
+        if tag > max {
+            return Err(syn::Error::new_spanned(
+                lit,
+                format!("Tag {tag} does not fit the tag encoding of the type, at most {max}"),
+            ));
+        }
+
+        if encoding == "BerTag" {
+            let size = 8 - (tag | 1).leading_zeros() as usize / 8;
+            let bytes = &tag.to_be_bytes()[8 - size..];
+            if !is_ber_tag(bytes) {
+                return Err(syn::Error::new_spanned(
+                    lit,
+                    format!("Tag {tag:#x} is not a BER tag: a tag number of 0x1f in the first octet is continued in the next ones, the last of which has its high bit clear"),
+                ));
+            }
+            // ISO/IEC 7816-4 pads between records with these octets
+            if bytes[0] == 0x00 || bytes[0] == 0xff {
+                return Err(syn::Error::new_spanned(
+                    lit,
+                    format!(
+                        "Tag {tag:#x} is reserved: a BER-TLV tag cannot start with 0x00 or 0xff"
+                    ),
+                ));
+            }
+        }
+
+        if self.0.contains(&tag) {
+            return Err(syn::Error::new_spanned(lit, format!("Duplicate tag {tag}")));
+        }
+        self.0.push(tag);
+
+        Ok(())
+    }
+}
+
+/// Whether `bytes` are the octets of a BER tag, as `tlv::ber` reads them.
+fn is_ber_tag(bytes: &[u8]) -> bool {
+    match bytes {
+        [first] => first & 0x1f != 0x1f,
+        [first, subsequent @ ..] => {
+            first & 0x1f == 0x1f
+                && subsequent[0] != 0x80
+                && subsequent.iter().rev().skip(1).all(|byte| byte & 0x80 != 0)
+                && subsequent[subsequent.len() - 1] & 0x80 == 0
+        }
+        [] => false,
+    }
+}
+
+/// Whether the field is marked `#[tlv(unknown)]`.
+pub(crate) fn is_unknown_field(field: &syn::Field) -> Result<bool, syn::Error> {
+    for attr in field.attrs.iter().filter(|attr| attr.path.is_ident("tlv")) {
+        // other forms are reported with the other attributes of the field
+        let syn::Meta::List(list) = attr.parse_meta()? else {
+            continue;
+        };
+        let marked = list.nested.iter().any(|nested| {
+            matches!(nested, syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("unknown"))
+        });
+        if marked {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Finds the field marked `#[tlv(unknown)]`, which keeps the records with
+/// tags no other field has, and returns its index.
+///
+/// Only the modes reading records until the end of the input have such
+/// records to keep, and a container has at most one such field.
+pub(crate) fn parse_unknown_field<'a>(
+    container: &TlvContainerAttributes,
+    fields: &'a Punctuated<syn::Field, syn::Token![,]>,
+) -> Result<Option<(usize, &'a syn::Field)>, syn::Error> {
+    let mut unknown = Vec::new();
+    for (index, field) in fields.iter().enumerate() {
+        if is_unknown_field(field)? {
+            unknown.push((index, field));
+        }
+    }
+
+    let Some(&(index, field)) = unknown.first() else {
+        return Ok(None);
+    };
+    if let Some((_, other)) = unknown.get(1) {
+        return Err(syn::Error::new_spanned(
+            other,
+            "Only one field can be marked #[tlv(unknown)]",
+        ));
+    }
+
+    let attrs = field.attrs.iter().filter(|attr| attr.path.is_ident("tlv"));
+    for attr in attrs {
+        if !matches!(attr.parse_meta()?, syn::Meta::List(list) if list.nested.len() == 1) {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "#[tlv(unknown)] cannot be combined with other attributes",
+            ));
+        }
+    }
+
+    if container.deny_unknown_fields {
+        return Err(syn::Error::new_spanned(
+            field,
+            "#[tlv(unknown)] keeps the records deny_unknown_fields rejects",
+        ));
+    }
+
+    match container.mode {
+        Mode::Stream | Mode::Bolt | Mode::Ber => Ok(Some((index, field))),
+        Mode::Exact | Mode::Der => Err(syn::Error::new_spanned(
+            field,
+            "#[tlv(unknown)] needs mode = \"stream\", \"bolt\" or \"ber\", which read records until the end of the input",
+        )),
+    }
 }
 
 /// Attributes for a TLV field.
 ///
 /// Specified using the `#[tlv()]` macro attribute
+#[derive(Default)]
 pub struct TlvFieldAttributes {
-    /// The `tag` of the field
-    pub tag: LitInt,
+    /// The `tag` of the field, none for a field with `skip`, which has no
+    /// record.
+    pub tag: Option<LitInt>,
+
+    /// What a missing record decodes to, with `default` or
+    /// `default = "path"`.
+    pub default: Option<FieldDefault>,
+
+    /// Module whose `serialize`, `serialized_length` and `deserialize`
+    /// functions encode the field, with `with = "module"`.
+    pub with: Option<syn::Path>,
+
+    /// Whether the record has to be present, even for an `Option`.
+    pub required: bool,
+}
+
+/// The value of a field whose record is missing.
+pub enum FieldDefault {
+    /// `Default::default()`.
+    Trait,
+    /// The value returned by a function.
+    Path(syn::Path),
+}
+
+impl FieldDefault {
+    /// Expression evaluating to the default value.
+    pub fn value(&self) -> TokenStream {
+        match self {
+            FieldDefault::Trait => quote! { ::core::default::Default::default() },
+            FieldDefault::Path(path) => quote! { #path() },
+        }
+    }
+}
+
+/// Parses the attributes of the fields of a `mode = "der"` container, one
+/// entry per field whether it has attributes or not.
+pub(crate) fn parse_der_fields_attributes(
+    fields: &Punctuated<syn::Field, syn::Token![,]>,
+) -> Result<Vec<DerFieldAttributes>, syn::Error> {
+    let mut parsed = Vec::with_capacity(fields.len());
+
+    for field in fields.iter() {
+        let mut attributes = DerFieldAttributes::default();
+
+        for attr in field.attrs.iter().filter(|attr| attr.path.is_ident("tlv")) {
+            let list = match attr.parse_meta()? {
+                syn::Meta::List(list) => list,
+                meta => {
+                    return Err(syn::Error::new_spanned(
+                        meta,
+                        "Attribute must be like #[tlv(implicit = 0)] or #[tlv(explicit = 0)]",
+                    ))
+                }
+            };
+
+            for nested in list.nested.iter() {
+                let tagging = match nested {
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                        path,
+                        lit: syn::Lit::Int(lit),
+                        ..
+                    })) if path.is_ident("implicit") => (Tagging::Implicit, lit.clone()),
+                    syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                        path,
+                        lit: syn::Lit::Int(lit),
+                        ..
+                    })) if path.is_ident("explicit") => (Tagging::Explicit, lit.clone()),
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            nested,
+                            "Invalid attribute: only 'implicit' and 'explicit' are valid in mode = \"der\", the tags of other fields are the ones of their types",
+                        ))
+                    }
+                };
+
+                if attributes.tagging.is_some() {
+                    return Err(syn::Error::new_spanned(
+                        nested,
+                        "A field is tagged either implicitly or explicitly, once",
+                    ));
+                }
+                tagging.1.base10_parse::<u32>()?;
+                attributes.tagging = Some(tagging);
+            }
+        }
+
+        parsed.push(attributes);
+    }
+
+    Ok(parsed)
+}
+
+/// How a field of a `mode = "der"` container is tagged in the
+/// context-specific class.
+pub enum Tagging {
+    /// The tag replaces the one of the type of the field.
+    Implicit,
+    /// The record of the field is the value of a record with the tag.
+    Explicit,
+}
+
+/// Attributes for a field of a `mode = "der"` container.
+#[derive(Default)]
+pub struct DerFieldAttributes {
+    /// The context-specific tagging of the field and its tag number, none
+    /// for a field tagged by its type.
+    pub tagging: Option<(Tagging, LitInt)>,
 }
 
-impl TlvFieldAttributes {
-    pub fn new(tag: LitInt) -> Self {
-        Self { tag }
+impl DerFieldAttributes {
+    /// Expression evaluating to the tag of the record of a field of type
+    /// `ty`, the `T` of an `Option<T>`.
+    pub fn tag(&self, ty: &syn::Type) -> TokenStream {
+        match &self.tagging {
+            None => quote! { <#ty as __tlv::der::Tagged>::TAG },
+            Some((Tagging::Implicit, number)) => quote! { __tlv::der::implicit::<#ty>(#number) },
+            Some((Tagging::Explicit, number)) => quote! { __tlv::der::explicit(#number) },
+        }
+    }
+
+    pub fn is_explicit(&self) -> bool {
+        matches!(self.tagging, Some((Tagging::Explicit, _)))
     }
 }
 
-impl TryFrom<syn::Attribute> for TlvFieldAttributes {
-    type Error = syn::Error;
+/// Parses the attributes of the variants of an enum, one entry per variant.
+///
+/// The tags of the variants are checked like the ones of fields.
+pub(crate) fn parse_tlv_variants_attributes(
+    container: &TlvContainerAttributes,
+    variants: &Punctuated<syn::Variant, syn::Token![,]>,
+) -> Result<Vec<TlvVariantAttributes>, syn::Error> {
+    let mut parsed = Vec::with_capacity(variants.len());
+    let mut tags = Tags::default();
+
+    for variant in variants.iter() {
+        let attributes = parse_tlv_variant_attributes(variant)?;
+        if let Some(tag) = &attributes.tag {
+            tags.insert(container, tag)?;
+        }
+        parsed.push(attributes);
+    }
+
+    Ok(parsed)
+}
 
-    fn try_from(value: syn::Attribute) -> Result<Self, Self::Error> {
-        let meta = value.parse_meta().unwrap();
-        let list = match meta {
-            syn::Meta::List(meta) => meta,
-            _ => {
+fn parse_tlv_variant_attributes(
+    variant: &syn::Variant,
+) -> Result<TlvVariantAttributes, syn::Error> {
+    let mut attributes = TlvVariantAttributes::default();
+
+    for attr in variant
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("tlv"))
+    {
+        let list = match attr.parse_meta()? {
+            syn::Meta::List(list) => list,
+            meta => {
                 return Err(syn::Error::new_spanned(
-                    &meta,
-                    "Attribute must be like #[tlv(tag = 1)]",
+                    meta,
+                    "Attribute must be like #[tlv(tag = 1)] or #[tlv(other)]",
                 ))
             }
         };
 
-        let (key, value) = match list.nested.first().unwrap() {
-            syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
-                path,
-                lit: syn::Lit::Int(lit),
-                ..
-            })) => (path, lit),
-            _ => {
+        for nested in list.nested.iter() {
+            let set = match nested {
+                syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    path,
+                    lit: syn::Lit::Int(lit),
+                    ..
+                })) if path.is_ident("tag") => attributes.tag.replace(lit.clone()).is_none(),
+                syn::NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("other") => {
+                    !std::mem::replace(&mut attributes.other, true)
+                }
+                _ => {
+                    return Err(syn::Error::new_spanned(
+                        nested,
+                        "Invalid attribute: only 'tag' and 'other' are valid",
+                    ))
+                }
+            };
+
+            if !set {
                 return Err(syn::Error::new_spanned(
-                    &list,
-                    format!(
-                        "Invalid attribute, shoudld be tag = 1, got: {}",
-                        list.nested.first().unwrap().to_token_stream()
-                    ),
+                    nested,
+                    "Each attribute can only be given once",
+                ));
+            }
+        }
+    }
+
+    if attributes.tag.is_some() == attributes.other {
+        return Err(syn::Error::new_spanned(
+            &variant.ident,
+            "Variant must have either #[tlv(tag = ...)] or #[tlv(other)]",
+        ));
+    }
+
+    if attributes.other
+        && !matches!(&variant.fields, syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1)
+    {
+        return Err(syn::Error::new_spanned(
+            &variant.ident,
+            "The #[tlv(other)] variant must hold a single tlv::RawField",
+        ));
+    }
+
+    Ok(attributes)
+}
+
+/// Attributes for a variant of a TLV enum.
+///
+/// Specified using the `#[tlv()]` macro attribute on the variant.
+#[derive(Default)]
+pub struct TlvVariantAttributes {
+    /// The `tag` of the record the variant is encoded as.
+    pub tag: Option<LitInt>,
+
+    /// Whether the variant catches records with unknown tags.
+    pub other: bool,
+}
+
+pub(crate) fn parse_tlv_container_attributes(
+    input: &syn::DeriveInput,
+) -> Result<TlvContainerAttributes, syn::Error> {
+    let mut container = TlvContainerAttributes::default();
+    let mut mode = None;
+    let mut order = None;
+    let mut deny = None;
+
+    for attr in input.attrs.iter().filter(|attr| attr.path.is_ident("tlv")) {
+        let list = match attr.parse_meta()? {
+            syn::Meta::List(list) => list,
+            meta => {
+                return Err(syn::Error::new_spanned(
+                    meta,
+                    "Attribute must be like #[tlv(tag_encoding = \"u16\")]",
                 ))
             }
         };
 
-        if key.is_ident("tag") {
-            Ok(TlvFieldAttributes::new(value.clone()))
-        } else {
-            Err(syn::Error::new_spanned(
-                &key,
-                "Invalid attribute: only 'tag' is valid",
+        for nested in list.nested.iter() {
+            if let syn::NestedMeta::Meta(syn::Meta::Path(path)) = nested {
+                if path.is_ident("deny_unknown_fields") {
+                    container.deny_unknown_fields = true;
+                    if deny.replace(path.clone()).is_some() {
+                        return Err(syn::Error::new_spanned(
+                            nested,
+                            "Each attribute can only be given once",
+                        ));
+                    }
+                    continue;
+                }
+            }
+
+            let syn::NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                path,
+                lit: syn::Lit::Str(lit),
+                ..
+            })) = nested
+            else {
+                return Err(syn::Error::new_spanned(
+                    nested,
+                    "Invalid attribute, should be like tag_encoding = \"u16\" or deny_unknown_fields",
+                ));
+            };
+
+            let set = if path.is_ident("tag_encoding") {
+                let encoding = parse_int_encoding(lit, "BerTag")?;
+                container.tag_encoding.replace(encoding).is_none()
+            } else if path.is_ident("length_encoding") {
+                let encoding = parse_int_encoding(lit, "BerLength")?;
+                container.length_encoding.replace(encoding).is_none()
+            } else if path.is_ident("byte_order") {
+                let order = parse_byte_order(lit)?;
+                container.byte_order.replace(order).is_none()
+            } else if path.is_ident("bound") {
+                let bound = lit.parse_with(
+                    Punctuated::<syn::WherePredicate, syn::Token![,]>::parse_terminated,
+                )?;
+                container.bound.replace(bound).is_none()
+            } else if path.is_ident("mode") {
+                container.mode = match lit.value().as_str() {
+                    "exact" => Mode::Exact,
+                    "stream" => Mode::Stream,
+                    "bolt" => Mode::Bolt,
+                    "ber" => Mode::Ber,
+                    "der" => Mode::Der,
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            lit,
+                            "Invalid mode, expected \"exact\", \"stream\", \"bolt\", \"ber\" or \"der\"",
+                        ))
+                    }
+                };
+                mode.replace(lit.clone()).is_none()
+            } else if path.is_ident("order") {
+                let value = match lit.value().as_str() {
+                    "declaration" => Order::Declaration,
+                    "tag" => Order::Tag,
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            lit,
+                            "Invalid order, expected \"declaration\" or \"tag\"",
+                        ))
+                    }
+                };
+                order.replace((value, lit.clone())).is_none()
+            } else if path.is_ident("crate") {
+                container.krate.replace(lit.parse()?).is_none()
+            } else {
+                return Err(syn::Error::new_spanned(
+                    path,
+                    "Invalid attribute: only 'tag_encoding', 'length_encoding', 'byte_order', 'mode', 'order', 'deny_unknown_fields', 'bound' and 'crate' are valid",
+                ));
+            };
+
+            if !set {
+                return Err(syn::Error::new_spanned(
+                    nested,
+                    "Each attribute can only be given once",
+                ));
+            }
+        }
+    }
+
+    // a BOLT #1 stream is in order of tag, DER in the order of the fields
+    container.order = match (&container.mode, order) {
+        (Mode::Bolt, Some((Order::Declaration, lit))) => {
+            return Err(syn::Error::new_spanned(
+                lit,
+                "mode = \"bolt\" writes the records in order of tag",
+            ))
+        }
+        (Mode::Der, Some((Order::Tag, lit))) => {
+            return Err(syn::Error::new_spanned(
+                lit,
+                "mode = \"der\" writes the records in the order of the fields",
+            ))
+        }
+        (Mode::Bolt, _) => Order::Tag,
+        (_, order) => order.map_or(Order::Declaration, |(order, _)| order),
+    };
+
+    // only the modes skipping unknown tags have something to deny
+    if let Some(deny) = deny {
+        if matches!(input.data, syn::Data::Enum(_)) {
+            return Err(syn::Error::new_spanned(
+                deny,
+                "deny_unknown_fields only applies to structs, an enum without a #[tlv(other)] variant rejects unknown tags already",
+            ));
+        }
+        if matches!(container.mode, Mode::Exact | Mode::Der) {
+            return Err(syn::Error::new_spanned(
+                deny,
+                "deny_unknown_fields needs mode = \"stream\", \"bolt\" or \"ber\", the other modes reject unknown tags already",
+            ));
+        }
+    }
+
+    // the profiles fix the layout: a BOLT #1 stream has BigSize types and
+    // lengths, BER-TLV and DER their own tags and lengths, all big-endian
+    // values
+    let profile = match container.mode {
+        Mode::Bolt => Some(("bolt", "BigSize", "BigSize")),
+        Mode::Ber => Some(("ber", "BerTag", "BerLength")),
+        Mode::Der => Some(("der", "BerTag", "BerLength")),
+        Mode::Exact | Mode::Stream => None,
+    };
+    if let (Some((name, tag, length)), Some(lit)) = (profile, mode) {
+        if container.tag_encoding.is_some()
+            || container.length_encoding.is_some()
+            || container.byte_order.is_some()
+        {
+            return Err(syn::Error::new_spanned(
+                lit,
+                format!("mode = \"{name}\" cannot be combined with 'tag_encoding', 'length_encoding' or 'byte_order'"),
+            ));
+        }
+
+        container.tag_encoding = Some(Ident::new(tag, lit.span()));
+        container.length_encoding = Some(Ident::new(length, lit.span()));
+        container.byte_order = Some(Ident::new("Big", lit.span()));
+    }
+
+    Ok(container)
+}
+
+/// Parses an encoding of `tlv::IntEncoding`, "ber" being `ber`, the BER
+/// encoding of tags or of lengths.
+fn parse_int_encoding(lit: &LitStr, ber: &str) -> Result<Ident, syn::Error> {
+    let variant = match lit.value().as_str() {
+        "u8" => "U8",
+        "u16" => "U16",
+        "u32" => "U32",
+        "u64" => "U64",
+        "leb128" => "Leb128",
+        "bigsize" => "BigSize",
+        "quic" => "Quic",
+        "ber" => ber,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                lit,
+                "Invalid encoding, expected one of \"u8\", \"u16\", \"u32\", \"u64\", \"leb128\", \"bigsize\", \"quic\", \"ber\"",
+            ))
+        }
+    };
+
+    Ok(Ident::new(variant, lit.span()))
+}
+
+fn parse_byte_order(lit: &LitStr) -> Result<Ident, syn::Error> {
+    let variant = match lit.value().as_str() {
+        "little" => "Little",
+        "big" => "Big",
+        _ => {
+            return Err(syn::Error::new_spanned(
+                lit,
+                "Invalid byte order, expected \"little\" or \"big\"",
             ))
         }
+    };
+
+    Ok(Ident::new(variant, lit.span()))
+}
+
+/// How the fields of a container are laid out as records.
+#[derive(Default, PartialEq, Eq)]
+pub enum Mode {
+    /// Exactly one record per field, decoded with `tlv::extract_raw`.
+    #[default]
+    Exact,
+    /// Records until the end of the input, in any order and skipping unknown
+    /// tags, decoded with `tlv::extract_stream`.
+    Stream,
+    /// A BOLT #1 TLV stream, decoded with `tlv::extract_bolt`: records in
+    /// increasing order of tag, `None` fields left out and byte strings
+    /// written without their length prefix.
+    Bolt,
+    /// A BER-TLV template, decoded with `tlv::extract_stream`: records in any
+    /// order, `None` fields left out and byte strings written without their
+    /// length prefix.
+    Ber,
+    /// An ASN.1 DER `SEQUENCE`, decoded with `tlv::extract_der`: records in
+    /// the order of the fields, tagged by the types of the fields unless
+    /// tagged `implicit` or `explicit`, and `None` fields left out.
+    Der,
+}
+
+/// The order the records of the fields of a container are written in.
+#[derive(Default, PartialEq, Eq)]
+pub enum Order {
+    /// The order the fields are declared in.
+    #[default]
+    Declaration,
+    /// Increasing order of tag.
+    Tag,
+}
+
+impl Mode {
+    /// Whether the mode is the profile of a protocol, where `None` fields
+    /// have no record and byte strings are the whole value of theirs.
+    pub fn is_profile(&self) -> bool {
+        matches!(self, Mode::Bolt | Mode::Ber)
+    }
+}
+
+/// Attributes for a TLV container (struct or enum).
+///
+/// Specified using the `#[tlv()]` macro attribute on the type itself.
+#[derive(Default)]
+pub struct TlvContainerAttributes {
+    /// Variant of `tlv::IntEncoding` used for the tags of the fields.
+    pub tag_encoding: Option<Ident>,
+
+    /// Variant of `tlv::IntEncoding` used for the lengths of the fields.
+    pub length_encoding: Option<Ident>,
+
+    /// Variant of `tlv::ByteOrder` of the records and of the values of the
+    /// container, whatever the order of the encode or decode.
+    pub byte_order: Option<Ident>,
+
+    /// How the records of the fields are read and written.
+    pub mode: Mode,
+
+    /// The order the records of the fields are written in.
+    pub order: Order,
+
+    /// Whether records with unknown tags are an error rather than skipped.
+    pub deny_unknown_fields: bool,
+
+    /// Where predicates replacing the bounds the derives add to the type
+    /// parameters.
+    pub bound: Option<Punctuated<syn::WherePredicate, syn::Token![,]>>,
+
+    /// Path of the `tlv` crate, for crates re-exporting it.
+    pub krate: Option<syn::Path>,
+}
+
+impl TlvContainerAttributes {
+    /// Expression evaluating to the `tlv::Layout` of the container.
+    pub fn layout(&self) -> TokenStream {
+        let mut layout = quote! { __tlv::Layout::DEFAULT };
+
+        if let Some(encoding) = &self.tag_encoding {
+            layout = quote! { #layout.with_tag(__tlv::IntEncoding::#encoding) };
+        }
+        if let Some(encoding) = &self.length_encoding {
+            layout = quote! { #layout.with_length(__tlv::IntEncoding::#encoding) };
+        }
+        if let Some(order) = &self.byte_order {
+            layout = quote! { #layout.with_byte_order(__tlv::ByteOrder::#order) };
+        }
+
+        layout
+    }
+
+    /// Statement shadowing the `config` of an encode with one in the byte
+    /// order of the container, if it has one.
+    pub fn config_byte_order(&self) -> TokenStream {
+        match &self.byte_order {
+            Some(order) => quote! {
+                let config = &config.with_byte_order(__tlv::ByteOrder::#order);
+            },
+            None => quote! {},
+        }
+    }
+
+    /// Statement shadowing the `limiter` of a decode with one in the byte
+    /// order of the container, if it has one. The copy starts at the same
+    /// depth, and every level it enters is left before it is dropped.
+    pub fn limiter_byte_order(&self) -> TokenStream {
+        match &self.byte_order {
+            Some(order) => quote! {
+                let limiter = &mut limiter.clone().with_byte_order(__tlv::ByteOrder::#order);
+            },
+            None => quote! {},
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The error of parsing the tags of the fields or the variants of `input`.
+    fn tag_error(input: syn::DeriveInput) -> String {
+        let container = parse_tlv_container_attributes(&input).unwrap();
+        let err = match input.data {
+            syn::Data::Struct(data) => match data.fields {
+                syn::Fields::Named(fields) => {
+                    parse_tlv_fields_attributes(&container, &fields.named).map(drop)
+                }
+                syn::Fields::Unnamed(fields) => {
+                    parse_tlv_fields_attributes(&container, &fields.unnamed).map(drop)
+                }
+                syn::Fields::Unit => Ok(()),
+            },
+            syn::Data::Enum(data) => {
+                parse_tlv_variants_attributes(&container, &data.variants).map(drop)
+            }
+            syn::Data::Union(_) => Ok(()),
+        };
+        err.unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_missing_tag() {
+        let err = tag_error(syn::parse_quote! {
+            struct Message {
+                #[tlv(tag = 1)]
+                id: u32,
+                name: String,
+                #[tlv(tag = 2)]
+                flag: bool,
+            }
+        });
+        assert_eq!(err, "Field must have a #[tlv(tag = ...)] attribute");
+
+        let err = tag_error(syn::parse_quote! {
+            struct Pair(#[tlv(tag = 1)] u8, u8);
+        });
+        assert_eq!(err, "Field must have a #[tlv(tag = ...)] attribute");
+    }
+
+    #[test]
+    fn test_duplicate_tags() {
+        let err = tag_error(syn::parse_quote! {
+            struct Message {
+                #[tlv(tag = 1)]
+                id: u32,
+                #[tlv(tag = 0x01)]
+                name: String,
+            }
+        });
+        assert_eq!(err, "Duplicate tag 1");
+
+        let err = tag_error(syn::parse_quote! {
+            enum Command {
+                #[tlv(tag = 2)]
+                Ping,
+                #[tlv(tag = 2)]
+                Pong,
+            }
+        });
+        assert_eq!(err, "Duplicate tag 2");
+
+        let err = tag_error(syn::parse_quote! {
+            struct Message {
+                #[tlv(tag = 1, tag = 2)]
+                id: u32,
+            }
+        });
+        assert_eq!(err, "Each attribute can only be given once");
+
+        let err = tag_error(syn::parse_quote! {
+            enum Command {
+                #[tlv(tag = 1, tag = 2)]
+                Ping,
+            }
+        });
+        assert_eq!(err, "Each attribute can only be given once");
+    }
+
+    #[test]
+    fn test_unknown_field_errors() {
+        let input: syn::DeriveInput = syn::parse_quote! {
+            #[tlv(mode = "stream")]
+            struct Relay {
+                #[tlv(tag = 1)]
+                id: u32,
+                #[tlv(unknown = [1])]
+                unknown: Vec<RawField>,
+            }
+        };
+        let container = parse_tlv_container_attributes(&input).unwrap();
+        let syn::Data::Struct(data) = input.data else {
+            unreachable!()
+        };
+
+        let err = parse_unknown_field(&container, &data.fields.iter().cloned().collect())
+            .map(drop)
+            .unwrap_err();
+        assert_eq!(err.to_string(), "expected literal");
+    }
+
+    #[test]
+    fn test_field_attributes() {
+        let err = tag_error(syn::parse_quote! {
+            struct Message {
+                #[tlv(tag = 1, skip)]
+                id: u32,
+            }
+        });
+        assert!(err.starts_with("A skipped field has no record"));
+
+        let err = tag_error(syn::parse_quote! {
+            #[tlv(mode = "stream")]
+            struct Message {
+                #[tlv(tag = 1, default, required)]
+                id: u32,
+            }
+        });
+        assert_eq!(err, "A field is either required or has a default, not both");
+
+        // in exact mode every record is present
+        let err = tag_error(syn::parse_quote! {
+            struct Message {
+                #[tlv(tag = 1, default = "one")]
+                id: u32,
+            }
+        });
+        assert!(err.starts_with("#[tlv(default)] needs mode"));
+
+        let err = tag_error(syn::parse_quote! {
+            struct Message {
+                #[tlv(tag = 1, with = "not a path")]
+                id: u32,
+            }
+        });
+        assert_eq!(err, "unexpected token");
+    }
+
+    #[test]
+    fn test_tag_width() {
+        let err = tag_error(syn::parse_quote! {
+            struct Message {
+                #[tlv(tag = 256)]
+                id: u32,
+            }
+        });
+        assert_eq!(
+            err,
+            "Tag 256 does not fit the tag encoding of the type, at most 255"
+        );
+
+        let err = tag_error(syn::parse_quote! {
+            #[tlv(tag_encoding = "u16")]
+            enum Command {
+                #[tlv(tag = 0x10000)]
+                Ping,
+            }
+        });
+        assert_eq!(
+            err,
+            "Tag 65536 does not fit the tag encoding of the type, at most 65535"
+        );
+
+        let err = tag_error(syn::parse_quote! {
+            #[tlv(tag_encoding = "quic")]
+            struct Message {
+                #[tlv(tag = 0x4000_0000_0000_0000)]
+                id: u32,
+            }
+        });
+        assert!(err.starts_with("Tag 4611686018427387904 does not fit"));
+
+        let err = tag_error(syn::parse_quote! {
+            #[tlv(tag_encoding = "u64")]
+            struct Message {
+                #[tlv(tag = 0x1_0000_0000_0000_0000)]
+                id: u32,
+            }
+        });
+        assert_eq!(err, "number too large to fit in target type");
+    }
+
+    #[test]
+    fn test_ber_tags() {
+        let err = tag_error(syn::parse_quote! {
+            #[tlv(mode = "ber")]
+            struct Template {
+                #[tlv(tag = 0x9f)]
+                amount: Vec<u8>,
+            }
+        });
+        assert!(err.starts_with("Tag 0x9f is not a BER tag"));
+
+        let err = tag_error(syn::parse_quote! {
+            #[tlv(mode = "ber")]
+            struct Template {
+                #[tlv(tag = 0x9f8002)]
+                amount: Vec<u8>,
+            }
+        });
+        assert!(err.starts_with("Tag 0x9f8002 is not a BER tag"));
+
+        for reserved in [quote! { 0x00 }, quote! { 0xff01 }] {
+            let err = tag_error(syn::parse_quote! {
+                #[tlv(tag_encoding = "ber")]
+                struct Template {
+                    #[tlv(tag = #reserved)]
+                    padding: Vec<u8>,
+                }
+            });
+            assert!(err.contains("is reserved"), "{err}");
+        }
+    }
+
+    #[test]
+    fn test_container_attributes() {
+        let container_error = |input: syn::DeriveInput| match parse_tlv_container_attributes(&input)
+        {
+            Ok(_) => panic!("no error"),
+            Err(err) => err.to_string(),
+        };
+
+        let err = container_error(syn::parse_quote! {
+            #[tlv(mode = "bolt", order = "declaration")]
+            struct Payload;
+        });
+        assert_eq!(err, "mode = \"bolt\" writes the records in order of tag");
+
+        let err = container_error(syn::parse_quote! {
+            #[tlv(order = "tag", mode = "der")]
+            struct Extension;
+        });
+        assert_eq!(
+            err,
+            "mode = \"der\" writes the records in the order of the fields"
+        );
+
+        let err = container_error(syn::parse_quote! {
+            #[tlv(order = "size")]
+            struct Message;
+        });
+        assert_eq!(err, "Invalid order, expected \"declaration\" or \"tag\"");
+
+        // within one attribute or across several
+        for input in [
+            syn::parse_quote! {
+                #[tlv(mode = "stream", mode = "bolt")]
+                struct Message;
+            },
+            syn::parse_quote! {
+                #[tlv(byte_order = "big")]
+                #[tlv(byte_order = "little")]
+                struct Message;
+            },
+            syn::parse_quote! {
+                #[tlv(deny_unknown_fields, mode = "stream", deny_unknown_fields)]
+                struct Message;
+            },
+        ] {
+            assert_eq!(
+                container_error(input),
+                "Each attribute can only be given once"
+            );
+        }
+
+        // deny_unknown_fields where unknown tags are rejected anyway
+        for input in [
+            syn::parse_quote! {
+                #[tlv(deny_unknown_fields)]
+                struct Message;
+            },
+            syn::parse_quote! {
+                #[tlv(mode = "der", deny_unknown_fields)]
+                struct Extension;
+            },
+        ] {
+            let err = container_error(input);
+            assert!(err.starts_with("deny_unknown_fields needs mode"), "{err}");
+        }
+        let err = container_error(syn::parse_quote! {
+            #[tlv(mode = "stream", deny_unknown_fields)]
+            enum Command {}
+        });
+        assert!(err.starts_with("deny_unknown_fields only applies to structs"));
+
+        let input: syn::DeriveInput = syn::parse_quote! {
+            #[tlv(mode = "stream", deny_unknown_fields, crate = "reexport::tlv")]
+            struct Message {
+                #[tlv(unknown)]
+                unknown: Vec<RawField>,
+            }
+        };
+        let container = parse_tlv_container_attributes(&input).unwrap();
+        assert!(container.order == Order::Declaration);
+        let krate = container.krate.as_ref().unwrap();
+        assert_eq!(quote! { #krate }.to_string(), "reexport :: tlv");
+
+        let syn::Data::Struct(data) = input.data else {
+            unreachable!()
+        };
+        let syn::Fields::Named(fields) = data.fields else {
+            unreachable!()
+        };
+        let err = match parse_unknown_field(&container, &fields.named) {
+            Ok(_) => panic!("no error"),
+            Err(err) => err.to_string(),
+        };
+        assert_eq!(
+            err,
+            "#[tlv(unknown)] keeps the records deny_unknown_fields rejects"
+        );
     }
 }
@@ -1,176 +1,633 @@
-use proc_macro2::{Ident, TokenStream};
+use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{punctuated::Punctuated, spanned::Spanned, Data, DeriveInput, Field, Fields, Token};
+use syn::{punctuated::Punctuated, Data, DeriveInput, Field, Fields, Token};
 
 use crate::{
-    attributes::{parse_tlv_fields_attributes, TlvFieldAttributes},
-    utils::{ty_is_option, ty_is_vec_u8},
+    attributes::{
+        parse_der_fields_attributes, parse_tlv_container_attributes, parse_tlv_fields_attributes,
+        parse_tlv_variants_attributes, parse_unknown_field, FieldDefault, Mode,
+        TlvContainerAttributes, TlvFieldAttributes,
+    },
+    utils::{
+        field_member, fields_are_transparent, impl_async_header, impl_header, import_crate,
+        member_binding, member_name, ty_is_option, ty_is_vec_u8, ty_option_inner,
+    },
 };
 
+/// What the generated code decodes from: any reader for `Deserialize`, a
+/// slice it may borrow from for `BorrowDeserialize`, or an asynchronous
+/// source for `AsyncDeserialize`.
+enum Flavor {
+    Owned,
+    Borrowed(syn::Lifetime),
+    Async,
+}
+
+impl Flavor {
+    fn trait_path(&self) -> TokenStream {
+        match self {
+            Flavor::Owned => quote! { __tlv::Deserialize },
+            Flavor::Borrowed(lifetime) => quote! { __tlv::BorrowDeserialize<#lifetime> },
+            Flavor::Async => quote! { __tlv::AsyncDeserialize },
+        }
+    }
+
+    /// Methods of the trait implemented in terms of the one the generated
+    /// code defines, which also has the `limiter` of the decode.
+    fn entry(&self) -> TokenStream {
+        match self {
+            Flavor::Owned => quote! {
+                fn deserialize<__R: __tlv::io::Read>(reader: &mut __R) -> __tlv::Result<Self> {
+                    __tlv::deserialize_with_limits(reader, __tlv::DecodeLimits::DEFAULT)
+                }
+            },
+            Flavor::Borrowed(lifetime) => quote! {
+                fn borrow_deserialize(input: &mut &#lifetime [u8]) -> __tlv::Result<Self> {
+                    Self::borrow_deserialize_limited(input, &mut __tlv::Limiter::default())
+                }
+            },
+            Flavor::Async => quote! {
+                async fn deserialize_async<__R>(reader: &mut __R) -> __tlv::Result<Self>
+                where
+                    __R: __tlv::AsyncSource + ?Sized,
+                {
+                    __tlv::deserialize_with_limits_async(reader, __tlv::DecodeLimits::DEFAULT).await
+                }
+            },
+        }
+    }
+
+    /// Signature of the trait method the generated code defines, `reader`
+    /// and `limiter` are the names of its arguments.
+    fn signature(&self, reader: TokenStream, limiter: TokenStream) -> TokenStream {
+        match self {
+            Flavor::Owned => quote! {
+                fn deserialize_limited<__R: __tlv::io::Read>(
+                    #reader: &mut __R,
+                    #limiter: &mut __tlv::Limiter,
+                ) -> __tlv::Result<Self>
+            },
+            Flavor::Borrowed(lifetime) => quote! {
+                fn borrow_deserialize_limited(
+                    #reader: &mut &#lifetime [u8],
+                    #limiter: &mut __tlv::Limiter,
+                ) -> __tlv::Result<Self>
+            },
+            Flavor::Async => quote! {
+                async fn deserialize_async_limited<__R>(
+                    #reader: &mut __R,
+                    #limiter: &mut __tlv::Limiter,
+                ) -> __tlv::Result<Self>
+                where
+                    __R: __tlv::AsyncSource + ?Sized,
+            },
+        }
+    }
+
+    /// Call decoding a value of type `ty` from `reader`.
+    fn decode(&self, ty: &syn::Type, reader: TokenStream) -> TokenStream {
+        let trait_path = self.trait_path();
+        match self {
+            Flavor::Owned => quote! { <#ty as #trait_path>::deserialize_limited(#reader, limiter) },
+            Flavor::Borrowed(_) => {
+                quote! { <#ty as #trait_path>::borrow_deserialize_limited(#reader, limiter) }
+            }
+            Flavor::Async => {
+                quote! { <#ty as #trait_path>::deserialize_async_limited(#reader, limiter).await }
+            }
+        }
+    }
+
+    /// Call extracting records, `name` is the suffix shared by the owned
+    /// `extract_*`, the borrowed `borrow_*` and the asynchronous
+    /// `extract_*_async` functions. The asynchronous ones are also given the
+    /// `limiter` of the decode, which they check the length of each record
+    /// against before reading it.
+    fn extract(&self, name: &str, args: TokenStream) -> TokenStream {
+        match self {
+            Flavor::Owned => {
+                let ident = quote::format_ident!("extract_{}", name);
+                quote! { __tlv::#ident(#args) }
+            }
+            Flavor::Borrowed(_) => {
+                let ident = quote::format_ident!("borrow_{}", name);
+                quote! { __tlv::#ident(#args) }
+            }
+            Flavor::Async => {
+                let ident = quote::format_ident!("extract_{}_async", name);
+                quote! { __tlv::#ident(#args, limiter).await }
+            }
+        }
+    }
+
+    /// Flavor decoding the value of a record once it has been read. The
+    /// asynchronous flavor reads whole records, whose values are then
+    /// decoded like owned ones.
+    fn record(&self) -> &Flavor {
+        match self {
+            Flavor::Async => &Flavor::Owned,
+            _ => self,
+        }
+    }
+
+    /// Type of the slice holding the value of a record.
+    fn slice(&self) -> TokenStream {
+        match self {
+            Flavor::Owned | Flavor::Async => quote! { &[u8] },
+            Flavor::Borrowed(lifetime) => quote! { &#lifetime [u8] },
+        }
+    }
+}
+
 pub fn tlv_deserialize_derive_impl(input: DeriveInput) -> Result<TokenStream, syn::Error> {
-    // Get the name of the struct
-    let struct_name = &input.ident;
+    let container = parse_tlv_container_attributes(&input)?;
+    let owned = derive(input.clone(), Flavor::Owned, None)?;
+    let asynchronous = derive(input, Flavor::Async, None)?;
 
-    // Get the fields of the struct
-    match input.data {
-        Data::Struct(ref data) => match data.fields {
-            Fields::Named(ref fields) => impl_for_struct(struct_name, fields.named.clone()),
-            _ => unimplemented!(),
+    // the asynchronous impl only exists when `tlv` has an async runtime
+    Ok(import_crate(
+        &container,
+        quote! {
+            #owned
+            __tlv::__async! { #asynchronous }
         },
-        _ => unimplemented!(),
-    }
+    ))
 }
 
-fn impl_for_struct(
-    struct_name: &Ident,
-    fields: Punctuated<Field, Token![,]>,
-) -> Result<TokenStream, syn::Error> {
-    let attributes = parse_tlv_fields_attributes(&fields)?;
+pub fn tlv_borrow_deserialize_derive_impl(input: DeriveInput) -> Result<TokenStream, syn::Error> {
+    let mut lifetimes = input.generics.lifetimes();
+    let lifetime = match (lifetimes.next(), lifetimes.next()) {
+        (None, _) => None,
+        (Some(def), None) => Some(def.lifetime.clone()),
+        (Some(_), Some(def)) => {
+            return Err(syn::Error::new_spanned(
+                def,
+                "BorrowDeserialize can only be derived for types with at most one lifetime",
+            ))
+        }
+    };
 
-    // Generate code for deserialization
-    let mut deserialization_code = Vec::new();
-    let mut expected_tags = Vec::new();
-    let mut field_extraction = Vec::new();
+    // a type that does not borrow anything can still be decoded from any
+    // input, with a lifetime of its own
+    let container = parse_tlv_container_attributes(&input)?;
+    let borrowed = match lifetime {
+        Some(lifetime) => derive(input, Flavor::Borrowed(lifetime), None)?,
+        None => {
+            let lifetime: syn::Lifetime = syn::parse_quote! { '__de };
+            derive(input, Flavor::Borrowed(lifetime.clone()), Some(lifetime))?
+        }
+    };
 
-    for (field, attributes) in fields.iter().zip(attributes.iter()) {
-        let tag = &attributes.tag;
-        let ident = &field.ident;
+    Ok(import_crate(&container, borrowed))
+}
 
-        let field_deserialization = create_deserializer_for_field(field, attributes)?;
+/// `extra_lifetime` is a lifetime the impl introduces on top of the generics
+/// of the type.
+fn derive(
+    input: DeriveInput,
+    flavor: Flavor,
+    extra_lifetime: Option<syn::Lifetime>,
+) -> Result<TokenStream, syn::Error> {
+    let container = parse_tlv_container_attributes(&input)?;
+    let header = match flavor {
+        Flavor::Async => impl_async_header(&input, &container, flavor.trait_path()),
+        _ => impl_header(
+            &input,
+            &container,
+            flavor.trait_path(),
+            extra_lifetime.as_ref(),
+        ),
+    };
 
-        expected_tags.push(tag);
-        deserialization_code.push(quote! {
-            #field_deserialization
-        });
-        field_extraction.push(quote! {
-            #ident,
-        });
+    // Get the fields of the struct
+    match input.data {
+        Data::Struct(ref data) => {
+            match data.fields {
+                Fields::Named(ref fields) => {
+                    impl_for_struct(&header, &flavor, &container, &fields.named)
+                }
+                Fields::Unnamed(ref fields) if fields_are_transparent(fields) => Ok(
+                    impl_for_newtype(&header, &flavor, &container, &fields.unnamed[0].ty),
+                ),
+                Fields::Unnamed(ref fields) => {
+                    impl_for_struct(&header, &flavor, &container, &fields.unnamed)
+                }
+                Fields::Unit => Ok(impl_for_unit(&header, &flavor)),
+            }
+        }
+        Data::Enum(_) if container.mode == Mode::Der => Err(syn::Error::new_spanned(
+            &input,
+            "mode = \"der\" is only supported on structs",
+        )),
+        Data::Enum(ref data) => impl_for_enum(&header, &flavor, &container, data),
+        Data::Union(_) => Err(syn::Error::new_spanned(
+            &input,
+            "Deserialize cannot be derived for unions",
+        )),
     }
+}
 
-    let expected_tags_len = expected_tags.len();
-
-    let expected_tags = quote! { [ #( #expected_tags ),* ] };
+fn impl_for_struct(
+    header: &TokenStream,
+    flavor: &Flavor,
+    container: &TlvContainerAttributes,
+    fields: &Punctuated<Field, Token![,]>,
+) -> Result<TokenStream, syn::Error> {
+    let layout = container.layout();
+    let entry = flavor.entry();
+    let signature = flavor.signature(quote! { reader }, quote! { limiter });
+    let byte_order = container.limiter_byte_order();
+    let deserialization_code = create_deserializers_for_fields(flavor, container, fields)?;
+    let field_extraction = create_field_extraction(fields);
 
     // Generate the code for the implementation
     let gen = quote! {
-        impl ::tlv::Deserialize for #struct_name {
-            fn deserialize<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
-                const EXPECTED_TAGS: [u8; #expected_tags_len] = #expected_tags;
+        #header {
+            #entry
 
-                let fields = ::tlv::extract_raw(reader, EXPECTED_TAGS)?;
+            #signature {
+                const LAYOUT: __tlv::Layout = #layout;
+                #byte_order
+                let layout = LAYOUT.with_byte_order(limiter.byte_order());
 
-                #(#deserialization_code)*
+                #deserialization_code
 
                 Ok(Self {
-                    #(#field_extraction)*
+                    #(#field_extraction,)*
                 })
             }
         }
     };
 
     // Return the generated implementation
-    Ok(gen.into())
+    Ok(gen)
 }
 
-fn create_deserializer_for_field(
-    field: &Field,
-    attribute: &TlvFieldAttributes,
-) -> Result<TokenStream, syn::Error> {
-    let tag = &attribute.tag;
-    let name = &field
-        .ident
-        .as_ref()
-        .ok_or_else(|| syn::Error::new(field.span(), "missing field name"))?;
-    let field_ty = &field.ty;
+fn impl_for_newtype(
+    header: &TokenStream,
+    flavor: &Flavor,
+    container: &TlvContainerAttributes,
+    ty: &syn::Type,
+) -> TokenStream {
+    let entry = flavor.entry();
+    let signature = flavor.signature(quote! { reader }, quote! { limiter });
+    let byte_order = container.limiter_byte_order();
+    let decode = flavor.decode(ty, quote! { reader });
 
-    let kind = DeserializerKind::from(field);
+    quote! {
+        #header {
+            #entry
 
-    match kind {
-        DeserializerKind::Bytes => gen_bytes_deserializer(tag, name, field_ty),
-        DeserializerKind::Optional => gen_optional_deserializer(tag, name, field_ty),
-        DeserializerKind::Regular => gen_regular_deserializer(tag, name, field_ty),
+            #signature {
+                #byte_order
+                Ok(Self(#decode?))
+            }
+        }
     }
 }
 
-fn gen_bytes_deserializer(
-    tag: &syn::LitInt,
-    name: &Ident,
-    field_ty: &syn::Type,
-) -> Result<TokenStream, syn::Error> {
-    Ok(quote! {
-        let #name: #field_ty = fields
-            .get(&#tag)
-            .map(|field| ::tlv::deserialize_bytes(
-                &mut ::std::io::Cursor::new(field.value.as_slice())
-            ))
-            .transpose()?
-            .unwrap_or_else(|| Vec::new());
+fn impl_for_unit(header: &TokenStream, flavor: &Flavor) -> TokenStream {
+    let entry = flavor.entry();
+    let signature = flavor.signature(quote! { _reader }, quote! { _limiter });
+
+    quote! {
+        #header {
+            #entry
+
+            #signature {
+                Ok(Self)
+            }
+        }
     }
-    .into())
 }
 
-fn gen_optional_deserializer(
-    tag: &syn::LitInt,
-    name: &Ident,
-    field_ty: &syn::Type,
+/// A single record is read, its tag selects the variant and its value is
+/// decoded as the payload of the variant.
+fn impl_for_enum(
+    header: &TokenStream,
+    flavor: &Flavor,
+    container: &TlvContainerAttributes,
+    data: &syn::DataEnum,
 ) -> Result<TokenStream, syn::Error> {
-    Ok(quote! {
-        let #name: #field_ty = fields
-            .get(&#tag)
-            .map(|field| ::tlv::Deserialize::deserialize(
-                &mut std::io::Cursor::new(field.value.as_slice())
-            ))
-            .transpose()?;
+    let layout = container.layout();
+
+    let mut arms = Vec::new();
+    let mut fallback = quote! {
+        _ => Err(__tlv::Error::new(__tlv::ErrorKind::UnexpectedTag)
+            .with_tag(record.id)
+            .with_offset(record.offset)),
+    };
+
+    let attributes = parse_tlv_variants_attributes(container, &data.variants)?;
+    for (variant, attributes) in data.variants.iter().zip(attributes) {
+        let ident = &variant.ident;
+        let ident_str = ident.to_string();
+
+        let Some(tag) = attributes.tag else {
+            fallback = quote! {
+                _ => Ok(Self::#ident(::core::convert::From::from(record))),
+            };
+            continue;
+        };
+
+        let arm = match &variant.fields {
+            Fields::Unit => quote! {
+                #tag => {
+                    record.decode_limited::<[u8; 0]>(#ident_str, limiter)?;
+                    Ok(Self::#ident)
+                }
+            },
+            Fields::Unnamed(fields) if fields.unnamed.len() == 1 => quote! {
+                #tag => Ok(Self::#ident(record.decode_limited(#ident_str, limiter)?)),
+            },
+            Fields::Named(fields) => {
+                let deserialization_code =
+                    create_deserializers_for_fields(flavor.record(), container, &fields.named)?;
+                let slice = flavor.slice();
+                let field_extraction = create_field_extraction(&fields.named);
+
+                quote! {
+                    #tag => {
+                        let decode = |reader: &mut #slice,
+                                      limiter: &mut __tlv::Limiter|
+                         -> __tlv::Result<Self> {
+                            #deserialization_code
+
+                            if !reader.is_empty() {
+                                return Err(__tlv::Error::new(__tlv::ErrorKind::LengthMismatch));
+                            }
+
+                            Ok(Self::#ident {
+                                #(#field_extraction,)*
+                            })
+                        };
+
+                        limiter
+                            .nested(|limiter| decode(&mut &record.value[..], limiter))
+                            .map_err(|err| err.within(#tag, #ident_str, record.offset))
+                    }
+                }
+            }
+            Fields::Unnamed(fields) => {
+                return Err(syn::Error::new_spanned(
+                    fields,
+                    "Variants with more than one unnamed field are not supported",
+                ))
+            }
+        };
+
+        arms.push(arm);
     }
-    .into())
+
+    let entry = flavor.entry();
+    let signature = flavor.signature(quote! { reader }, quote! { limiter });
+    let byte_order = container.limiter_byte_order();
+    let extract_record = flavor.extract("record", quote! { reader, &layout });
+
+    let gen = quote! {
+        #header {
+            #entry
+
+            #signature {
+                const LAYOUT: __tlv::Layout = #layout;
+                #byte_order
+                let layout = LAYOUT.with_byte_order(limiter.byte_order());
+
+                let record = #extract_record?;
+
+                match record.id {
+                    #(#arms)*
+                    #fallback
+                }
+            }
+        }
+    };
+
+    Ok(gen)
+}
+
+/// Creates the `member: binding` pairs building the value out of the
+/// variables the fields were decoded into. Tuple structs use their indices
+/// as members, i.e. `Self { 0: field_0 }`.
+fn create_field_extraction(fields: &Punctuated<Field, Token![,]>) -> Vec<TokenStream> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let member = field_member(index, field);
+            let binding = member_binding(&member);
+            quote! { #member: #binding }
+        })
+        .collect()
 }
 
-fn gen_regular_deserializer(
-    tag: &syn::LitInt,
-    name: &Ident,
-    field_ty: &syn::Type,
+/// Creates the code extracting the records of `fields` from `reader` and
+/// binding each decoded field to a variable named after it.
+fn create_deserializers_for_fields(
+    flavor: &Flavor,
+    container: &TlvContainerAttributes,
+    fields: &Punctuated<Field, Token![,]>,
 ) -> Result<TokenStream, syn::Error> {
-    let error_msg = format!("missing {} field", name);
+    let unknown = parse_unknown_field(container, fields)?;
+    let (stream, bolt) = match (unknown.is_some(), container.deny_unknown_fields) {
+        (true, _) => ("stream_with_unknown", "bolt_with_unknown"),
+        // the first record with an unknown tag is an error, before its
+        // value is read
+        (false, true) => ("stream_deny_unknown", "bolt_deny_unknown"),
+        (false, false) => ("stream", "bolt"),
+    };
 
-    Ok(quote! {
-        let #name: #field_ty = fields
-            .get(&#tag)
-            .map(|field| ::tlv::Deserialize::deserialize(
-                &mut std::io::Cursor::new(field.value.as_slice())
-            ))
-            .ok_or_else(|| std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                #error_msg,
-            ))??;
+    let extract = match container.mode {
+        Mode::Exact => {
+            let extract_raw = flavor.extract("raw", quote! { reader, &layout, EXPECTED_TAGS });
+            quote! { #extract_raw? }
+        }
+        Mode::Stream | Mode::Ber => {
+            let extract_stream = flavor.extract(stream, quote! { reader, &layout, &EXPECTED_TAGS });
+            quote! { #extract_stream? }
+        }
+        Mode::Bolt => {
+            let extract_bolt = flavor.extract(bolt, quote! { reader, &layout, &EXPECTED_TAGS });
+            quote! { #extract_bolt? }
+        }
+        Mode::Der => return create_der_deserializers_for_fields(flavor, fields),
+    };
+
+    // the records no field takes go into the #[tlv(unknown)] field, if any
+    let extract = match unknown {
+        Some((index, field)) => {
+            let binding = member_binding(&field_member(index, field));
+            let ty = &field.ty;
+            quote! {
+                let extracted = #extract;
+                let fields = extracted.fields;
+                let mut #binding: #ty = ::core::default::Default::default();
+                for record in extracted.unknown {
+                    __tlv::UnknownRecords::insert(
+                        &mut #binding,
+                        ::core::convert::From::from(record),
+                    )?;
+                }
+            }
+        }
+        None if container.mode == Mode::Exact => quote! { let fields = #extract; },
+        None => quote! { let fields = #extract.fields; },
+    };
+
+    let attributes = parse_tlv_fields_attributes(container, fields)?;
+
+    // Generate code for deserialization
+    let mut deserialization_code = Vec::new();
+    let mut expected_tags = Vec::new();
+
+    for (index, field, attributes) in attributes.iter() {
+        expected_tags.extend(&attributes.tag);
+        deserialization_code.push(create_deserializer_for_field(
+            container,
+            &field_member(*index, field),
+            field,
+            attributes,
+        ));
     }
-    .into())
+
+    let expected_tags_len = expected_tags.len();
+
+    let expected_tags = quote! { [ #( #expected_tags ),* ] };
+
+    Ok(quote! {
+        const EXPECTED_TAGS: [u64; #expected_tags_len] = #expected_tags;
+
+        #extract
+
+        #(#deserialization_code)*
+    })
 }
 
-enum DeserializerKind {
-    /// The field is a Vec<u8>, and we need to use the specialized
-    /// `deserialize_bytes` function.
-    Bytes,
+/// Creates the code taking the records of `fields` in order, binding each
+/// decoded field to a variable named after it. A missing record is a `None`
+/// or an error, and records left over are an error.
+fn create_der_deserializers_for_fields(
+    flavor: &Flavor,
+    fields: &Punctuated<Field, Token![,]>,
+) -> Result<TokenStream, syn::Error> {
+    let attributes = parse_der_fields_attributes(fields)?;
+    let mut deserialization_code = Vec::new();
+
+    for ((index, field), attributes) in fields.iter().enumerate().zip(attributes) {
+        let member = field_member(index, field);
+        let name = member_binding(&member);
+        let name_str = member_name(&member);
+        let field_ty = &field.ty;
+        let ty = ty_option_inner(field_ty).unwrap_or(field_ty);
+        let tag = attributes.tag(ty);
+
+        let decode = match attributes.is_explicit() {
+            true => quote! {
+                field
+                    .decode_limited::<__tlv::der::Explicit<#ty>>(#name_str, limiter)
+                    .map(|explicit| explicit.0)
+            },
+            false => quote! { field.decode_limited::<#ty>(#name_str, limiter) },
+        };
+
+        deserialization_code.push(match ty_is_option(field_ty) {
+            true => quote! {
+                let #name: #field_ty = records
+                    .take_tagged(#tag)
+                    .map(|field| #decode)
+                    .transpose()?;
+            },
+            false => quote! {
+                let #name: #field_ty = records
+                    .take_tagged(#tag)
+                    .ok_or_else(|| __tlv::Error::new(__tlv::ErrorKind::MissingTag)
+                        .with_tag(#tag)
+                        .with_field(#name_str))
+                    .and_then(|field| #decode)?;
+            },
+        });
+    }
+
+    let extract_der = flavor.extract("der", quote! { reader, &layout });
 
-    /// The field is optional, and we need to use the `Option` type
-    /// to deserialize it and don't need the return error if the field
-    /// is not present.
-    Optional,
+    Ok(quote! {
+        let mut records = #extract_der?;
+
+        #(#deserialization_code)*
 
-    /// The field is a regular field and we can use the `Deserialize`
-    /// trait to deserialize it.
-    Regular,
+        records.finish()?;
+    })
 }
 
-impl From<&Field> for DeserializerKind {
-    fn from(field: &Field) -> Self {
-        let ty = &field.ty;
+/// Creates the code decoding the record of a field, if present, into a
+/// variable named after the field.
+///
+/// A `Vec<u8>` is read in one go and a `with` module replaces the
+/// `Deserialize` impl of the field. In the profiles a byte string is the
+/// whole value of its record, and the record of an `Option` is the one of
+/// its value. A missing record is an error, unless the field is an `Option`,
+/// a `Vec<u8>` or has a default, which it then decodes to. A skipped field
+/// has no record and always decodes to its default.
+fn create_deserializer_for_field(
+    container: &TlvContainerAttributes,
+    member: &syn::Member,
+    field: &Field,
+    attributes: &TlvFieldAttributes,
+) -> TokenStream {
+    let name = &member_binding(member);
+    let name_str = member_name(member);
+    let field_ty = &field.ty;
+    let profile = container.mode.is_profile();
+
+    let Some(tag) = &attributes.tag else {
+        let default = attributes
+            .default
+            .as_ref()
+            .map_or_else(|| FieldDefault::Trait.value(), FieldDefault::value);
+        return quote! { let #name: #field_ty = #default; };
+    };
+
+    let decode = |ty: &syn::Type| match &attributes.with {
+        Some(with) => quote! { field.decode_with(#name_str, limiter, #with::deserialize) },
+        None if ty_is_vec_u8(ty) && profile => quote! {
+            field.decode_with(#name_str, limiter, |value, limiter| {
+                limiter.check_length(value.len())?;
+                Ok(::core::mem::take(value).to_vec())
+            })
+        },
+        None if ty_is_vec_u8(ty) => quote! {
+            field.decode_with(#name_str, limiter, __tlv::deserialize_bytes_limited)
+        },
+        None => quote! { field.decode_limited::<#ty>(#name_str, limiter) },
+    };
+
+    let present = match ty_option_inner(field_ty) {
+        Some(inner) if profile => {
+            let decode = decode(inner);
+            quote! { Some(#decode?) }
+        }
+        _ => {
+            let decode = decode(field_ty);
+            quote! { #decode? }
+        }
+    };
 
-        if ty_is_vec_u8(ty) {
-            DeserializerKind::Bytes
-        } else if ty_is_option(ty) {
-            DeserializerKind::Optional
-        } else {
-            DeserializerKind::Regular
+    let missing = match &attributes.default {
+        Some(default) => default.value(),
+        None if !attributes.required && (ty_is_option(field_ty) || ty_is_vec_u8(field_ty)) => {
+            quote! { ::core::default::Default::default() }
         }
+        None => quote! {
+            return Err(__tlv::Error::new(__tlv::ErrorKind::MissingTag)
+                .with_tag(#tag)
+                .with_field(#name_str))
+        },
+    };
+
+    quote! {
+        let #name: #field_ty = match fields.get(&#tag) {
+            Some(field) => #present,
+            None => #missing,
+        };
     }
 }
@@ -30,7 +30,136 @@ mod utils;
 ///
 /// This will generate an implementation of the `Serialize` trait for `MyStruct`.
 ///
-/// The generated code will look like this:
+/// Tags are encoded as `u8` by default. A wider tag can be chosen for the whole
+/// type with `#[tlv(tag_encoding = "u16")]` (or `"u32"`, `"u64"`) on the struct,
+/// and the width of the record lengths, `u16` by default, with
+/// `#[tlv(length_encoding = "u32")]`. Both also take the variable-length
+/// encodings `"leb128"`, `"bigsize"` and `"quic"` of `tlv::varint`. The `Deserialize` derive reads the same
+/// attributes, so both sides always agree. A field whose value does not fit into
+/// the length encoding fails to serialize instead of producing a corrupt record.
+/// Integers are little-endian unless the caller asks otherwise, and
+/// `#[tlv(byte_order = "big")]` makes the type and everything it contains
+/// big-endian whatever the caller asks.
+///
+/// Records are written in the order the fields are declared, or in
+/// increasing order of tag with `#[tlv(order = "tag")]`, which decoders do
+/// not require. A crate re-exporting `tlv` lets its users derive the traits
+/// without depending on `tlv` themselves with `#[tlv(crate = "path::to::tlv")]`,
+/// the path the generated code then uses instead of `::tlv`.
+///
+/// Tuple structs work the same way, with a `#[tlv(tag = N)]` on every
+/// position. A tuple struct with a single untagged field, like
+/// `struct UserId(u64);`, is a newtype and is encoded exactly as the value it
+/// wraps, and a unit struct is encoded as zero bytes.
+///
+/// Tags are checked at compile time: every field needs one, no two fields or
+/// variants share one, and each fits the tag encoding of the type, e.g. at
+/// most 255 with the default `u8` tags. BER tags have to be well-formed, and
+/// cannot start with `0x00` or `0xff`, which ISO/IEC 7816-4 reserves for
+/// padding.
+///
+/// Fields take a few more attributes besides their tag:
+///
+/// - `#[tlv(default)]` or `#[tlv(default = "path")]` decodes a missing record
+///   to `Default::default()` or to the value `path()` returns. `Option` and
+///   `Vec<u8>` fields default to `None` and to empty without it. Only the
+///   modes reading records until the end of the input can miss one.
+/// - `#[tlv(skip)]` leaves the field out of the encoding, it takes no tag
+///   and is decoded to its default.
+/// - `#[tlv(with = "module")]` encodes the value of the record with
+///   `module::serialize(&value, writer, config)`,
+///   `module::serialized_length(&value)` and
+///   `module::deserialize(reader, limiter)`, with the signatures of
+///   `serialize_with`, `serialized_length` and `deserialize_limited`. In the
+///   profiles the functions of an `Option` field encode its `Some` value.
+/// - `#[tlv(required)]` makes a missing record an error even for an `Option`
+///   field, and in the profiles a `None` fails to encode.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// use tlv::{Deserialize, Serialize};
+///
+/// mod seconds {
+///     use std::time::Duration;
+///
+///     use tlv::{Config, Deserialize, Limiter, Serialize};
+///
+///     pub fn serialize<W>(value: &Duration, writer: &mut W, config: &Config) -> tlv::Result<usize>
+///     where
+///         W: tlv::io::Write,
+///     {
+///         value.as_secs().serialize_with(writer, config)
+///     }
+///
+///     pub fn serialized_length(_value: &Duration) -> usize {
+///         8
+///     }
+///
+///     pub fn deserialize<R>(reader: &mut R, limiter: &mut Limiter) -> tlv::Result<Duration>
+///     where
+///         R: tlv::io::Read,
+///     {
+///         u64::deserialize_limited(reader, limiter).map(Duration::from_secs)
+///     }
+/// }
+///
+/// #[derive(Serialize, Deserialize)]
+/// #[tlv(mode = "stream")]
+/// struct Session {
+///    #[tlv(tag = 1, with = "seconds")]
+///    timeout: Duration,
+///    #[tlv(tag = 2, default)]
+///    retries: u8,
+///    #[tlv(skip)]
+///    connected: bool,
+/// }
+/// ```
+///
+/// Enums are encoded as a single record, tagged with the tag of the variant,
+/// which every variant declares with `#[tlv(tag = N)]`. The value of a unit
+/// variant is empty, the value of a newtype variant is the encoding of its
+/// field, and a variant with named fields holds one record per field, like a
+/// struct. One newtype variant holding a `tlv::RawField` can be marked with
+/// `#[tlv(other)]` instead of a tag: it receives records with unknown tags,
+/// which are then written back unchanged.
+///
+/// ```
+/// use tlv::{Deserialize, RawField, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// enum Command {
+///    #[tlv(tag = 1)]
+///    Ping,
+///    #[tlv(tag = 2)]
+///    Echo(String),
+///    #[tlv(tag = 3)]
+///    Move {
+///        #[tlv(tag = 1)]
+///        x: u16,
+///    },
+///    #[tlv(other)]
+///    Unknown(RawField),
+/// }
+/// ```
+///
+/// Generic types are supported, every type parameter is required to implement
+/// `Serialize` (`Deserialize` for the other derive). When that is not the
+/// right bound, e.g. because a field is an associated type, the bounds can be
+/// given explicitly with `#[tlv(bound = "T::Id: tlv::Serialize")]`.
+///
+/// `serialized_length` is generated as well and is exact: it adds up the
+/// headers and the `serialized_length` of every field.
+///
+/// With the `tokio` or `futures` feature of `tlv` enabled, `AsyncSerialize`
+/// is derived too. It writes the records of a struct one at a time, so only
+/// the encoding of a single field is held in memory.
+///
+/// The generated code is wrapped in a `const _` block importing `tlv` as
+/// `__tlv`, so it does not depend on the names in scope where the derive is
+/// used. `serialize` writes with the default `tlv::Config`, which
+/// `serialize_with` applies to the layout of the records. It will look like
+/// this:
 ///
 /// ```
 /// struct MyStruct {
@@ -38,30 +167,45 @@ mod utils;
 ///   field2: String,
 /// }
 ///
-/// impl tlv::Serialize for MyStruct {
-///    fn serialize<W>(&self, writer: &mut W) -> std::io::Result<usize>
-///    where
-///        W: std::io::Write,
-///    {
-///        let mut len = 0;
-///
-///        len += ::tlv::Serialize::serialize(&(1 as u16), writer)?;
-///        len += ::tlv::Serialize::serialize(
-///            &::tlv::Serialize::serialized_length(&self.field1),
-///            writer,
-///        )?;
-///        len += self.field1.serialize(writer)?;
-///
-///        len += ::tlv::Serialize::serialize(&(2 as u16), writer)?;
-///        len += ::tlv::Serialize::serialize(
-///            &::tlv::Serialize::serialized_length(&self.field2),
-///            writer,
-///        )?;
-///        len += self.field2.serialize(writer)?;
-///
-///        Ok(len)
-///    }
-/// }
+/// const _: () = {
+///   use ::tlv as __tlv;
+///
+///   impl __tlv::Serialize for MyStruct {
+///     fn serialize<W>(&self, writer: &mut W) -> __tlv::Result<usize>
+///     where
+///       W: __tlv::io::Write,
+///     {
+///       self.serialize_with(writer, &__tlv::Config::DEFAULT)
+///     }
+///
+///     fn serialize_with<W>(&self, writer: &mut W, config: &__tlv::Config) -> __tlv::Result<usize>
+///     where
+///       W: __tlv::io::Write,
+///     {
+///       const LAYOUT: __tlv::Layout = __tlv::Layout::DEFAULT;
+///       let layout = LAYOUT.with_byte_order(config.byte_order);
+///
+///       let mut len = 0;
+///
+///       len += layout
+///         .write_record(writer, 1, &self.field1)
+///         .map_err(|err| err.within(1, "field1", len as u64))?;
+///       len += layout
+///         .write_record(writer, 2, &self.field2)
+///         .map_err(|err| err.within(2, "field2", len as u64))?;
+///
+///       Ok(len)
+///     }
+///
+///     fn serialized_length(&self) -> usize {
+///       const LAYOUT: __tlv::Layout = __tlv::Layout::DEFAULT;
+///
+///       0usize
+///         .saturating_add(LAYOUT.record_length(1, __tlv::Serialize::serialized_length(&self.field1)))
+///         .saturating_add(LAYOUT.record_length(2, __tlv::Serialize::serialized_length(&self.field2)))
+///     }
+///   }
+/// };
 /// ```
 #[proc_macro_derive(Serialize, attributes(tlv))]
 pub fn tlv_serialize_derive(input: TokenStream) -> TokenStream {
@@ -96,8 +240,83 @@ pub fn tlv_serialize_derive(input: TokenStream) -> TokenStream {
 /// ```
 ///
 /// This will generate an implementation of the `Deserialize` trait for `MyStruct`.
+/// As the records are gathered before they are decoded, the generated code
+/// needs the `alloc` feature of `tlv`.
+///
+/// By default exactly one record per field is read, in any order. With
+/// `#[tlv(mode = "stream")]` on the struct, records are read until the end of the
+/// input instead, so optional fields may be left out and records with unknown tags
+/// are skipped. As a struct in this mode has no end of its own, it should only be
+/// decoded at the top level or as the value of another record.
+///
+/// `#[tlv(mode = "bolt")]` reads a Lightning BOLT #1 TLV stream: BigSize tags
+/// and lengths, big-endian values, records in strictly increasing order of tag.
+/// Unknown odd tags are skipped and unknown even ones are an error. `Option`
+/// fields are absent when `None` and `Vec<u8>` fields take the whole value of
+/// their record, without a length of their own. The layout attributes cannot
+/// be combined with this mode.
+///
+/// `#[tlv(mode = "ber")]` reads a BER-TLV template, as used by ISO/IEC 7816-4 and
+/// EMV: BER tags and definite lengths, big-endian values, records in any order
+/// and unknown tags skipped. Tags are written as they appear in specifications,
+/// e.g. `#[tlv(tag = 0x9f02)]`. `Option` and `Vec<u8>` fields are handled as in
+/// `mode = "bolt"`, and constructed records are fields of types deriving the
+/// traits in the same mode. The layout attributes cannot be combined with it
+/// either, `tag_encoding = "ber"` and `length_encoding = "ber"` give the same
+/// layout to the other modes.
+///
+/// `#[tlv(mode = "der")]` reads the contents of an ASN.1 DER `SEQUENCE`. Fields
+/// take no `tag`: each one is tagged by its type, one of the types of
+/// `tlv::der` or of another type in this mode, or in the context-specific class
+/// with `#[tlv(implicit = 0)]` or `#[tlv(explicit = 0)]`. Records come in the
+/// order of the fields, `Option` fields are absent when `None`, and anything
+/// that is not DER fails to decode. The `Serialize` derive also implements
+/// `tlv::der::Tagged`, `SEQUENCE` for a struct and the tag of its field for a
+/// newtype, which a type only deriving `Deserialize` implements itself. Enums
+/// are not supported in this mode, and neither is a `SET` of fields of
+/// different types: a struct is always a `SEQUENCE`, and only the elements of
+/// a `tlv::der::SetOf<T>`, all of the same type, are read as a `SET`.
 ///
-/// The generated code will look like this:
+/// In the stream, BOLT and BER modes, the records that would be skipped can be
+/// kept instead in a field marked `#[tlv(unknown)]`, of type
+/// `Vec<tlv::RawField>` or `BTreeMap<u64, Vec<u8>>`, or any other type
+/// implementing `tlv::UnknownRecords`. The `Serialize` derive writes them back
+/// unchanged after the other records, or in BOLT mode between them in order of
+/// tag, so a type can pass on the records of a newer version of a message.
+/// Records with the tag of another field are left out.
+///
+/// ```
+/// use tlv::{Deserialize, RawField, Serialize};
+///
+/// #[derive(Serialize, Deserialize)]
+/// #[tlv(mode = "stream")]
+/// struct Settings {
+///    #[tlv(tag = 1)]
+///    id: u32,
+///    #[tlv(unknown)]
+///    unknown: Vec<RawField>,
+/// }
+/// ```
+///
+/// In the stream, BOLT and BER modes, `#[tlv(deny_unknown_fields)]` on the
+/// type makes a record with an unknown tag an `ErrorKind::UnexpectedTag`
+/// error instead of skipping it, as in the exact and DER modes. Decoding
+/// stops at the tag of that record, without reading its value. It is a
+/// compile error in those modes and on enums, where it would have no effect.
+///
+/// An enum reads a single record and picks the variant by its tag. A record
+/// with a tag no variant declares is an `ErrorKind::UnexpectedTag` error,
+/// unless the enum has an `#[tlv(other)]` variant.
+///
+/// With the `tokio` or `futures` feature of `tlv` enabled, `AsyncDeserialize`
+/// is derived too. It reads the records of the value asynchronously and
+/// decodes each of them as `Deserialize` would.
+///
+/// The generated code is wrapped like that of the `Serialize` derive. It
+/// decodes within `tlv::DecodeLimits`: the default ones through
+/// `deserialize`, the ones of the enclosing value through
+/// `deserialize_limited`, whose `limiter` is passed on to the decoding of
+/// every field. It will look like this:
 ///
 /// ```
 /// struct MyStruct {
@@ -105,28 +324,46 @@ pub fn tlv_serialize_derive(input: TokenStream) -> TokenStream {
 ///   field2: String,
 /// }
 ///
-/// impl tlv::Deserialize for MyStruct {
-///   fn deserialize<R: std::io::Read>(reader: &mut R) -> std::io::Result<Self> {
-///     const EXPECTED_IDS: [u8; 2] = [1, 2];
+/// const _: () = {
+///   use ::tlv as __tlv;
 ///
-///     let fields = ::tlv::extract_raw(reader, EXPECTED_IDS)?;
+///   impl __tlv::Deserialize for MyStruct {
+///     fn deserialize<R: __tlv::io::Read>(reader: &mut R) -> __tlv::Result<Self> {
+///       __tlv::deserialize_with_limits(reader, __tlv::DecodeLimits::DEFAULT)
+///     }
 ///
-///     let field1 = fields
-///         .get(&1)
-///         .map(|field| ::tlv::Deserialize::deserialize(&mut std::io::Cursor::new(field.value.as_slice())))
-///         .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "missing field1"))??;
+///     fn deserialize_limited<R: __tlv::io::Read>(
+///       reader: &mut R,
+///       limiter: &mut __tlv::Limiter,
+///     ) -> __tlv::Result<Self> {
+///       const LAYOUT: __tlv::Layout = __tlv::Layout::DEFAULT;
+///       let layout = LAYOUT.with_byte_order(limiter.byte_order());
 ///
-///     let field2 = fields
-///         .get(&2)
-///         .map(|field| ::tlv::Deserialize::deserialize(&mut std::io::Cursor::new(field.value.as_slice())))
-///         .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "missing field2"))??;
+///       const EXPECTED_TAGS: [u64; 2] = [1, 2];
 ///
-///    Ok(Self {
-///     field1,
-///     field2,
-///    })
+///       let fields = __tlv::extract_raw(reader, &layout, EXPECTED_TAGS)?;
+///
+///       let field1: u8 = match fields.get(&1) {
+///         Some(field) => field.decode_limited::<u8>("field1", limiter)?,
+///         None => {
+///           return Err(__tlv::Error::new(__tlv::ErrorKind::MissingTag)
+///             .with_tag(1)
+///             .with_field("field1"))
+///         }
+///       };
+///       let field2: String = match fields.get(&2) {
+///         Some(field) => field.decode_limited::<String>("field2", limiter)?,
+///         None => {
+///           return Err(__tlv::Error::new(__tlv::ErrorKind::MissingTag)
+///             .with_tag(2)
+///             .with_field("field2"))
+///         }
+///       };
+///
+///       Ok(Self { field1, field2 })
+///     }
 ///   }
-/// }
+/// };
 /// ```
 #[proc_macro_derive(Deserialize, attributes(tlv))]
 pub fn tlv_deserialize_derive(input: TokenStream) -> TokenStream {
@@ -138,3 +375,41 @@ pub fn tlv_deserialize_derive(input: TokenStream) -> TokenStream {
         Err(err) => err.to_compile_error().into(),
     }
 }
+
+/// Derive macro for the `BorrowDeserialize` trait.
+///
+/// Takes the same attributes and reads the same encoding as the
+/// `Deserialize` derive, but decodes from a `&'de [u8]`, so `&'de [u8]`,
+/// `&'de str` and `Cow<'de, _>` fields point into the input instead of being
+/// copied out of it. The type may have at most one lifetime, which is the
+/// lifetime of the input. Fields of other derived types have to implement
+/// `BorrowDeserialize` as well. The `#[tlv(other)]` variant of an enum may
+/// hold a `tlv::BorrowedField<'de>` instead of a `tlv::RawField`.
+///
+/// # Example
+///
+/// ```
+/// use tlv::BorrowDeserialize;
+///
+/// #[derive(BorrowDeserialize)]
+/// struct Packet<'a> {
+///   #[tlv(tag = 1)]
+///   id: u32,
+///   #[tlv(tag = 2)]
+///   payload: &'a [u8],
+/// }
+///
+/// let buf = [1, 4, 0, 7, 0, 0, 0, 2, 5, 0, 1, 0, 0, 0, 0xaa];
+/// let packet = Packet::borrow_deserialize(&mut buf.as_slice()).unwrap();
+/// assert_eq!(packet.payload, [0xaa]);
+/// ```
+#[proc_macro_derive(BorrowDeserialize, attributes(tlv))]
+pub fn tlv_borrow_deserialize_derive(input: TokenStream) -> TokenStream {
+    // Parse the input tokens into a syntax tree
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match der::tlv_borrow_deserialize_derive_impl(input) {
+        Ok(tokens) => tokens.into(),
+        Err(err) => err.to_compile_error().into(),
+    }
+}
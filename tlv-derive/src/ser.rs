@@ -2,91 +2,739 @@ use proc_macro2::TokenStream;
 use quote::quote;
 use syn::{punctuated::Punctuated, DeriveInput};
 
-use crate::{attributes::parse_tlv_fields_attributes, utils::ty_is_vec_u8};
+use crate::{
+    attributes::{
+        parse_der_fields_attributes, parse_tlv_container_attributes, parse_tlv_fields_attributes,
+        parse_tlv_variants_attributes, parse_unknown_field, Mode, Order, TlvContainerAttributes,
+        TlvFieldAttributes,
+    },
+    utils::{
+        field_member, fields_are_transparent, impl_async_header, impl_header, import_crate,
+        member_name, ty_is_vec_u8, ty_option_inner,
+    },
+};
 
 pub(crate) fn tlv_serialize_derive_impl(input: DeriveInput) -> Result<TokenStream, syn::Error> {
     // Get the name of the struct
-    let struct_name = &input.ident;
+    let container = parse_tlv_container_attributes(&input)?;
+    let layout = container.layout();
+    let header = impl_header(&input, &container, quote! { __tlv::Serialize }, None);
 
+    let gen = impl_serialize(&input, &header, &container)?;
+    let async_header = impl_async_header(&input, &container, quote! { __tlv::AsyncSerialize });
+    let async_gen = impl_async_serialize(&input, &async_header, &container, layout)?;
+    let tagged = match container.mode {
+        Mode::Der => impl_der_tagged(&input),
+        _ => quote! {},
+    };
+
+    // the asynchronous impl only exists when `tlv` has an async runtime
+    Ok(import_crate(
+        &container,
+        quote! {
+            #gen
+            #tagged
+            __tlv::__async! { #async_gen }
+        },
+    ))
+}
+
+/// A DER struct is a `SEQUENCE`, and a newtype has the tag of the value it
+/// wraps.
+fn impl_der_tagged(input: &DeriveInput) -> TokenStream {
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let tag = match &input.data {
+        syn::Data::Struct(syn::DataStruct {
+            fields: syn::Fields::Unnamed(fields),
+            ..
+        }) if fields_are_transparent(fields) => {
+            let ty = &fields.unnamed[0].ty;
+            quote! { <#ty as __tlv::der::Tagged>::TAG }
+        }
+        _ => quote! { 0x30 },
+    };
+
+    quote! {
+        #[automatically_derived]
+        impl #impl_generics __tlv::der::Tagged for #name #ty_generics #where_clause {
+            const TAG: u64 = #tag;
+        }
+    }
+}
+
+fn impl_serialize(
+    input: &DeriveInput,
+    header: &TokenStream,
+    container: &TlvContainerAttributes,
+) -> Result<TokenStream, syn::Error> {
     // Get the fields of the struct
     match input.data {
         syn::Data::Struct(ref data) => match data.fields {
-            syn::Fields::Named(ref fields) => impl_for_struct(struct_name, &fields.named),
-            _ => unimplemented!(),
+            syn::Fields::Named(ref fields) => impl_for_struct(header, container, &fields.named),
+            syn::Fields::Unnamed(ref fields) if fields_are_transparent(fields) => {
+                Ok(impl_for_newtype(header, container))
+            }
+            syn::Fields::Unnamed(ref fields) => impl_for_struct(header, container, &fields.unnamed),
+            syn::Fields::Unit => Ok(impl_for_unit(header)),
         },
-        _ => unimplemented!(),
+        syn::Data::Enum(_) if container.mode == Mode::Der => Err(syn::Error::new_spanned(
+            input,
+            "mode = \"der\" is only supported on structs",
+        )),
+        syn::Data::Enum(ref data) => impl_for_enum(header, container, data),
+        syn::Data::Union(_) => Err(syn::Error::new_spanned(
+            input,
+            "Serialize cannot be derived for unions",
+        )),
     }
 }
 
-fn impl_for_struct(
-    struct_name: &syn::Ident,
-    fields: &Punctuated<syn::Field, syn::Token![,]>,
+/// Structs made of records write one record at a time, everything else is
+/// small enough to be encoded in memory by the default implementation.
+fn impl_async_serialize(
+    input: &DeriveInput,
+    header: &TokenStream,
+    container: &TlvContainerAttributes,
+    layout: TokenStream,
 ) -> Result<TokenStream, syn::Error> {
-    let attributes = parse_tlv_fields_attributes(fields)?;
+    let fields = match &input.data {
+        syn::Data::Struct(data) => match &data.fields {
+            syn::Fields::Named(fields) => &fields.named,
+            syn::Fields::Unnamed(fields) if !fields_are_transparent(fields) => &fields.unnamed,
+            _ => return Ok(quote! { #header {} }),
+        },
+        _ => return Ok(quote! { #header {} }),
+    };
 
-    let field_serialize_function = fields
+    let accessors = fields
         .iter()
-        .zip(attributes.iter())
-        .map(|(field, attributes)| {
-            let serializer = create_serializer_for_field(field);
+        .enumerate()
+        .map(|(index, field)| {
+            let member = field_member(index, field);
+            quote! { self.#member }
+        })
+        .collect::<Vec<_>>();
+    let field_serialize_function = create_serializers_for_fields(container, fields, &accessors)?;
 
-            let id = &attributes.tag;
-            let serialize_type = quote! {
-                len += ::tlv::Serialize::serialize(&(#id as u16), writer)?;
-            };
+    Ok(quote! {
+        #[automatically_derived]
+        #header {
+            fn serialize_async<__W>(
+                &self,
+                sink: &mut __W,
+            ) -> impl ::std::future::Future<Output = __tlv::Result<usize>> + ::std::marker::Send
+            where
+                __W: __tlv::AsyncSink + ?Sized
+            {
+                async move {
+                    const LAYOUT: __tlv::Layout = #layout;
+                    let layout = LAYOUT;
 
-            Ok(quote! {
-                // Serialize type
-                #serialize_type
-                // Serialize value and length
-                #serializer
-            })
+                    let mut len = 0;
+                    let mut buf = ::std::vec::Vec::new();
+
+                    #(
+                        {
+                            let start = len;
+                            let writer = &mut buf;
+                            #field_serialize_function
+                            sink.write_all(&buf).await.map_err(|err| err.with_offset(start as u64))?;
+                            buf.clear();
+                        }
+                    )*
+
+                    Ok(len)
+                }
+            }
+        }
+    })
+}
+
+fn impl_for_struct(
+    header: &TokenStream,
+    container: &TlvContainerAttributes,
+    fields: &Punctuated<syn::Field, syn::Token![,]>,
+) -> Result<TokenStream, syn::Error> {
+    let layout = container.layout();
+    let byte_order = container.config_byte_order();
+    let accessors = fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| {
+            let member = field_member(index, field);
+            quote! { self.#member }
         })
-        .collect::<Result<Vec<_>, syn::Error>>()?;
+        .collect::<Vec<_>>();
+
+    let field_serialize_function = create_serializers_for_fields(container, fields, &accessors)?;
+    let field_length = create_lengths_for_fields(container, fields, &accessors)?;
 
     // Generate the code for the implementation
     let gen = quote! {
         #[automatically_derived]
-        impl ::tlv::Serialize for #struct_name {
-            fn serialize<W>(&self, writer: &mut W) -> ::std::io::Result<usize>
+        #header {
+            fn serialize<__W>(&self, writer: &mut __W) -> __tlv::Result<usize>
+            where
+                __W: __tlv::io::Write
+            {
+                self.serialize_with(writer, &__tlv::Config::DEFAULT)
+            }
+
+            fn serialize_with<__W>(
+                &self,
+                writer: &mut __W,
+                config: &__tlv::Config,
+            ) -> __tlv::Result<usize>
             where
-                W: ::std::io::Write
+                __W: __tlv::io::Write
             {
+                const LAYOUT: __tlv::Layout = #layout;
+                #byte_order
+                let layout = LAYOUT.with_byte_order(config.byte_order);
+
                 let mut len = 0;
 
                 #(#field_serialize_function)*
 
                 Ok(len)
             }
+
+            fn serialized_length(&self) -> usize {
+                const LAYOUT: __tlv::Layout = #layout;
+
+                0usize #(.saturating_add(#field_length))*
+            }
         }
     };
 
     // Return the generated implementation
-    Ok(gen.into())
+    Ok(gen)
 }
 
-fn create_serializer_for_field(field: &syn::Field) -> TokenStream {
-    let ty = field.ty.to_owned();
-    let name = field.ident.as_ref().unwrap();
+/// A newtype is encoded exactly as the value it wraps.
+fn impl_for_newtype(header: &TokenStream, container: &TlvContainerAttributes) -> TokenStream {
+    let byte_order = container.config_byte_order();
+
+    quote! {
+        #[automatically_derived]
+        #header {
+            fn serialize<__W>(&self, writer: &mut __W) -> __tlv::Result<usize>
+            where
+                __W: __tlv::io::Write
+            {
+                self.serialize_with(writer, &__tlv::Config::DEFAULT)
+            }
+
+            fn serialize_with<__W>(
+                &self,
+                writer: &mut __W,
+                config: &__tlv::Config,
+            ) -> __tlv::Result<usize>
+            where
+                __W: __tlv::io::Write
+            {
+                #byte_order
+                __tlv::Serialize::serialize_with(&self.0, writer, config)
+            }
+
+            fn serialized_length(&self) -> usize {
+                __tlv::Serialize::serialized_length(&self.0)
+            }
+        }
+    }
+}
 
-    // Check if the field is a Vec<u8> and use the specialized function
-    // for serializing bytes. Otherwise, use the generic Serialize trait
-    let is_bytes = ty_is_vec_u8(&ty);
+/// A unit struct has no value, it is encoded as zero bytes.
+fn impl_for_unit(header: &TokenStream) -> TokenStream {
+    quote! {
+        #[automatically_derived]
+        #header {
+            fn serialize<__W>(&self, _writer: &mut __W) -> __tlv::Result<usize>
+            where
+                __W: __tlv::io::Write
+            {
+                Ok(0)
+            }
 
-    if is_bytes {
-        // `serialize_bytes` encodes the length of the vec of bytes as well
+            fn serialized_length(&self) -> usize {
+                0
+            }
+        }
+    }
+}
+
+/// Every variant of an enum is encoded as a single record, tagged with the
+/// tag of the variant.
+fn impl_for_enum(
+    header: &TokenStream,
+    container: &TlvContainerAttributes,
+    data: &syn::DataEnum,
+) -> Result<TokenStream, syn::Error> {
+    let layout = container.layout();
+    let byte_order = container.config_byte_order();
+    let mut serialize_arms = Vec::new();
+    let mut length_arms = Vec::new();
+
+    let attributes = parse_tlv_variants_attributes(container, &data.variants)?;
+    for (variant, attributes) in data.variants.iter().zip(attributes) {
+        let ident = &variant.ident;
+        let ident_str = ident.to_string();
+
+        let Some(tag) = attributes.tag else {
+            // the catch-all variant holds the record it was decoded from
+            serialize_arms.push(quote! {
+                Self::#ident(field) => layout.write_raw(writer, field.id, &field.value),
+            });
+            length_arms.push(quote! {
+                Self::#ident(field) => LAYOUT.record_length(field.id, field.value.len()),
+            });
+            continue;
+        };
+
+        let (serialize, length) = match &variant.fields {
+            syn::Fields::Unit => (
+                quote! {
+                    Self::#ident => layout
+                        .write_record(writer, #tag, &[0u8; 0])
+                        .map_err(|err| err.within(#tag, #ident_str, 0)),
+                },
+                quote! {
+                    Self::#ident => LAYOUT.record_length(#tag, 0),
+                },
+            ),
+            syn::Fields::Unnamed(fields) if fields.unnamed.len() == 1 => (
+                quote! {
+                    Self::#ident(value) => layout
+                        .write_record(writer, #tag, value)
+                        .map_err(|err| err.within(#tag, #ident_str, 0)),
+                },
+                quote! {
+                    Self::#ident(value) => {
+                        LAYOUT.record_length(#tag, __tlv::Serialize::serialized_length(value))
+                    }
+                },
+            ),
+            syn::Fields::Named(fields) => {
+                let names = fields.named.iter().map(|field| &field.ident);
+                let names = quote! { #(#names),* };
+                let accessors = fields
+                    .named
+                    .iter()
+                    .map(|field| {
+                        let name = &field.ident;
+                        quote! { (*#name) }
+                    })
+                    .collect::<Vec<_>>();
+
+                let field_serialize_function =
+                    create_serializers_for_fields(container, &fields.named, &accessors)?;
+                let field_length = create_lengths_for_fields(container, &fields.named, &accessors)?;
+
+                (
+                    quote! {
+                        Self::#ident { #names } => {
+                            let length = 0usize #(.saturating_add(#field_length))*;
+
+                            let mut len = layout
+                                .write_tag(writer, #tag)
+                                .map_err(|err| err.within(#tag, #ident_str, 0))?;
+                            len += layout
+                                .write_length(writer, length)
+                                .map_err(|err| err.within(#tag, #ident_str, 0))?;
+                            let header = len;
+
+                            #(#field_serialize_function)*
+
+                            if len - header != length {
+                                return Err(__tlv::Error::new(__tlv::ErrorKind::LengthMismatch)
+                                    .with_tag(#tag)
+                                    .with_field(#ident_str)
+                                    .with_offset(header as u64));
+                            }
+
+                            Ok(len)
+                        }
+                    },
+                    quote! {
+                        Self::#ident { #names } => {
+                            LAYOUT.record_length(#tag, 0usize #(.saturating_add(#field_length))*)
+                        }
+                    },
+                )
+            }
+            syn::Fields::Unnamed(fields) => {
+                return Err(syn::Error::new_spanned(
+                    fields,
+                    "Variants with more than one unnamed field are not supported",
+                ))
+            }
+        };
+
+        serialize_arms.push(serialize);
+        length_arms.push(length);
+    }
+
+    let gen = quote! {
+        #[automatically_derived]
+        #header {
+            fn serialize<__W>(&self, writer: &mut __W) -> __tlv::Result<usize>
+            where
+                __W: __tlv::io::Write
+            {
+                self.serialize_with(writer, &__tlv::Config::DEFAULT)
+            }
+
+            fn serialize_with<__W>(
+                &self,
+                writer: &mut __W,
+                config: &__tlv::Config,
+            ) -> __tlv::Result<usize>
+            where
+                __W: __tlv::io::Write
+            {
+                const LAYOUT: __tlv::Layout = #layout;
+                #byte_order
+                let layout = LAYOUT.with_byte_order(config.byte_order);
+
+                match self {
+                    #(#serialize_arms)*
+                }
+            }
+
+            fn serialized_length(&self) -> usize {
+                const LAYOUT: __tlv::Layout = #layout;
+
+                match self {
+                    #(#length_arms)*
+                }
+            }
+        }
+    };
+
+    Ok(gen)
+}
+
+/// Creates the code serializing each of `fields` as a record, `accessors`
+/// are the places holding the values of the fields.
+fn create_serializers_for_fields(
+    container: &TlvContainerAttributes,
+    fields: &Punctuated<syn::Field, syn::Token![,]>,
+    accessors: &[TokenStream],
+) -> Result<Vec<TokenStream>, syn::Error> {
+    let unknown = parse_unknown_field(container, fields)?
+        .map(|(index, field)| (member_name(&field_member(index, field)), &accessors[index]));
+
+    if container.mode == Mode::Der {
+        return create_der_serializers_for_fields(fields, accessors);
+    }
+
+    let ordered = ordered_fields(container, fields, accessors)?;
+    let mut serializers = Vec::new();
+
+    // in a BOLT #1 stream the unknown records go between the known ones
+    // whose tags surround theirs, in the order of their tags
+    if let (Some((unknown, access)), Mode::Bolt) = (&unknown, &container.mode) {
+        let records = quote! { __tlv::bolt::sorted_records(&#access).into_iter() };
+        let mut previous = None;
+        for record in &ordered {
+            let id = &record.tag;
+            let range = match &previous {
+                Some(previous) => quote! { #previous < id && id < #id },
+                None => quote! { id < #id },
+            };
+            serializers.push(create_unknown_serializer(
+                unknown,
+                records.clone(),
+                Some(range),
+            ));
+            serializers.push(create_serializer_for_field(container, record));
+            previous = Some(id);
+        }
+
+        let range = previous.map(|previous| quote! { #previous < id });
+        serializers.push(create_unknown_serializer(unknown, records, range));
+        return Ok(serializers);
+    }
+
+    serializers.extend(
+        ordered
+            .iter()
+            .map(|record| create_serializer_for_field(container, record)),
+    );
+
+    // the unknown records come last, but for those that have the tag of a
+    // known field, which would be read as that field
+    if let Some((unknown, access)) = &unknown {
+        let tags = ordered.iter().map(|record| &record.tag);
+        let range = (!ordered.is_empty()).then(|| quote! { ![#(#tags),*].contains(&id) });
+        serializers.push(create_unknown_serializer(
+            unknown,
+            quote! { __tlv::UnknownRecords::records(&#access) },
+            range,
+        ));
+    }
+
+    Ok(serializers)
+}
+
+/// Writes back the `records`, an iterator over the tags and values of the
+/// `#[tlv(unknown)]` field, whose tags are in `range`, a condition on `id`,
+/// or all of them.
+fn create_unknown_serializer(
+    name_str: &str,
+    records: TokenStream,
+    range: Option<TokenStream>,
+) -> TokenStream {
+    let filter = range.map(|range| quote! { .filter(|&(id, _)| #range) });
+
+    quote! {
+        for (id, value) in #records #filter {
+            len += layout
+                .write_raw(writer, id, value)
+                .map_err(|err| err.within(id, #name_str, len as u64))?;
+        }
+    }
+}
+
+/// A field written as a record.
+struct Record<'a> {
+    name: String,
+    field: &'a syn::Field,
+    tag: syn::LitInt,
+    attributes: TlvFieldAttributes,
+    /// The place holding the value of the field.
+    access: &'a TokenStream,
+}
+
+/// The fields written as records, in the order they are written: the order
+/// of declaration, or of tags with `order = "tag"` and in a BOLT #1 stream.
+/// Skipped fields have no record.
+fn ordered_fields<'a>(
+    container: &TlvContainerAttributes,
+    fields: &'a Punctuated<syn::Field, syn::Token![,]>,
+    accessors: &'a [TokenStream],
+) -> Result<Vec<Record<'a>>, syn::Error> {
+    let mut ordered = parse_tlv_fields_attributes(container, fields)?
+        .into_iter()
+        .filter_map(|(index, field, attributes)| {
+            Some(Record {
+                name: member_name(&field_member(index, field)),
+                field,
+                tag: attributes.tag.clone()?,
+                attributes,
+                access: &accessors[index],
+            })
+        })
+        .collect::<Vec<_>>();
+
+    if container.order == Order::Tag {
+        let mut tags = Vec::with_capacity(ordered.len());
+        for record in &ordered {
+            tags.push(record.tag.base10_parse::<u64>()?);
+        }
+        let mut keyed = tags.into_iter().zip(ordered).collect::<Vec<_>>();
+        keyed.sort_by_key(|(tag, _)| *tag);
+        ordered = keyed.into_iter().map(|(_, record)| record).collect();
+    }
+
+    Ok(ordered)
+}
+
+/// Creates the code writing the record of a field.
+///
+/// `Vec<u8>` is written in one go, as a `&[u8]`, and a `with` module
+/// replaces the `Serialize` impl of the field. In the profiles a `None` has
+/// no record, or fails to encode if the field is `required`, a `Some` has
+/// the record of its value, and byte strings are the whole value of their
+/// record.
+fn create_serializer_for_field(container: &TlvContainerAttributes, record: &Record) -> TokenStream {
+    let Record {
+        name: name_str,
+        field,
+        tag: id,
+        attributes,
+        access,
+    } = record;
+    let profile = container.mode.is_profile();
+
+    let write = |ty: &syn::Type, value: TokenStream| {
+        let write = match &attributes.with {
+            Some(with) => quote! {
+                layout.write_record_with(
+                    writer,
+                    #id,
+                    #with::serialized_length(#value),
+                    |writer, config| #with::serialize(#value, writer, config),
+                )
+            },
+            None if ty_is_vec_u8(ty) && profile => quote! { layout.write_raw(writer, #id, #value) },
+            None if ty_is_vec_u8(ty) => {
+                quote! { layout.write_record(writer, #id, &(#value).as_slice()) }
+            }
+            None => quote! { layout.write_record(writer, #id, #value) },
+        };
         quote! {
-            len += ::tlv::serialize_bytes(&self.#name, writer)?;
+            len += #write.map_err(|err| err.within(#id, #name_str, len as u64))?;
         }
-    } else {
+    };
+
+    match ty_option_inner(&field.ty) {
+        Some(inner) if profile => {
+            let write = write(inner, quote! { value });
+            let missing = attributes.required.then(|| {
+                quote! {
+                    else {
+                        return Err(__tlv::Error::new(__tlv::ErrorKind::MissingTag)
+                            .with_tag(#id)
+                            .with_field(#name_str));
+                    }
+                }
+            });
+            quote! {
+                if let Some(value) = &#access {
+                    #write
+                } #missing
+            }
+        }
+        _ => write(&field.ty, quote! { &#access }),
+    }
+}
+
+/// In DER the records of the fields are written in order, tagged by their
+/// types or by their attributes, and a `None` has no record.
+fn create_der_serializers_for_fields(
+    fields: &Punctuated<syn::Field, syn::Token![,]>,
+    accessors: &[TokenStream],
+) -> Result<Vec<TokenStream>, syn::Error> {
+    let attributes = parse_der_fields_attributes(fields)?;
+
+    Ok(fields
+        .iter()
+        .enumerate()
+        .zip(attributes)
+        .zip(accessors)
+        .map(|(((index, field), attributes), access)| {
+            let name_str = member_name(&field_member(index, field));
+            let write = |ty: &syn::Type, value: TokenStream| {
+                let id = attributes.tag(ty);
+                let write = match attributes.is_explicit() {
+                    true => quote! { __tlv::der::serialize_explicit(writer, #id, #value) },
+                    false => quote! { layout.write_record(writer, #id, #value) },
+                };
+                quote! {
+                    len += #write.map_err(|err| err.within(#id, #name_str, len as u64))?;
+                }
+            };
+
+            match ty_option_inner(&field.ty) {
+                Some(inner) => {
+                    let write = write(inner, quote! { value });
+                    quote! {
+                        if let Some(value) = &#access {
+                            #write
+                        }
+                    }
+                }
+                None => write(&field.ty, quote! { &#access }),
+            }
+        })
+        .collect())
+}
+
+fn create_der_lengths_for_fields(
+    fields: &Punctuated<syn::Field, syn::Token![,]>,
+    accessors: &[TokenStream],
+) -> Result<Vec<TokenStream>, syn::Error> {
+    let attributes = parse_der_fields_attributes(fields)?;
+
+    Ok(fields
+        .iter()
+        .zip(attributes)
+        .zip(accessors)
+        .map(|((field, attributes), access)| {
+            let length = |ty: &syn::Type, value: TokenStream| {
+                let id = attributes.tag(ty);
+                match attributes.is_explicit() {
+                    true => quote! { __tlv::der::explicit_length(#id, #value) },
+                    false => quote! {
+                        LAYOUT.record_length(#id, __tlv::Serialize::serialized_length(#value))
+                    },
+                }
+            };
+
+            match ty_option_inner(&field.ty) {
+                Some(inner) => {
+                    let length = length(inner, quote! { value });
+                    quote! { #access.as_ref().map_or(0, |value| #length) }
+                }
+                None => length(&field.ty, quote! { &#access }),
+            }
+        })
+        .collect())
+}
+
+fn create_lengths_for_fields(
+    container: &TlvContainerAttributes,
+    fields: &Punctuated<syn::Field, syn::Token![,]>,
+    accessors: &[TokenStream],
+) -> Result<Vec<TokenStream>, syn::Error> {
+    if container.mode == Mode::Der {
+        return create_der_lengths_for_fields(fields, accessors);
+    }
+
+    let ordered = ordered_fields(container, fields, accessors)?;
+    let unknown = parse_unknown_field(container, fields)?.map(|(index, _)| {
+        // the unknown records with the tag of a known field are left out
+        let access = &accessors[index];
+        let filter = (!ordered.is_empty()).then(|| {
+            let tags = ordered.iter().map(|record| &record.tag);
+            quote! { .filter(|&(id, _)| ![#(#tags),*].contains(&id)) }
+        });
         quote! {
-            // serialize length
-            len += ::tlv::Serialize::serialize(
-                &::tlv::Serialize::serialized_length(&self.#name),
-                writer,
-            )?;
-            // serialize value
-            len += ::tlv::Serialize::serialize(&self.#name, writer)?;
+            __tlv::UnknownRecords::records(&#access)
+                #filter
+                .fold(0usize, |len, (id, value)| {
+                    len.saturating_add(LAYOUT.record_length(id, value.len()))
+                })
+        }
+    });
+
+    Ok(ordered
+        .iter()
+        .map(|record| create_length_for_field(container, record))
+        .chain(unknown)
+        .collect())
+}
+
+/// Creates the expression of the length of the record of a field, see
+/// [`create_serializer_for_field`].
+fn create_length_for_field(container: &TlvContainerAttributes, record: &Record) -> TokenStream {
+    let Record {
+        field,
+        tag: id,
+        attributes,
+        access,
+        ..
+    } = record;
+    let profile = container.mode.is_profile();
+
+    let length = |ty: &syn::Type, value: TokenStream| match &attributes.with {
+        Some(with) => quote! { LAYOUT.record_length(#id, #with::serialized_length(#value)) },
+        None if ty_is_vec_u8(ty) && profile => quote! { LAYOUT.record_length(#id, (#value).len()) },
+        None if ty_is_vec_u8(ty) => quote! {
+            LAYOUT.record_length(#id, __tlv::serialized_bytes_length(#value))
+        },
+        None => quote! {
+            LAYOUT.record_length(#id, __tlv::Serialize::serialized_length(#value))
+        },
+    };
+
+    match ty_option_inner(&field.ty) {
+        Some(inner) if profile => {
+            let length = length(inner, quote! { value });
+            quote! { #access.as_ref().map_or(0, |value| #length) }
         }
+        _ => length(&field.ty, quote! { &#access }),
     }
 }
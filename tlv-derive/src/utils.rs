@@ -1,15 +1,19 @@
+use proc_macro2::TokenStream;
+use quote::quote;
 use syn::{GenericArgument, Type, TypePath};
 
+use crate::attributes::TlvContainerAttributes;
+
 pub(crate) fn ty_is_vec_u8(ty: &Type) -> bool {
     let Type::Path(syn::TypePath { path, .. }) = ty else {
         return false;
     };
 
-    let Some(segment) = path.segments.first() else {
+    let Some(segment) = path.segments.last() else {
         return false;
     };
 
-    if segment.ident.to_string() != "Vec" {
+    if segment.ident != "Vec" {
         return false;
     }
 
@@ -32,5 +36,146 @@ pub(crate) fn ty_is_option(ty: &Type) -> bool {
     let segment = path.segments.last().unwrap();
     let ident = &segment.ident;
 
-    ident.to_string() == "Option"
+    ident == "Option"
+}
+
+/// The `T` of an `Option<T>`.
+pub(crate) fn ty_option_inner(ty: &Type) -> Option<&Type> {
+    let Type::Path(syn::TypePath { path, .. }) = ty else {
+        return None;
+    };
+
+    let segment = path.segments.last()?;
+    let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+        return None;
+    };
+
+    match (segment.ident == "Option", args.args.first()) {
+        (true, Some(GenericArgument::Type(inner))) => Some(inner),
+        _ => None,
+    }
+}
+
+/// How the field at `index` is accessed: by its name, or by its position in
+/// a tuple struct.
+pub(crate) fn field_member(index: usize, field: &syn::Field) -> syn::Member {
+    match &field.ident {
+        Some(ident) => syn::Member::Named(ident.clone()),
+        None => syn::Member::Unnamed(index.into()),
+    }
+}
+
+/// Name of the field used in error messages, its position for tuple structs.
+pub(crate) fn member_name(member: &syn::Member) -> String {
+    match member {
+        syn::Member::Named(ident) => ident.to_string(),
+        syn::Member::Unnamed(index) => index.index.to_string(),
+    }
+}
+
+/// Name of the variable the decoded value of a field is bound to.
+pub(crate) fn member_binding(member: &syn::Member) -> proc_macro2::Ident {
+    match member {
+        syn::Member::Named(ident) => ident.clone(),
+        syn::Member::Unnamed(index) => quote::format_ident!("field_{}", index.index),
+    }
+}
+
+/// Whether a tuple struct is a newtype encoded as its only field, i.e. the
+/// field has no tag of its own.
+pub(crate) fn fields_are_transparent(fields: &syn::FieldsUnnamed) -> bool {
+    fields.unnamed.len() == 1
+        && !fields.unnamed[0]
+            .attrs
+            .iter()
+            .any(|attr| attr.path.is_ident("tlv"))
+}
+
+/// Wraps the generated impls in an anonymous constant importing the `tlv`
+/// crate as `__tlv`, which the generated code refers to it by. The crate is
+/// `::tlv` unless the container gives another path with
+/// `#[tlv(crate = "...")]`.
+pub(crate) fn import_crate(container: &TlvContainerAttributes, impls: TokenStream) -> TokenStream {
+    let krate = match &container.krate {
+        Some(path) => quote! { #path },
+        None => quote! { ::tlv },
+    };
+
+    quote! {
+        const _: () = {
+            use #krate as __tlv;
+
+            #impls
+        };
+    }
+}
+
+/// Creates `impl<..> #trait_path for Type<..> where ..`, carrying over the
+/// generics of the type. `extra_lifetime` is added to the generics of the
+/// impl only.
+///
+/// Every type parameter is bound by `trait_path`, unless the container
+/// gives its own bounds with `#[tlv(bound = "...")]`.
+pub(crate) fn impl_header(
+    input: &syn::DeriveInput,
+    container: &TlvContainerAttributes,
+    trait_path: TokenStream,
+    extra_lifetime: Option<&syn::Lifetime>,
+) -> TokenStream {
+    impl_header_with(input, container, trait_path, extra_lifetime, None)
+}
+
+/// Creates the header of an impl of `AsyncSerialize` or `AsyncDeserialize`.
+///
+/// Bounds given with `#[tlv(bound = "...")]` are written for the blocking
+/// traits, so the type itself is additionally required to be `Send + Sync`,
+/// which the futures of the asynchronous traits need.
+pub(crate) fn impl_async_header(
+    input: &syn::DeriveInput,
+    container: &TlvContainerAttributes,
+    trait_path: TokenStream,
+) -> TokenStream {
+    let extra = container.bound.as_ref().map(|_| {
+        syn::parse_quote! { Self: ::std::marker::Send + ::std::marker::Sync }
+    });
+    impl_header_with(input, container, trait_path, None, extra)
+}
+
+fn impl_header_with(
+    input: &syn::DeriveInput,
+    container: &TlvContainerAttributes,
+    trait_path: TokenStream,
+    extra_lifetime: Option<&syn::Lifetime>,
+    extra_predicate: Option<syn::WherePredicate>,
+) -> TokenStream {
+    let name = &input.ident;
+    let mut generics = input.generics.clone();
+    if let Some(lifetime) = extra_lifetime {
+        let param = syn::LifetimeDef::new(lifetime.clone());
+        generics
+            .params
+            .insert(0, syn::GenericParam::Lifetime(param));
+    }
+
+    let predicates = match &container.bound {
+        Some(bound) => bound.iter().cloned().collect(),
+        None => input
+            .generics
+            .type_params()
+            .map(|param| {
+                let ident = &param.ident;
+                syn::parse_quote! { #ident: #trait_path }
+            })
+            .collect::<Vec<syn::WherePredicate>>(),
+    };
+    let where_clause = generics.make_where_clause();
+    where_clause.predicates.extend(predicates);
+    where_clause.predicates.extend(extra_predicate);
+
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    quote! {
+        impl #impl_generics #trait_path for #name #ty_generics #where_clause
+    }
 }
@@ -0,0 +1,641 @@
+use tlv::{BorrowDeserialize, Deserialize, Serialize};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Default {
+    #[tlv(tag = 1)]
+    id: u32,
+    #[tlv(tag = 2)]
+    flag: bool,
+    #[tlv(tag = 3)]
+    extra: Option<u64>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(tag_encoding = "u16")]
+struct Wide {
+    #[tlv(tag = 1)]
+    id: u32,
+    #[tlv(tag = 300)]
+    flag: bool,
+}
+
+fn round_trip<T>(value: &T) -> Vec<u8>
+where
+    T: Serialize + Deserialize + PartialEq + std::fmt::Debug,
+{
+    let mut buf = Vec::new();
+    let written = value.serialize(&mut buf).unwrap();
+    assert_eq!(written, buf.len());
+    assert_eq!(value.serialized_length(), buf.len());
+    assert_eq!(&T::deserialize(&mut buf.as_slice()).unwrap(), value);
+    buf
+}
+
+#[test]
+fn test_default_tag_encoding() {
+    let buf = round_trip(&Default {
+        id: 42,
+        flag: true,
+        extra: None,
+    });
+
+    #[rustfmt::skip]
+    assert_eq!(buf, vec![
+        1, 4, 0, 42, 0, 0, 0,
+        2, 1, 0, 1,
+        3, 1, 0, 0,
+    ]);
+}
+
+#[test]
+fn test_wide_tag_encoding() {
+    let buf = round_trip(&Wide { id: 42, flag: true });
+
+    #[rustfmt::skip]
+    assert_eq!(buf, vec![
+        1, 0, 4, 0, 42, 0, 0, 0,
+        44, 1, 1, 0, 1,
+    ]);
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(mode = "stream")]
+struct Stream {
+    #[tlv(tag = 1)]
+    id: u32,
+    #[tlv(tag = 2)]
+    extra: Option<u64>,
+}
+
+#[test]
+fn test_stream_mode() {
+    round_trip(&Stream {
+        id: 42,
+        extra: Some(7),
+    });
+
+    #[rustfmt::skip]
+    let buf = [
+        9, 2, 0, 1, 2,
+        1, 4, 0, 42, 0, 0, 0,
+    ];
+
+    assert_eq!(
+        Stream::deserialize(&mut buf.as_slice()).unwrap(),
+        Stream {
+            id: 42,
+            extra: None
+        }
+    );
+}
+
+#[test]
+fn test_stream_mode_missing_field() {
+    let buf = [2, 1, 0, 0];
+
+    assert!(Stream::deserialize(&mut buf.as_slice()).is_err());
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Outer {
+    #[tlv(tag = 1)]
+    id: u8,
+    #[tlv(tag = 2)]
+    inner: Default,
+}
+
+#[test]
+fn test_error_context() {
+    let err = Stream::deserialize(&mut [2, 1, 0, 0].as_slice()).unwrap_err();
+    assert_eq!(err.kind(), tlv::ErrorKind::MissingTag);
+    assert_eq!(err.tag(), Some(1));
+    assert_eq!(err.field(), Some("id"));
+
+    #[rustfmt::skip]
+    let buf = [
+        1, 1, 0, 5,
+        2, 14, 0,
+            1, 4, 0, 42, 0, 0, 0,
+            2, 0, 0,
+            3, 1, 0, 0,
+    ];
+
+    let err = Outer::deserialize(&mut buf.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), tlv::ErrorKind::Truncated);
+    assert_eq!(err.tag(), Some(2));
+    assert_eq!(err.field(), Some("flag"));
+    assert_eq!(err.offset(), Some(17));
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(length_encoding = "u32")]
+struct Lengths {
+    #[tlv(tag = 1)]
+    name: String,
+    #[tlv(tag = 2)]
+    inner: Stream,
+    #[tlv(tag = 3)]
+    values: Vec<u32>,
+}
+
+#[test]
+fn test_length_encoding() {
+    let buf = round_trip(&Lengths {
+        name: "abc".to_string(),
+        inner: Stream { id: 1, extra: None },
+        values: vec![1, 2],
+    });
+
+    #[rustfmt::skip]
+    assert_eq!(buf[..12], [
+        1, 7, 0, 0, 0,
+            3, 0, 0, 0, b'a', b'b', b'c',
+    ]);
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(length_encoding = "u8")]
+struct Short {
+    #[tlv(tag = 1)]
+    values: Vec<u64>,
+}
+
+#[test]
+fn test_length_out_of_range() {
+    let value = Short {
+        values: vec![0; 32],
+    };
+    assert_eq!(value.serialized_length(), 2 + 4 + 32 * 8);
+
+    let err = value.serialize(&mut Vec::new()).unwrap_err();
+    assert_eq!(err.kind(), tlv::ErrorKind::OutOfRange);
+    assert_eq!(err.tag(), Some(1));
+    assert_eq!(err.field(), Some("values"));
+    assert_eq!(err.offset(), Some(0));
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(tag_encoding = "u16", byte_order = "big")]
+struct Network {
+    #[tlv(tag = 0x0102)]
+    port: u16,
+    #[tlv(tag = 2)]
+    names: Vec<String>,
+    #[tlv(tag = 3)]
+    inner: Default,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(byte_order = "big")]
+struct Port(u16);
+
+#[test]
+fn test_byte_order() {
+    let buf = round_trip(&Network {
+        port: 0x0304,
+        names: vec!["a".to_string()],
+        inner: Default {
+            id: 1,
+            flag: true,
+            extra: None,
+        },
+    });
+
+    // a nested container without a byte order of its own inherits it
+    #[rustfmt::skip]
+    assert_eq!(buf, vec![
+        1, 2, 0, 2, 3, 4,
+        0, 2, 0, 9, 0, 0, 0, 1, 0, 0, 0, 1, b'a',
+        0, 3, 0, 15,
+            1, 0, 4, 0, 0, 0, 1,
+            2, 0, 1, 1,
+            3, 0, 1, 0,
+    ]);
+
+    assert_eq!(round_trip(&Port(0x0102)), vec![1, 2]);
+
+    // the same value in another byte order is another value
+    let mut buf = Vec::new();
+    let config = tlv::Config::DEFAULT.with_byte_order(tlv::ByteOrder::Big);
+    tlv::serialize_with_config(&0x0102u16, &mut buf, &config).unwrap();
+    assert_eq!(
+        Port::deserialize(&mut buf.as_slice()).unwrap(),
+        Port(0x0102)
+    );
+    assert_eq!(u16::deserialize(&mut buf.as_slice()).unwrap(), 0x0201);
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(tag_encoding = "bigsize", length_encoding = "leb128")]
+struct Compact {
+    #[tlv(tag = 1)]
+    id: tlv::varint::Leb128,
+    #[tlv(tag = 300)]
+    payload: Vec<u8>,
+    #[tlv(tag = 2)]
+    delta: tlv::varint::ZigZag,
+}
+
+#[test]
+fn test_varint_encodings() {
+    let buf = round_trip(&Compact {
+        id: tlv::varint::Leb128(300),
+        payload: vec![0; 130],
+        delta: tlv::varint::ZigZag(-2),
+    });
+
+    assert_eq!(buf[..4], [1, 2, 0xac, 0x02]);
+    assert_eq!(buf[4..10], [0xfd, 0x01, 0x2c, 0x86, 0x01, 130]);
+    assert_eq!(buf[10 + 3 + 130..], [2, 1, 3]);
+
+    // a tag in a longer form than needed is rejected
+    let mut padded = buf.clone();
+    padded.splice(..1, [0xfd, 0x00, 0x01]);
+    let err = Compact::deserialize(&mut padded.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), tlv::ErrorKind::NonCanonical);
+    assert_eq!(err.offset(), Some(0));
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Command {
+    #[tlv(tag = 1)]
+    Ping,
+    #[tlv(tag = 2)]
+    Echo(String),
+    #[tlv(tag = 3)]
+    Nested(Default),
+    #[tlv(tag = 4)]
+    Move {
+        #[tlv(tag = 1)]
+        x: u16,
+        #[tlv(tag = 2)]
+        y: Option<u16>,
+    },
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(tag_encoding = "u16")]
+enum Open {
+    #[tlv(tag = 1)]
+    Known(u8),
+    #[tlv(other)]
+    Unknown(tlv::RawField),
+}
+
+#[test]
+fn test_enum() {
+    assert_eq!(round_trip(&Command::Ping), vec![1, 0, 0]);
+    assert_eq!(
+        round_trip(&Command::Echo("hi".to_string())),
+        vec![2, 6, 0, 2, 0, 0, 0, b'h', b'i']
+    );
+    round_trip(&Command::Nested(Default {
+        id: 1,
+        flag: false,
+        extra: Some(2),
+    }));
+
+    #[rustfmt::skip]
+    assert_eq!(
+        round_trip(&Command::Move { x: 5, y: None }),
+        vec![
+            4, 9, 0,
+                1, 2, 0, 5, 0,
+                2, 1, 0, 0,
+        ]
+    );
+}
+
+#[test]
+fn test_enum_unknown_variant() {
+    let err = Command::deserialize(&mut [9, 0, 0].as_slice()).unwrap_err();
+    assert_eq!(err.kind(), tlv::ErrorKind::UnexpectedTag);
+    assert_eq!(err.tag(), Some(9));
+    assert_eq!(err.offset(), Some(3));
+
+    let err = Command::deserialize(&mut [1, 1, 0, 0].as_slice()).unwrap_err();
+    assert_eq!(err.kind(), tlv::ErrorKind::LengthMismatch);
+    assert_eq!(err.field(), Some("Ping"));
+
+    round_trip(&Open::Known(7));
+    let buf = round_trip(&Open::Unknown(tlv::RawField {
+        offset: 4,
+        ..tlv::RawField::new(300, 2, vec![1, 2])
+    }));
+    assert_eq!(buf, vec![44, 1, 2, 0, 1, 2]);
+}
+
+#[test]
+fn test_enum_error_context() {
+    #[rustfmt::skip]
+    let buf = [
+        4, 8, 0,
+            1, 2, 0, 5, 0,
+            2, 0, 0,
+    ];
+
+    let err = Command::deserialize(&mut buf.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), tlv::ErrorKind::Truncated);
+    assert_eq!(err.field(), Some("y"));
+    assert_eq!(err.offset(), Some(11));
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct UserId(u64);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Point(#[tlv(tag = 1)] u16, #[tlv(tag = 2)] Option<u16>);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Tagged(#[tlv(tag = 7)] u8);
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Marker;
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Wrappers {
+    #[tlv(tag = 1)]
+    user: UserId,
+    #[tlv(tag = 2)]
+    marker: Marker,
+    #[tlv(tag = 3)]
+    point: Point,
+}
+
+#[test]
+fn test_newtype() {
+    assert_eq!(round_trip(&UserId(5)), vec![5, 0, 0, 0, 0, 0, 0, 0]);
+    assert_eq!(round_trip(&Tagged(5)), vec![7, 1, 0, 5]);
+}
+
+#[test]
+fn test_tuple_struct() {
+    #[rustfmt::skip]
+    assert_eq!(
+        round_trip(&Point(3, Some(4))),
+        vec![
+            1, 2, 0, 3, 0,
+            2, 3, 0, 1, 4, 0,
+        ]
+    );
+
+    let err = Point::deserialize(&mut [2, 1, 0, 0, 3, 0, 0].as_slice()).unwrap_err();
+    assert_eq!(err.kind(), tlv::ErrorKind::UnexpectedTag);
+
+    let err = Point::deserialize(&mut [1, 1, 0, 3, 2, 1, 0, 0].as_slice()).unwrap_err();
+    assert_eq!(err.kind(), tlv::ErrorKind::Truncated);
+    assert_eq!(err.field(), Some("0"));
+}
+
+#[test]
+fn test_unit_struct() {
+    assert_eq!(round_trip(&Marker), Vec::<u8>::new());
+
+    #[rustfmt::skip]
+    assert_eq!(
+        round_trip(&Wrappers {
+            user: UserId(1),
+            marker: Marker,
+            point: Point(2, None),
+        }),
+        vec![
+            1, 8, 0, 1, 0, 0, 0, 0, 0, 0, 0,
+            2, 0, 0,
+            3, 9, 0,
+                1, 2, 0, 2, 0,
+                2, 1, 0, 0,
+        ]
+    );
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Envelope<T> {
+    #[tlv(tag = 1)]
+    version: u8,
+    #[tlv(tag = 2)]
+    body: T,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+enum Either<L, R>
+where
+    L: std::fmt::Debug,
+{
+    #[tlv(tag = 1)]
+    Left(L),
+    #[tlv(tag = 2)]
+    Right(R),
+}
+
+#[derive(Serialize)]
+struct Borrowed<'a> {
+    #[tlv(tag = 1)]
+    name: &'a str,
+}
+
+trait Schema {
+    type Id;
+}
+
+#[derive(Debug, PartialEq)]
+struct Users;
+
+impl Schema for Users {
+    type Id = UserId;
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[tlv(bound = "S::Id: tlv::Serialize + tlv::Deserialize")]
+struct Key<S: Schema> {
+    #[tlv(tag = 1)]
+    id: S::Id,
+}
+
+#[test]
+fn test_generics() {
+    assert_eq!(
+        round_trip(&Envelope {
+            version: 1,
+            body: Point(2, None),
+        }),
+        vec![1, 1, 0, 1, 2, 9, 0, 1, 2, 0, 2, 0, 2, 1, 0, 0]
+    );
+    round_trip(&Envelope {
+        version: 1,
+        body: vec![1u16, 2],
+    });
+
+    round_trip(&Either::<u8, String>::Left(1));
+    round_trip(&Either::<u8, String>::Right("right".to_string()));
+
+    let mut buf = Vec::new();
+    Borrowed { name: "ab" }.serialize(&mut buf).unwrap();
+    assert_eq!(buf, vec![1, 6, 0, 2, 0, 0, 0, b'a', b'b']);
+
+    round_trip(&Key::<Users> { id: UserId(3) });
+}
+
+#[derive(Debug, PartialEq, Serialize, BorrowDeserialize)]
+struct Packet<'a> {
+    #[tlv(tag = 1)]
+    id: UserId,
+    #[tlv(tag = 2)]
+    payload: &'a [u8],
+    #[tlv(tag = 3)]
+    name: Option<std::borrow::Cow<'a, str>>,
+    #[tlv(tag = 4)]
+    kind: Kind<'a>,
+}
+
+#[derive(Debug, PartialEq, Serialize, BorrowDeserialize)]
+enum Kind<'a> {
+    #[tlv(tag = 1)]
+    Ping,
+    #[tlv(tag = 2)]
+    Text {
+        #[tlv(tag = 1)]
+        text: &'a str,
+    },
+    #[tlv(other)]
+    Other(tlv::BorrowedField<'a>),
+}
+
+#[derive(Debug, PartialEq, Serialize, BorrowDeserialize)]
+#[tlv(mode = "stream")]
+struct Counters {
+    #[tlv(tag = 1)]
+    sent: u32,
+    #[tlv(tag = 2)]
+    dropped: Option<u32>,
+}
+
+#[derive(Debug, PartialEq, Serialize, BorrowDeserialize)]
+struct Owned<T> {
+    #[tlv(tag = 1)]
+    values: Vec<T>,
+}
+
+impl<'de> BorrowDeserialize<'de> for UserId {
+    fn borrow_deserialize(input: &mut &'de [u8]) -> tlv::Result<Self> {
+        Self::deserialize(input)
+    }
+}
+
+fn encode<T: Serialize>(value: &T) -> Vec<u8> {
+    let mut buf = Vec::new();
+    value.serialize(&mut buf).unwrap();
+    buf
+}
+
+/// Decodes `buf`, the encoding of `value`, borrowing from it.
+fn borrow_check<'de, T>(value: &T, buf: &'de [u8]) -> T
+where
+    T: BorrowDeserialize<'de> + PartialEq + std::fmt::Debug,
+{
+    let mut input = buf;
+    let decoded = T::borrow_deserialize(&mut input).unwrap();
+    assert!(input.is_empty());
+    assert_eq!(&decoded, value);
+    decoded
+}
+
+#[test]
+fn test_borrow_deserialize() {
+    let packet = Packet {
+        id: UserId(1),
+        payload: &[1, 2, 3],
+        name: Some("name".into()),
+        kind: Kind::Text { text: "text" },
+    };
+
+    let buf = encode(&packet);
+    let decoded = borrow_check(&packet, &buf);
+    assert!(matches!(decoded.name, Some(std::borrow::Cow::Borrowed(_))));
+    assert!(buf.as_ptr_range().contains(&decoded.payload.as_ptr()));
+    let Kind::Text { text } = decoded.kind else {
+        panic!("{:?}", decoded.kind);
+    };
+    assert!(buf.as_ptr_range().contains(&text.as_ptr()));
+
+    borrow_check(&Kind::Ping, &encode(&Kind::Ping));
+
+    let counters = Counters {
+        sent: 3,
+        dropped: None,
+    };
+    borrow_check(&counters, &encode(&counters));
+
+    let owned = Owned {
+        values: vec!["a", "b"],
+    };
+    borrow_check(&owned, &encode(&owned));
+}
+
+#[test]
+fn test_borrow_deserialize_errors() {
+    let buf = [9, 1, 0, 0];
+    let kind = Kind::borrow_deserialize(&mut buf.as_slice()).unwrap();
+    assert_eq!(
+        kind,
+        Kind::Other(tlv::BorrowedField {
+            id: 9,
+            value: &[0],
+            offset: 3,
+        })
+    );
+
+    #[rustfmt::skip]
+    let buf = [
+        2, 9, 0,
+            1, 6, 0, 2, 0, 0, 0, 0xff, 0xfe,
+    ];
+    let err = Kind::borrow_deserialize(&mut buf.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), tlv::ErrorKind::InvalidUtf8);
+    assert_eq!(err.field(), Some("text"));
+    assert_eq!(err.offset(), Some(6));
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize, BorrowDeserialize)]
+struct Tree {
+    #[tlv(tag = 1)]
+    children: Vec<Tree>,
+}
+
+impl Tree {
+    fn deep(depth: usize) -> Self {
+        (0..depth).fold(Tree { children: vec![] }, |tree, _| Tree {
+            children: vec![tree],
+        })
+    }
+}
+
+#[test]
+fn test_limits() {
+    // every level is a record and a collection
+    let shallow = Tree::deep(10);
+    let buf = encode(&shallow);
+    assert_eq!(Tree::deserialize(&mut buf.as_slice()).unwrap(), shallow);
+    assert_eq!(
+        Tree::borrow_deserialize(&mut buf.as_slice()).unwrap(),
+        shallow
+    );
+
+    let buf = encode(&Tree::deep(40));
+    let err = Tree::deserialize(&mut buf.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), tlv::ErrorKind::LimitExceeded);
+    assert_eq!(err.field(), Some("children"));
+    let err = Tree::borrow_deserialize(&mut buf.as_slice()).unwrap_err();
+    assert_eq!(err.kind(), tlv::ErrorKind::LimitExceeded);
+
+    let limits = tlv::DecodeLimits::DEFAULT.with_max_depth(100);
+    let deep: Tree = tlv::deserialize_with_limits(&mut buf.as_slice(), limits).unwrap();
+    assert_eq!(deep, Tree::deep(40));
+
+    // reading stops once the total is reached, however long the record
+    use std::io::Read;
+    let limits = tlv::DecodeLimits::DEFAULT.with_max_total_bytes(16);
+    let mut endless = [1, 0xff, 0xff].chain(std::io::repeat(0));
+    let err = tlv::deserialize_with_limits::<Tree, _>(&mut endless, limits).unwrap_err();
+    assert_eq!(err.kind(), tlv::ErrorKind::LimitExceeded);
+    assert_eq!(err.offset(), Some(16));
+}
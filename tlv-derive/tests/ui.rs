@@ -0,0 +1,7 @@
+//! Types the derives reject at compile time, with the errors they report.
+
+#[test]
+fn test_compile_errors() {
+    let cases = trybuild::TestCases::new();
+    cases.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,17 @@
+use tlv::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+#[tlv(deny_unknown_fields)]
+struct Message {
+    #[tlv(tag = 1)]
+    id: u32,
+}
+
+#[derive(Serialize, Deserialize)]
+#[tlv(mode = "stream", deny_unknown_fields)]
+enum Command {
+    #[tlv(tag = 1)]
+    Ping,
+}
+
+fn main() {}
@@ -0,0 +1,9 @@
+use tlv::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+enum Command {
+    #[tlv(tag = 1, tag = 2)]
+    Ping,
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use tlv::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+#[tlv(tag_encoding = "u16")]
+#[tlv(mode = "stream", tag_encoding = "u32")]
+struct Message {
+    #[tlv(tag = 1)]
+    id: u32,
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+use tlv::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+struct Message {
+    #[tlv(tag = 1)]
+    id: u32,
+    #[tlv(tag = 0x01)]
+    name: String,
+}
+
+#[derive(Serialize, Deserialize)]
+enum Command {
+    #[tlv(tag = 2)]
+    Ping,
+    #[tlv(tag = 2)]
+    Pong,
+}
+
+fn main() {}
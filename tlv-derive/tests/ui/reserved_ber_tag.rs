@@ -0,0 +1,17 @@
+use tlv::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+#[tlv(mode = "ber")]
+struct Padded {
+    #[tlv(tag = 0x00)]
+    zero: u8,
+}
+
+#[derive(Serialize, Deserialize)]
+#[tlv(mode = "ber")]
+struct Malformed {
+    #[tlv(tag = 0x1f)]
+    value: u8,
+}
+
+fn main() {}
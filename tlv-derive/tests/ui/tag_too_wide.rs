@@ -0,0 +1,10 @@
+use tlv::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize)]
+#[tlv(tag_encoding = "u8")]
+struct Message {
+    #[tlv(tag = 256)]
+    id: u32,
+}
+
+fn main() {}